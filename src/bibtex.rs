@@ -0,0 +1,811 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::error::{Error, Result};
+use super::json_parser::{Creator, Item, Note, Tag, ZoteroData};
+
+// BibTeX/BibLaTeX entry type, Zotero item type. Anything else becomes a `document`.
+const ITEM_TYPES: &[(&str, &str)] = &[
+    ("article", "journalArticle"),
+    ("book", "book"),
+    ("mvbook", "book"),
+    ("booklet", "book"),
+    ("inbook", "bookSection"),
+    ("bookinbook", "bookSection"),
+    ("incollection", "bookSection"),
+    ("inproceedings", "conferencePaper"),
+    ("conference", "conferencePaper"),
+    ("online", "webpage"),
+    ("electronic", "webpage"),
+    ("www", "webpage"),
+    ("phdthesis", "thesis"),
+    ("mastersthesis", "thesis"),
+    ("thesis", "thesis"),
+    ("techreport", "report"),
+    ("report", "report"),
+    ("unpublished", "manuscript"),
+    ("manual", "document"),
+    ("misc", "document"),
+    ("software", "computerProgram"),
+    ("dataset", "dataset"),
+    ("patent", "patent"),
+    ("video", "videoRecording"),
+    ("audio", "audioRecording"),
+    ("jurisdiction", "case"),
+    ("legislation", "statute"),
+];
+
+// the macros every BibTeX style defines
+const MONTHS: &[&str] = &[
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+// Reads a `.bib` file into the same items a Better BibTeX export gives us. Text outside
+// entries, `@comment` and `@preamble` are ignored, `@string` macros are expanded.
+pub fn read(path: &Path, contents: &str) -> Result<ZoteroData> {
+    let mut parser = Parser {
+        path,
+        chars: contents.chars().collect(),
+        position: 0,
+        macros: MONTHS
+            .iter()
+            .enumerate()
+            .map(|(index, month)| (month.to_string(), (index + 1).to_string()))
+            .collect(),
+    };
+
+    let mut items = Vec::new();
+    while parser.skip_to('@') {
+        let entry_type = parser.identifier().to_lowercase();
+        parser.skip_whitespace();
+        let close = match parser.next() {
+            Some('{') => '}',
+            Some('(') => ')',
+            _ => continue,
+        };
+        match entry_type.as_str() {
+            "comment" | "preamble" => parser.skip_block(close)?,
+            "string" => {
+                let (name, value) = parser.field()?;
+                parser.macros.insert(name, value);
+                parser.skip_block(close)?;
+            }
+            _ => items.push(parser.entry(&entry_type, close)?),
+        }
+    }
+
+    Ok(ZoteroData {
+        collections: HashMap::new(),
+        config: None,
+        items,
+    })
+}
+
+struct Parser<'a> {
+    path: &'a Path,
+    chars: Vec<char>,
+    position: usize,
+    macros: HashMap<String, String>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.position += 1;
+        c
+    }
+
+    fn error(&self, message: &str) -> Error {
+        let line = self.chars[..self.position.min(self.chars.len())]
+            .iter()
+            .filter(|c| **c == '\n')
+            .count()
+            + 1;
+        Error::Syntax {
+            path: self.path.to_path_buf(),
+            line,
+            message: message.to_string(),
+        }
+    }
+
+    fn skip_to(&mut self, target: char) -> bool {
+        while let Some(c) = self.next() {
+            if c == target {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    fn identifier(&mut self) -> String {
+        self.skip_whitespace();
+        let mut identifier = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || "{}()=,#\"".contains(c) {
+                break;
+            }
+            identifier.push(c);
+            self.position += 1;
+        }
+        identifier
+    }
+
+    // skips to the delimiter that closes the current entry
+    fn skip_block(&mut self, close: char) -> Result<()> {
+        let mut depth = 0;
+        while let Some(c) = self.next() {
+            match c {
+                '{' => depth += 1,
+                '}' if depth > 0 => depth -= 1,
+                c if c == close && depth == 0 => return Ok(()),
+                _ => {}
+            }
+        }
+        Err(self.error("entry is never closed"))
+    }
+
+    fn entry(&mut self, entry_type: &str, close: char) -> Result<Item> {
+        let start = self.position;
+        let key = self.identifier();
+        let mut fields = HashMap::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some(c) if c == close => {
+                    self.position += 1;
+                    break;
+                }
+                Some(_) => {
+                    let (name, value) = self.field()?;
+                    fields.insert(name, value);
+                }
+                None => {
+                    self.position = start;
+                    return Err(self.error(&format!("entry {} is never closed", key)));
+                }
+            }
+        }
+        Ok(item(entry_type, key, &fields))
+    }
+
+    // `name = {braced} # "quoted" # 2020 # macro`
+    fn field(&mut self) -> Result<(String, String)> {
+        let name = self.identifier().to_lowercase();
+        self.skip_whitespace();
+        if self.next() != Some('=') {
+            return Err(self.error(&format!("expected `=` after field `{}`", name)));
+        }
+        let mut value = String::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('{') => {
+                    self.position += 1;
+                    value.push_str(&self.delimited('}')?);
+                }
+                Some('"') => {
+                    self.position += 1;
+                    value.push_str(&self.delimited('"')?);
+                }
+                Some(_) => {
+                    let word = self.identifier();
+                    if word.is_empty() {
+                        return Err(self.error(&format!("field `{}` has no value", name)));
+                    }
+                    match self.macros.get(&word.to_lowercase()) {
+                        Some(expansion) => value.push_str(expansion),
+                        None => value.push_str(&word),
+                    }
+                }
+                None => return Err(self.error(&format!("field `{}` has no value", name))),
+            }
+            self.skip_whitespace();
+            if self.peek() != Some('#') {
+                break;
+            }
+            self.position += 1;
+        }
+        Ok((name, value))
+    }
+
+    // reads up to the closing delimiter, keeping nested braces in the value
+    fn delimited(&mut self, close: char) -> Result<String> {
+        let start = self.position;
+        let mut depth = 0;
+        let mut value = String::new();
+        while let Some(c) = self.next() {
+            match c {
+                '{' => depth += 1,
+                '}' if depth > 0 => depth -= 1,
+                c if c == close && depth == 0 => return Ok(value),
+                '}' => break,
+                _ => {}
+            }
+            value.push(c);
+        }
+        self.position = start;
+        Err(self.error("unbalanced braces in field value"))
+    }
+}
+
+fn item(entry_type: &str, key: String, fields: &HashMap<String, String>) -> Item {
+    let text = |name: &str| {
+        fields
+            .get(name)
+            .map(|value| latex_to_unicode(value))
+            .filter(|value| !value.is_empty())
+    };
+    // identifiers and links are taken as written, only escapes are undone
+    let verbatim = |name: &str| {
+        fields
+            .get(name)
+            .map(|value| {
+                value
+                    .replace(['{', '}'], "")
+                    .replace('\\', "")
+                    .trim()
+                    .to_string()
+            })
+            .filter(|value| !value.is_empty())
+    };
+    // `eprint = {2101.00001}` with `eprinttype = {arxiv}` (BibLaTeX) or
+    // `archivePrefix = {arXiv}` (BibTeX), the way arXiv's own export writes it
+    let arxiv_id = verbatim("eprint").filter(|_| {
+        text("eprinttype")
+            .or_else(|| text("archiveprefix"))
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("arxiv"))
+    });
+    let item_type = match ITEM_TYPES
+        .iter()
+        .find(|(bibtex_name, _)| *bibtex_name == entry_type)
+    {
+        Some((_, "document")) | Some((_, "manuscript")) | None if arxiv_id.is_some() => "preprint",
+        Some((_, zotero_name)) => zotero_name,
+        None => "document",
+    }
+    .to_string();
+
+    let mut creators = Vec::new();
+    for (field, creator_type) in [
+        ("author", "author"),
+        ("editor", "editor"),
+        ("translator", "translator"),
+    ] {
+        if let Some(names) = fields.get(field) {
+            creators.extend(
+                split_names(names)
+                    .iter()
+                    .map(|name| creator(name, creator_type)),
+            );
+        }
+    }
+
+    // BibTeX's `type` field overrides the degree the entry type implies
+    let work_type = text("type").or_else(|| match entry_type {
+        "phdthesis" => Some("PhD thesis".to_string()),
+        "mastersthesis" => Some("Master's thesis".to_string()),
+        _ => None,
+    });
+    let (book_title, proceedings_title) = match item_type.as_str() {
+        "conferencePaper" => (None, text("booktitle")),
+        _ => (text("booktitle"), None),
+    };
+    let thesis = item_type == "thesis";
+    let report = item_type == "report";
+
+    Item {
+        id: key,
+        item_type,
+        doi: verbatim("doi"),
+        title: text("title"),
+        url: verbatim("url"),
+        creators,
+        isbn: text("isbn"),
+        short_title: text("shorttitle"),
+        published_date: text("date").or_else(|| date(fields)),
+        journal: text("journaltitle").or_else(|| text("journal")),
+        book_title,
+        proceedings_title,
+        conference_name: text("eventtitle"),
+        publisher: text("publisher"),
+        place: text("location").or_else(|| text("address")),
+        pages: text("pages"),
+        university: text("school"),
+        thesis_type: work_type.clone().filter(|_| thesis),
+        institution: text("institution"),
+        report_number: text("number").filter(|_| report),
+        report_type: work_type.filter(|_| report),
+        series_title: text("series"),
+        edition: text("edition"),
+        // biblatex-software's fields
+        version_number: text("version"),
+        rights: text("license"),
+        repository: verbatim("repository")
+            .or_else(|| arxiv_id.as_ref().map(|_| "arXiv".to_string())),
+        archive_id: arxiv_id.as_ref().map(|id| format!("arXiv:{}", id)),
+        // the way Better BibTeX keeps BibTeX fields Zotero has no place for
+        extra: text("eprintclass")
+            .or_else(|| text("primaryclass"))
+            .map(|class| format!("tex.eprintclass: {}", class)),
+        tags: text("keywords")
+            .map(|keywords| {
+                keywords
+                    .split([',', ';'])
+                    .map(str::trim)
+                    .filter(|keyword| !keyword.is_empty())
+                    .map(|keyword| Tag {
+                        tag: keyword.to_string(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        notes: text("annotation")
+            .or_else(|| text("annote"))
+            .map(|annotation| {
+                vec![Note {
+                    date_added: String::new(),
+                    date_modified: String::new(),
+                    content: annotation,
+                    uri: String::new(),
+                }]
+            })
+            .unwrap_or_default(),
+        ..Item::default()
+    }
+}
+
+// BibTeX style `year` and `month` (a number once the month macros are expanded)
+fn date(fields: &HashMap<String, String>) -> Option<String> {
+    let year = fields.get("year")?.trim().to_string();
+    let month = fields.get("month").and_then(|month| {
+        month.trim().parse::<u32>().ok().or_else(|| {
+            let prefix: String = month.trim().to_lowercase().chars().take(3).collect();
+            MONTHS
+                .iter()
+                .position(|name| *name == prefix)
+                .map(|index| index as u32 + 1)
+        })
+    });
+    let day = fields
+        .get("day")
+        .and_then(|day| day.trim().parse::<u32>().ok());
+    Some(match (month, day) {
+        (Some(month), Some(day)) => format!("{}-{:02}-{:02}", year, month, day),
+        (Some(month), None) => format!("{}-{:02}", year, month),
+        _ => year,
+    })
+}
+
+// "Last, First and First von Last and {Some Organisation}"
+fn split_names(names: &str) -> Vec<String> {
+    let mut split = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    let words: Vec<&str> = names.split_whitespace().collect();
+    for word in words {
+        if depth == 0 && word.eq_ignore_ascii_case("and") {
+            split.push(current.trim().to_string());
+            current.clear();
+            continue;
+        }
+        depth += word.matches('{').count() as i32 - word.matches('}').count() as i32;
+        current.push_str(word);
+        current.push(' ');
+    }
+    split.push(current.trim().to_string());
+    split.retain(|name| !name.is_empty());
+    split
+}
+
+fn creator(name: &str, creator_type: &str) -> Creator {
+    let mut creator = Creator {
+        creator_type: Some(creator_type.to_string()),
+        first_name: None,
+        last_name: None,
+        name: None,
+    };
+
+    // a name wrapped in braces is kept as it is, e.g. a company
+    if name.starts_with('{') && name.ends_with('}') && top_level_commas(name).is_empty() {
+        let inner = &name[1..name.len() - 1];
+        if !inner.contains('{') || inner.starts_with('\\') {
+            creator.name = Some(latex_to_unicode(name));
+            return creator;
+        }
+    }
+
+    let commas = top_level_commas(name);
+    let (first, last) = match commas.as_slice() {
+        // "von Last, Jr, First"
+        [first_comma, second_comma, ..] => (
+            name[second_comma + 1..].to_string(),
+            format!(
+                "{}, {}",
+                &name[..*first_comma],
+                name[first_comma + 1..*second_comma].trim()
+            ),
+        ),
+        // "von Last, First"
+        [comma] => (name[comma + 1..].to_string(), name[..*comma].to_string()),
+        // "First von Last", the last name starts at the first lower case word
+        [] => {
+            let words: Vec<&str> = name.split_whitespace().collect();
+            let last_start = words
+                .iter()
+                .position(|word| word.starts_with(char::is_lowercase))
+                .filter(|position| *position < words.len() - 1)
+                .unwrap_or(words.len() - 1);
+            (words[..last_start].join(" "), words[last_start..].join(" "))
+        }
+    };
+
+    let first = latex_to_unicode(first.trim());
+    creator.first_name = Some(first).filter(|first| !first.is_empty());
+    creator.last_name = Some(latex_to_unicode(last.trim()));
+    creator
+}
+
+fn top_level_commas(name: &str) -> Vec<usize> {
+    let mut depth = 0;
+    name.char_indices()
+        .filter_map(|(index, c)| {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                ',' if depth == 0 => return Some(index),
+                _ => {}
+            }
+            None
+        })
+        .collect()
+}
+
+// accent command, combining character
+const ACCENTS: &[(char, char)] = &[
+    ('\'', '\u{301}'),
+    ('`', '\u{300}'),
+    ('^', '\u{302}'),
+    ('"', '\u{308}'),
+    ('~', '\u{303}'),
+    ('=', '\u{304}'),
+    ('.', '\u{307}'),
+    ('u', '\u{306}'),
+    ('v', '\u{30C}'),
+    ('H', '\u{30B}'),
+    ('c', '\u{327}'),
+    ('k', '\u{328}'),
+    ('r', '\u{30A}'),
+    ('d', '\u{323}'),
+    ('b', '\u{331}'),
+];
+
+// letters that LaTeX writes as a command
+const SYMBOLS: &[(&str, &str)] = &[
+    ("ss", "ß"),
+    ("o", "ø"),
+    ("O", "Ø"),
+    ("ae", "æ"),
+    ("AE", "Æ"),
+    ("oe", "œ"),
+    ("OE", "Œ"),
+    ("aa", "å"),
+    ("AA", "Å"),
+    ("l", "ł"),
+    ("L", "Ł"),
+    ("i", "ı"),
+    ("j", "ȷ"),
+    ("dh", "ð"),
+    ("DH", "Ð"),
+    ("th", "þ"),
+    ("TH", "Þ"),
+    ("textendash", "–"),
+    ("textemdash", "—"),
+    ("textquoteright", "’"),
+    ("textquoteleft", "‘"),
+    ("LaTeX", "LaTeX"),
+    ("TeX", "TeX"),
+];
+
+// Turns the LaTeX in a field value into plain text: accents become accented letters,
+// escaped characters lose their backslash, other commands keep only their argument and
+// the braces used for case protection go away
+pub fn latex_to_unicode(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    let mut output = String::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        index += 1;
+        match c {
+            '{' | '}' => {}
+            '~' => output.push(' '),
+            '-' if chars.get(index) == Some(&'-') => {
+                if chars.get(index + 1) == Some(&'-') {
+                    output.push('—');
+                    index += 2;
+                } else {
+                    output.push('–');
+                    index += 1;
+                }
+            }
+            '\\' => {
+                let name: String = chars[index..]
+                    .iter()
+                    .take_while(|c| c.is_alphabetic())
+                    .collect();
+                let command = match chars.get(index) {
+                    Some(command) => *command,
+                    None => continue,
+                };
+                let is_accent = ACCENTS.iter().any(|(accent, _)| *accent == command)
+                    && (!command.is_alphabetic() || name.chars().count() == 1);
+                if is_accent {
+                    // `\'e`, `\'{e}`, `\c c` and `\'{\i}` all work
+                    index += 1;
+                    while command.is_alphabetic() && chars.get(index) == Some(&' ') {
+                        index += 1;
+                    }
+                    let letter = if chars.get(index) == Some(&'{') {
+                        let end = chars[index..]
+                            .iter()
+                            .position(|c| *c == '}')
+                            .map_or(chars.len(), |end| index + end);
+                        let letter: String = chars[index + 1..end].iter().collect();
+                        index = (end + 1).min(chars.len());
+                        latex_to_unicode(&letter)
+                    } else if chars.get(index) == Some(&'\\') {
+                        // `\'\i`
+                        let letter: String = chars[index + 1..]
+                            .iter()
+                            .take_while(|c| c.is_alphabetic())
+                            .collect();
+                        index += 1 + letter.chars().count();
+                        latex_to_unicode(&format!("\\{}", letter))
+                    } else {
+                        index += 1;
+                        chars
+                            .get(index - 1)
+                            .map(char::to_string)
+                            .unwrap_or_default()
+                    };
+                    output.push_str(&accent(command, &letter));
+                } else if name.is_empty() {
+                    // `\&`, `\%`, `\_` and so on
+                    output.push(command);
+                    index += 1;
+                } else {
+                    index += name.chars().count();
+                    if let Some((_, symbol)) = SYMBOLS.iter().find(|(symbol, _)| *symbol == name) {
+                        output.push_str(symbol);
+                    }
+                    // `\emph{x}` and friends leave `{x}` behind, which is read as usual
+                    if chars.get(index) == Some(&' ') {
+                        index += 1;
+                    }
+                }
+            }
+            c => output.push(c),
+        }
+    }
+    output.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// puts the accent on the letter, using the precomposed letter where there is one
+fn accent(command: char, letter: &str) -> String {
+    let combining = ACCENTS
+        .iter()
+        .find(|(accent, _)| *accent == command)
+        .map(|(_, combining)| *combining);
+    let mut chars = letter.chars();
+    let base = match (chars.next(), combining) {
+        (Some(base), Some(combining)) => (base, combining),
+        _ => return letter.to_string(),
+    };
+    let rest: String = chars.collect();
+    match compose(base.0, base.1) {
+        Some(composed) => format!("{}{}", composed, rest),
+        None => format!("{}{}{}", base.0, base.1, rest),
+    }
+}
+
+// the precomposed forms of the accented letters that turn up in names and titles
+fn compose(base: char, combining: char) -> Option<char> {
+    let table: &str = match combining {
+        '\u{301}' => "aá eé ií oó uú yý AÁ EÉ IÍ OÓ UÚ YÝ cć CĆ nń NŃ sś SŚ zź ZŹ lĺ LĹ rŕ RŔ ıí",
+        '\u{300}' => "aà eè iì oò uù AÀ EÈ IÌ OÒ UÙ ıì",
+        '\u{302}' => "aâ eê iî oô uû AÂ EÊ IÎ OÔ UÛ ıî",
+        '\u{308}' => "aä eë iï oö uü yÿ AÄ EË IÏ OÖ UÜ ıï",
+        '\u{303}' => "aã nñ oõ AÃ NÑ OÕ",
+        '\u{304}' => "aā eē iī oō uū AĀ EĒ IĪ OŌ UŪ",
+        '\u{307}' => "zż ZŻ eė EĖ",
+        '\u{306}' => "aă gğ AĂ GĞ",
+        '\u{30C}' => "cč CČ sš SŠ zž ZŽ rř RŘ eě EĚ nň NŇ",
+        '\u{30B}' => "oő uű OŐ UŰ",
+        '\u{327}' => "cç CÇ sş SŞ",
+        '\u{328}' => "aą eę AĄ EĘ",
+        '\u{30A}' => "aå uů AÅ UŮ",
+        _ => "",
+    };
+    table.split(' ').find_map(|pair| {
+        let mut pair = pair.chars();
+        match (pair.next(), pair.next()) {
+            (Some(plain), Some(composed)) if plain == base => Some(composed),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str) -> Vec<Item> {
+        read(Path::new("library.bib"), contents)
+            .expect("library should parse")
+            .items
+    }
+
+    fn names(item: &Item) -> Vec<String> {
+        item.creators
+            .iter()
+            .map(|creator| match &creator.name {
+                Some(name) => name.clone(),
+                None => format!(
+                    "{} | {}",
+                    creator.last_name.as_deref().unwrap_or(""),
+                    creator.first_name.as_deref().unwrap_or("")
+                ),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn expands_string_and_month_macros() {
+        let items = parse(
+            r#"
+            @string{jml = "Journal of " # {Machine} # " Learning"}
+            @STRING(pre = {Attention})
+            @comment{ignored, title = {not an entry}}
+            @article{vaswani2017,
+              title = pre # " Is All You Need",
+              journal = jml,
+              year = 2017, month = jun, day = {12},
+            }
+            @article{smith2020, title = {Dates}, year = {2020}, month = {September}}
+            "#,
+        );
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].title.as_deref(), Some("Attention Is All You Need"));
+        assert_eq!(
+            items[0].journal.as_deref(),
+            Some("Journal of Machine Learning")
+        );
+        assert_eq!(items[0].published_date.as_deref(), Some("2017-06-12"));
+        assert_eq!(items[1].published_date.as_deref(), Some("2020-09"));
+    }
+
+    #[test]
+    fn keeps_nested_braces_in_values() {
+        let items = parse(
+            r#"@book(lee2019,
+              title = {The {RNA} World of {\emph{E. coli}}},
+              publisher = "Springer {"}Verlag{"}",
+              url = {https://example.org/a_{b}}
+            )"#,
+        );
+        assert_eq!(items[0].title.as_deref(), Some("The RNA World of E. coli"));
+        assert_eq!(items[0].publisher.as_deref(), Some("Springer \"Verlag\""));
+        assert_eq!(items[0].url.as_deref(), Some("https://example.org/a_b"));
+
+        let error = read(
+            Path::new("library.bib"),
+            "@article{ok, title = {Fine}}\n\n@article{broken,\n  title = {Oops}",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "library.bib:3: entry broken is never closed"
+        );
+    }
+
+    #[test]
+    fn turns_latex_into_unicode() {
+        assert_eq!(latex_to_unicode(r"Caf\'e"), "Café");
+        assert_eq!(latex_to_unicode(r"Mart{\'\i}nez"), "Martínez");
+        assert_eq!(latex_to_unicode(r"Mart\'{\i}nez"), "Martínez");
+        assert_eq!(
+            latex_to_unicode(r"Fran\c{c}ois and Fran\c cois"),
+            "François and François"
+        );
+        assert_eq!(
+            latex_to_unicode(r#"Schr\"{o}dinger, Schr\"odinger"#),
+            "Schrödinger, Schrödinger"
+        );
+        assert_eq!(latex_to_unicode(r"Stra\ss e, {\O}rsted"), "Straße, Ørsted");
+        assert_eq!(latex_to_unicode(r"10--20 \& 30---40~\%"), "10–20 & 30—40 %");
+        assert_eq!(latex_to_unicode(r"\textbf{Bold}  {case}"), "Bold case");
+
+        assert_eq!(compose('e', '\u{301}'), Some('é'));
+        assert_eq!(compose('ı', '\u{308}'), Some('ï'));
+        assert_eq!(compose('q', '\u{301}'), None);
+        assert_eq!(accent('\'', "q"), "q\u{301}");
+    }
+
+    #[test]
+    fn splits_names() {
+        let names_field =
+            "{Barnes and Noble, Inc.} and van der Berg, Jr, Jan AND Ludwig von Beethoven and Doe, J. and Plato";
+        assert_eq!(
+            split_names(names_field),
+            [
+                "{Barnes and Noble, Inc.}",
+                "van der Berg, Jr, Jan",
+                "Ludwig von Beethoven",
+                "Doe, J.",
+                "Plato"
+            ]
+        );
+        let items = parse(&format!(
+            "@book{{x, title = {{T}}, author = {{{}}}, editor = {{G{{\\\"o}}del, Kurt}}}}",
+            names_field
+        ));
+        assert_eq!(
+            names(&items[0]),
+            [
+                "Barnes and Noble, Inc.",
+                "van der Berg, Jr | Jan",
+                "von Beethoven | Ludwig",
+                "Doe | J.",
+                "Plato | ",
+                "Gödel | Kurt"
+            ]
+        );
+        assert_eq!(items[0].creators[5].creator_type.as_deref(), Some("editor"));
+    }
+
+    #[test]
+    fn maps_entry_types_to_item_types() {
+        let items = parse(
+            r#"
+            @article{a, title = {A}}
+            @book{b, title = {B}}
+            @incollection{c, title = {C}, booktitle = {The Collection}}
+            @inproceedings{d, title = {D}, booktitle = {Proceedings of D}}
+            @online{e, title = {E}}
+            @cookbook{f, title = {F}}
+            @misc{g, title = {G}, eprint = {2101.00001}, eprinttype = {arxiv}}
+            "#,
+        );
+        let types: Vec<&str> = items.iter().map(|item| item.item_type.as_str()).collect();
+        assert_eq!(
+            types,
+            [
+                "journalArticle",
+                "book",
+                "bookSection",
+                "conferencePaper",
+                "webpage",
+                "document",
+                "preprint"
+            ]
+        );
+        assert_eq!(items[2].book_title.as_deref(), Some("The Collection"));
+        assert_eq!(items[3].book_title, None);
+        assert_eq!(
+            items[3].proceedings_title.as_deref(),
+            Some("Proceedings of D")
+        );
+        assert_eq!(items[6].archive_id.as_deref(), Some("arXiv:2101.00001"));
+    }
+}
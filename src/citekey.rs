@@ -0,0 +1,319 @@
+use std::collections::HashSet;
+
+use super::json_parser::{Creator, Item};
+
+// Better BibTeX's own default
+pub const DEFAULT_FORMAT: &str = "[auth:lower][shorttitle3_3][year]";
+
+// words Better BibTeX leaves out of title based keys
+const SKIP_WORDS: &[&str] = &[
+    "a", "about", "above", "across", "after", "against", "al", "along", "among", "an", "and",
+    "around", "as", "at", "before", "behind", "below", "beside", "between", "beyond", "but", "by",
+    "d", "de", "del", "der", "des", "die", "du", "during", "el", "en", "et", "for", "from", "in",
+    "inside", "into", "is", "l", "la", "le", "les", "of", "off", "on", "onto", "or", "over", "per",
+    "since", "so", "than", "the", "through", "to", "toward", "towards", "under", "until", "up",
+    "upon", "via", "von", "with", "within", "without",
+];
+
+// Builds a citekey from a Better BibTeX citekey formula such as
+// `[auth][year][shorttitle3_3]`. Only the common parts of the formula language are
+// understood: `auth`, `authors`, `year`, `title`, `shorttitle`, `veryshorttitle`, the
+// `:lower` and `:upper` filters and `|` between alternatives. Unknown fields are skipped.
+pub fn generate(item: &Item, format: &str) -> String {
+    format
+        .split('|')
+        .map(|alternative| generate_alternative(item, alternative.trim()))
+        .find(|key| !key.is_empty())
+        .unwrap_or_else(|| fallback(item))
+}
+
+fn generate_alternative(item: &Item, formula: &str) -> String {
+    let mut key = String::new();
+    let mut rest = formula;
+    while let Some(start) = rest.find('[') {
+        key.push_str(&clean(&rest[..start]));
+        let end = match rest[start..].find(']') {
+            Some(end) => start + end,
+            None => break,
+        };
+        key.push_str(&field(item, &rest[start + 1..end]));
+        rest = &rest[end + 1..];
+    }
+    key.push_str(&clean(rest));
+    key
+}
+
+fn field(item: &Item, expression: &str) -> String {
+    let mut parts = expression.split(':');
+    let name = parts.next().unwrap_or("");
+
+    let mut value = if name == "auth" {
+        authors(item).first().cloned().unwrap_or_default()
+    } else if name == "authors" {
+        authors(item).concat()
+    } else if name == "year" {
+        year(item)
+    } else if name == "title" {
+        title_words(item).concat()
+    } else if name == "veryshorttitle" {
+        title_words(item).into_iter().take(1).collect()
+    } else if let Some(counts) = name.strip_prefix("shorttitle") {
+        let mut counts = counts.split('_').map(|count| count.parse::<usize>().ok());
+        let words = counts.next().flatten().unwrap_or(3);
+        title_words(item).into_iter().take(words).collect()
+    } else {
+        String::new()
+    };
+
+    parts.for_each(|filter| match filter {
+        "lower" => value = value.to_lowercase(),
+        "upper" => value = value.to_uppercase(),
+        _ => {}
+    });
+    value
+}
+
+fn authors(item: &Item) -> Vec<String> {
+    let is_author = |creator: &&Creator| creator.creator_type.as_deref() == Some("author");
+    let creators: Vec<&Creator> = if item.creators.iter().any(|creator| is_author(&creator)) {
+        item.creators.iter().filter(is_author).collect()
+    } else {
+        item.creators.iter().collect()
+    };
+    creators
+        .into_iter()
+        .filter_map(|creator| creator.last_name.clone().or_else(|| creator.name.clone()))
+        .map(|name| clean(&name))
+        .collect()
+}
+
+fn year(item: &Item) -> String {
+    let date = item.published_date.clone().unwrap_or_default();
+    date.as_bytes()
+        .windows(4)
+        .find(|window| window.iter().all(u8::is_ascii_digit))
+        .map(|window| String::from_utf8_lossy(window).to_string())
+        .unwrap_or_default()
+}
+
+fn title_words(item: &Item) -> Vec<String> {
+    item.title
+        .clone()
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(clean)
+        .filter(|word| !word.is_empty() && !SKIP_WORDS.contains(&word.to_lowercase().as_str()))
+        .map(|word| capitalise(&word))
+        .collect()
+}
+
+fn capitalise(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+// letters and digits only, with accented Latin letters folded to ASCII the way Better
+// BibTeX does (`Müller` -> `Muller`, `Łódź` -> `Lodz`); other scripts are kept
+fn clean(text: &str) -> String {
+    let folded: String = text
+        .chars()
+        .filter(|c| !('\u{300}'..='\u{36F}').contains(c))
+        .map(fold)
+        .collect();
+    folded.chars().filter(|c| c.is_alphanumeric()).collect()
+}
+
+const FOLDED: &[(&str, &str)] = &[
+    ("àáâãäåāăą", "a"),
+    ("ÀÁÂÃÄÅĀĂĄ", "A"),
+    ("çćč", "c"),
+    ("ÇĆČ", "C"),
+    ("ďđ", "d"),
+    ("ĎĐ", "D"),
+    ("èéêëēėęě", "e"),
+    ("ÈÉÊËĒĖĘĚ", "E"),
+    ("ğ", "g"),
+    ("Ğ", "G"),
+    ("ìíîïīı", "i"),
+    ("ÌÍÎÏĪİ", "I"),
+    ("ĺľł", "l"),
+    ("ĹĽŁ", "L"),
+    ("ñńň", "n"),
+    ("ÑŃŇ", "N"),
+    ("òóôõöøōő", "o"),
+    ("ÒÓÔÕÖØŌŐ", "O"),
+    ("ŕř", "r"),
+    ("ŔŘ", "R"),
+    ("śšş", "s"),
+    ("ŚŠŞ", "S"),
+    ("ťţ", "t"),
+    ("ŤŢ", "T"),
+    ("ùúûüūůű", "u"),
+    ("ÙÚÛÜŪŮŰ", "U"),
+    ("ýÿ", "y"),
+    ("Ý", "Y"),
+    ("źżž", "z"),
+    ("ŹŻŽ", "Z"),
+    ("ß", "ss"),
+    ("æ", "ae"),
+    ("Æ", "AE"),
+    ("œ", "oe"),
+    ("Œ", "OE"),
+    ("þ", "th"),
+    ("Þ", "Th"),
+];
+
+fn fold(c: char) -> String {
+    FOLDED
+        .iter()
+        .find(|(accented, _)| accented.contains(c))
+        .map_or_else(|| c.to_string(), |(_, plain)| plain.to_string())
+}
+
+// Zotero's item key is the one thing every item has
+fn fallback(item: &Item) -> String {
+    item.uri
+        .as_deref()
+        .and_then(|uri| uri.rsplit('/').next())
+        .map(clean)
+        .filter(|key| !key.is_empty())
+        .unwrap_or_else(|| "item".to_string())
+}
+
+// appends a, b, c... the way Better BibTeX does when a key is already in use
+pub fn unique(key: String, taken: &mut HashSet<String>) -> String {
+    if taken.insert(key.clone()) {
+        return key;
+    }
+    let mut suffix = 0;
+    loop {
+        let candidate = format!("{}{}", key, suffix_letters(suffix));
+        if taken.insert(candidate.clone()) {
+            return candidate;
+        }
+        suffix += 1;
+    }
+}
+
+fn suffix_letters(mut n: usize) -> String {
+    let mut letters = Vec::new();
+    loop {
+        letters.push((b'a' + (n % 26) as u8) as char);
+        if n < 26 {
+            break;
+        }
+        n = n / 26 - 1;
+    }
+    letters.iter().rev().collect()
+}
+
+// Better BibTeX writes pinned keys into the item's Extra field as `Citation Key: ...`
+pub fn pinned(extra: &str) -> Option<String> {
+    extra.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        let name = name.trim().to_lowercase();
+        if name == "citation key" || name == "bibtex" {
+            Some(value.trim().to_string()).filter(|key| !key.is_empty())
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(authors: &[&str], title: &str, date: &str) -> Item {
+        Item {
+            creators: authors
+                .iter()
+                .map(|last_name| Creator {
+                    creator_type: Some("author".to_string()),
+                    first_name: None,
+                    last_name: Some(last_name.to_string()),
+                    name: None,
+                })
+                .collect(),
+            title: Some(title.to_string()),
+            published_date: Some(date.to_string()).filter(|date| !date.is_empty()),
+            uri: Some("http://zotero.org/users/1/items/ABCD2345".to_string()),
+            ..Item::default()
+        }
+    }
+
+    #[test]
+    fn builds_keys_from_the_formula() {
+        let paper = item(
+            &["Vaswani", "Shazeer"],
+            "Attention Is All You Need",
+            "2017-06-12",
+        );
+        assert_eq!(
+            generate(&paper, DEFAULT_FORMAT),
+            "vaswaniAttentionAllYou2017"
+        );
+        assert_eq!(generate(&paper, "[auth][year]"), "Vaswani2017");
+        assert_eq!(
+            generate(&paper, "[authors:lower][veryshorttitle:upper]"),
+            "vaswanishazeerATTENTION"
+        );
+        // the first alternative that comes out non-empty is used
+        let anonymous = item(&[], "Beowulf", "");
+        assert_eq!(generate(&anonymous, "[auth][year] | [title]"), "Beowulf");
+        assert_eq!(generate(&anonymous, "[auth][year]"), "ABCD2345");
+    }
+
+    #[test]
+    fn folds_accented_names_to_ascii() {
+        let paper = item(&["Müller-Łęski"], "Über Straßen in Kraków", "1999");
+        assert_eq!(
+            generate(&paper, DEFAULT_FORMAT),
+            "mullerleskiUberStrassenKrakow1999"
+        );
+        // accents typed as combining marks fold the same way
+        let decomposed = item(&["Mu\u{308}ller"], "Title", "1999");
+        assert_eq!(generate(&decomposed, "[auth:lower]"), "muller");
+        // letters without an ASCII form are kept
+        assert_eq!(generate(&item(&["Ψαρράς"], "", ""), "[auth]"), "Ψαρράς");
+    }
+
+    #[test]
+    fn leaves_the_year_out_when_there_is_none() {
+        let undated = item(&["Smith"], "A History of Clocks", "");
+        assert_eq!(generate(&undated, DEFAULT_FORMAT), "smithHistoryClocks");
+        let unclear = item(&["Smith"], "Clocks", "spring");
+        assert_eq!(generate(&unclear, "[auth:lower][year]"), "smith");
+    }
+
+    #[test]
+    fn adds_letters_to_keys_already_in_use() {
+        let mut taken = HashSet::new();
+        let keys: Vec<String> = (0..4)
+            .map(|_| unique("smith2020".to_string(), &mut taken))
+            .collect();
+        assert_eq!(
+            keys,
+            ["smith2020", "smith2020a", "smith2020b", "smith2020c"]
+        );
+
+        // after `z` come two letters, like spreadsheet columns
+        assert_eq!(suffix_letters(25), "z");
+        assert_eq!(suffix_letters(26), "aa");
+        assert_eq!(suffix_letters(27), "ab");
+        assert_eq!(suffix_letters(26 + 26 * 26), "aaa");
+    }
+
+    #[test]
+    fn reads_pinned_keys_from_extra() {
+        assert_eq!(
+            pinned("tex.note: x\nCitation Key: vaswani2017").as_deref(),
+            Some("vaswani2017")
+        );
+        assert_eq!(pinned("bibtex: smith2020").as_deref(), Some("smith2020"));
+        assert_eq!(pinned("Citation Key:  \nother: value"), None);
+    }
+}
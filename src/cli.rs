@@ -0,0 +1,110 @@
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+use super::config::{VaultConfig, WebApiConfig, CONFIG_FILE};
+use super::sync::SYNC_STATE_PATH;
+use super::zotero_api::API_CACHE_PATH;
+
+#[derive(Parser)]
+#[command(
+    name = "zotero-to-obsidian",
+    version,
+    about = "Generates Obsidian reference notes from a Zotero library"
+)]
+pub struct Cli {
+    /// Root of the Obsidian vault, other default paths are relative to it
+    #[arg(long, global = true, default_value = ".")]
+    pub vault: PathBuf,
+
+    /// Vault config file [default: <vault>/zotero-to-obsidian.toml]
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Library file: a Better BibTeX JSON export, CSL-JSON, RIS, BibTeX or zotero.sqlite
+    /// [default: <vault>/.library/library.json]
+    #[arg(long, global = true)]
+    pub library: Option<PathBuf>,
+
+    /// Directory with the note templates [default: <vault>/Meta/Templates/Resource]
+    #[arg(long, global = true)]
+    pub templates: Option<PathBuf>,
+
+    /// Directory the notes are written to [default: <vault>/Resources]
+    #[arg(long, global = true)]
+    pub output: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Create, update and retire notes so they match the library (the default)
+    Sync {
+        /// Also overwrite notes that were edited by hand; text outside a note's managed
+        /// regions is kept either way
+        #[arg(long)]
+        force: bool,
+    },
+    /// Keep running and sync whenever the library file changes
+    Watch,
+    /// Report what a sync would change without writing anything
+    Check,
+    /// List every citekey that gets a note, with its folder and title
+    List,
+    /// Print the rendered note for one citekey
+    Show { citekey: String },
+    /// Show which classification rule decides the resource type of one citekey
+    Explain { citekey: String },
+    /// Check every template against the fields its resource type fills in; exits with 2
+    /// when a template uses a field that does not exist, or leaves one out unless --lenient
+    CheckTemplates {
+        /// Only list the fields a template leaves out instead of failing on them
+        #[arg(long)]
+        lenient: bool,
+    },
+}
+
+pub struct Paths {
+    pub library: PathBuf,
+    pub templates: PathBuf,
+    pub output: PathBuf,
+    pub sync_state: PathBuf,
+    // set when the library comes from the Zotero web API rather than a file
+    pub web_api: Option<WebApiConfig>,
+    pub api_cache: PathBuf,
+}
+
+impl Paths {
+    // flags win over the vault config, which wins over the defaults
+    pub fn new(cli: &Cli, config: &VaultConfig) -> Paths {
+        Paths {
+            library: cli
+                .library
+                .clone()
+                .unwrap_or_else(|| cli.vault.join(&config.library)),
+            templates: cli
+                .templates
+                .clone()
+                .unwrap_or_else(|| cli.vault.join(&config.templates)),
+            output: cli
+                .output
+                .clone()
+                .unwrap_or_else(|| cli.vault.join(&config.output)),
+            sync_state: cli.vault.join(SYNC_STATE_PATH),
+            web_api: match cli.library {
+                Some(_) => None,
+                None => config.web_api.clone(),
+            },
+            api_cache: cli.vault.join(API_CACHE_PATH),
+        }
+    }
+}
+
+impl Cli {
+    pub fn config_path(&self) -> PathBuf {
+        self.config
+            .clone()
+            .unwrap_or_else(|| self.vault.join(CONFIG_FILE))
+    }
+}
@@ -0,0 +1,386 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::error::{Error, Result};
+use super::resource_types::TEMPLATE_PATH;
+use super::rules::Rule;
+use super::zotero_api::DEFAULT_API_URL;
+use super::{LIBRARY_PATH, RESOURCES_PATH};
+
+pub const CONFIG_FILE: &str = "zotero-to-obsidian.toml";
+
+// resource kind, default folder, default template
+const DEFAULT_TYPES: &[(&str, &str, &str)] = &[
+    ("article", "Articles", "Article.md"),
+    ("academic_paper", "Academic Papers", "Academic Paper.md"),
+    ("preprint", "Preprints", "Preprint.md"),
+    ("press_article", "Press Articles", "Press Article.md"),
+    ("book", "Books", "Book.md"),
+    ("book_section", "Book Sections", "Book Section.md"),
+    ("thesis", "Theses", "Thesis.md"),
+    (
+        "conference_paper",
+        "Conference Papers",
+        "Conference Paper.md",
+    ),
+    ("report", "Reports", "Report.md"),
+    ("legal_case", "Legal/Cases", "Legal Case.md"),
+    ("statute", "Legal/Statutes", "Statute.md"),
+    ("bill", "Legal/Bills", "Bill.md"),
+    ("software", "Software", "Software.md"),
+    ("dataset", "Datasets", "Dataset.md"),
+    ("generic", "Misc", "Generic.md"),
+    ("ted_talk", "TED Talks", "TED Talk.md"),
+    ("youtube_video", "Youtube Videos", "Youtube Video.md"),
+    ("podcast", "Podcasts", "Podcast.md"),
+];
+
+// Everything about the vault layout that used to be hard coded. Relative paths are
+// relative to the vault, e.g.
+//
+//   library = ".library/library.json"
+//   output = "Resources"
+//   filename = "{citekey}"
+//   tag_prefix = "#"
+//   skip = ["webpage"]
+//
+//   [types.book]
+//   folder = "Reading/Books"
+//   template = "My Book.md"
+//
+//   [web_api]
+//   libraries = ["users/475425", "groups/12345"]
+//
+//   [[rules]]
+//   item_type = "videoRecording"
+//   matches = { url = "vimeo\\.com" }
+//   resource = "article"
+//   folder = "Vimeo Videos"
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct VaultConfig {
+    pub library: PathBuf,
+    pub templates: PathBuf,
+    pub output: PathBuf,
+    // file name of a note without `.md`, `{citekey}`, `{title}` and `{citation}` are filled in
+    pub filename: String,
+    pub tag_prefix: String,
+    // Zotero item types that never get a note
+    pub skip: Vec<String>,
+    pub types: HashMap<String, TypeConfig>,
+    // fetch the library from the Zotero web API instead of reading a file
+    pub web_api: Option<WebApiConfig>,
+    // which resource type an item becomes, tried in order before the built-in rules
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct TypeConfig {
+    pub folder: Option<String>,
+    pub template: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WebApiConfig {
+    // `users/<user id>` or `groups/<group id>`
+    pub libraries: Vec<String>,
+    // falls back to the ZOTERO_API_KEY environment variable
+    pub api_key: Option<String>,
+    #[serde(default = "default_api_url")]
+    pub url: String,
+}
+
+fn default_api_url() -> String {
+    DEFAULT_API_URL.to_string()
+}
+
+impl Default for VaultConfig {
+    fn default() -> VaultConfig {
+        VaultConfig {
+            library: PathBuf::from(LIBRARY_PATH),
+            templates: PathBuf::from(TEMPLATE_PATH),
+            output: PathBuf::from(RESOURCES_PATH),
+            filename: "{citekey}".to_string(),
+            tag_prefix: "#".to_string(),
+            skip: Vec::new(),
+            types: HashMap::new(),
+            web_api: None,
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl VaultConfig {
+    // a vault without a config file gets the defaults
+    pub fn load(path: &Path) -> Result<VaultConfig> {
+        if !path.exists() {
+            return Ok(VaultConfig::default());
+        }
+        let contents = fs::read_to_string(path).map_err(|error| Error::io(path, error))?;
+        let config: VaultConfig = toml::from_str(&contents).map_err(|error| Error::Config {
+            path: path.to_path_buf(),
+            message: error.to_string(),
+        })?;
+
+        if let Some(kind) = config
+            .types
+            .keys()
+            .find(|kind| !DEFAULT_TYPES.iter().any(|(known, _, _)| known == kind))
+        {
+            return Err(Error::Config {
+                path: path.to_path_buf(),
+                message: format!("unknown resource type [types.{}]", kind),
+            });
+        }
+        for (index, rule) in config.rules.iter().enumerate() {
+            let message = if !DEFAULT_TYPES
+                .iter()
+                .any(|(kind, _, _)| *kind == rule.resource)
+            {
+                format!(
+                    "rule {}: unknown resource type `{}`",
+                    index + 1,
+                    rule.resource
+                )
+            } else if let Some(field) = rule.unknown_fields().first() {
+                format!("rule {}: items have no field `{}`", index + 1, field)
+            } else {
+                continue;
+            };
+            return Err(Error::Config {
+                path: path.to_path_buf(),
+                message,
+            });
+        }
+        if let Some(library) = config
+            .web_api
+            .iter()
+            .flat_map(|web_api| &web_api.libraries)
+            .find(|library| !is_library_path(library))
+        {
+            return Err(Error::Config {
+                path: path.to_path_buf(),
+                message: format!(
+                    "web_api library `{}` should look like `users/<id>` or `groups/<id>`",
+                    library
+                ),
+            });
+        }
+        Ok(config)
+    }
+
+    pub fn skips(&self, item_type: &str) -> bool {
+        self.skip.iter().any(|skipped| skipped == item_type)
+    }
+
+    pub fn folder(&self, kind: &str) -> String {
+        self.types
+            .get(kind)
+            .and_then(|type_config| type_config.folder.clone())
+            .unwrap_or_else(|| default_for(kind).1.to_string())
+    }
+
+    pub fn template(&self, kind: &str) -> String {
+        self.types
+            .get(kind)
+            .and_then(|type_config| type_config.template.clone())
+            .unwrap_or_else(|| default_for(kind).2.to_string())
+    }
+
+    // every resource kind with the templates it can be written with, its own and those
+    // the vault's rules pick for it
+    pub fn template_uses(&self) -> Vec<(String, String)> {
+        let mut uses: Vec<(String, String)> = DEFAULT_TYPES
+            .iter()
+            .map(|(kind, _, _)| (kind.to_string(), self.template(kind)))
+            .collect();
+        self.rules.iter().for_each(|rule| {
+            if let Some(template) = &rule.template {
+                let rule_use = (rule.resource.clone(), template.clone());
+                if !uses.contains(&rule_use) {
+                    uses.push(rule_use);
+                }
+            }
+        });
+        uses
+    }
+
+    // `{citation}` is the title for items that are not cited by a short citation of their own
+    pub fn file_name(&self, citekey: &str, title: &str, citation: &str) -> String {
+        let citation = match citation {
+            "" => title,
+            citation => citation,
+        };
+        // citekeys from BibTeX, RIS or CSL-JSON can have `/` or `:` in them too
+        let citekey = sanitise_file_name(citekey);
+        let name = self
+            .filename
+            .replace("{citekey}", &citekey)
+            .replace("{title}", &sanitise_file_name(title))
+            .replace("{citation}", &sanitise_file_name(citation));
+        // a title made only of characters a file name can't have leaves nothing
+        match (name.trim(), citekey.as_str()) {
+            ("", "") => "item".to_string(),
+            ("", _) => citekey,
+            _ => name,
+        }
+    }
+}
+
+fn is_library_path(library: &str) -> bool {
+    match library.split_once('/') {
+        Some(("users", id)) | Some(("groups", id)) => {
+            !id.is_empty() && id.chars().all(|c| c.is_ascii_digit())
+        }
+        _ => false,
+    }
+}
+
+fn default_for(kind: &str) -> (&str, &str, &str) {
+    *DEFAULT_TYPES
+        .iter()
+        .find(|(default_kind, _, _)| *default_kind == kind)
+        .unwrap_or_else(|| panic!("no defaults for resource kind {}", kind))
+}
+
+// drops characters that are not allowed in file names or that Obsidian treats as link syntax
+pub fn sanitise_file_name(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| !"/\\:*?\"<>|#^[]".contains(*c))
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    // a config file of its own for every test
+    fn config_file(name: &str, contents: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!(
+            "zotero-to-obsidian-config-{}-{}.toml",
+            name,
+            process::id()
+        ));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn load_error(name: &str, contents: &str) -> String {
+        let path = config_file(name, contents);
+        let message = VaultConfig::load(&path).unwrap_err().to_string();
+        message
+            .trim_start_matches(&format!("{}: ", path.display()))
+            .to_string()
+    }
+
+    #[test]
+    fn fills_in_file_names() {
+        let by_title = VaultConfig {
+            filename: "{title} ({citekey})".to_string(),
+            ..VaultConfig::default()
+        };
+        assert_eq!(
+            by_title.file_name("vaswani2017", "Attention: \"All\" You Need?", ""),
+            "Attention All You Need (vaswani2017)"
+        );
+        assert_eq!(
+            VaultConfig::default().file_name("vaswani2017", "Attention", ""),
+            "vaswani2017"
+        );
+        // nothing is left of a title like this, so the note is named after its citekey
+        let title_only = VaultConfig {
+            filename: "{title}".to_string(),
+            ..VaultConfig::default()
+        };
+        assert_eq!(title_only.file_name("q2020", " ??? ", ""), "q2020");
+        assert_eq!(
+            title_only.file_name("smith/2020:a", "???", ""),
+            "smith2020a"
+        );
+
+        // a citekey can never reach outside the note's folder
+        let by_citekey = VaultConfig::default();
+        assert_eq!(by_citekey.file_name("smith/2020:a", "", ""), "smith2020a");
+        assert_eq!(by_citekey.file_name("../../etc/x", "", ""), "....etcx");
+        assert_eq!(by_citekey.file_name("//", "", ""), "item");
+    }
+
+    #[test]
+    fn drops_characters_file_names_and_links_cannot_have() {
+        assert_eq!(
+            sanitise_file_name(" C/C++: a #1 [guide] | *really*? "),
+            "CC++ a 1 guide  really"
+        );
+        assert_eq!(sanitise_file_name("^[]"), "");
+        assert_eq!(
+            sanitise_file_name("Übersicht, 2. Aufl."),
+            "Übersicht, 2. Aufl."
+        );
+    }
+
+    #[test]
+    fn loads_a_vault_config() {
+        let missing = env::temp_dir().join(format!(
+            "zotero-to-obsidian-config-none-{}.toml",
+            process::id()
+        ));
+        let defaults = VaultConfig::load(&missing).unwrap();
+        assert_eq!(defaults.filename, "{citekey}");
+        assert_eq!(defaults.folder("book"), "Books");
+
+        let path = config_file(
+            "vault",
+            r#"
+            filename = "{title}"
+            tag_prefix = "topic/"
+            skip = ["webpage"]
+
+            [types.book]
+            folder = "Reading/Books"
+
+            [[rules]]
+            item_type = "book"
+            resource = "book"
+            template = "Shelf.md"
+            "#,
+        );
+        let config = VaultConfig::load(&path).unwrap();
+        assert_eq!(config.tag_prefix, "topic/");
+        assert!(config.skips("webpage"));
+        assert_eq!(config.folder("book"), "Reading/Books");
+        assert_eq!(config.template("book"), "Book.md");
+        assert!(config
+            .template_uses()
+            .contains(&("book".to_string(), "Shelf.md".to_string())));
+    }
+
+    #[test]
+    fn rejects_configs_that_cannot_work() {
+        assert_eq!(
+            load_error("type", "[types.novel]\nfolder = \"Novels\"\n"),
+            "unknown resource type [types.novel]"
+        );
+        assert_eq!(
+            load_error(
+                "rule",
+                "[[rules]]\nitem_type = \"book\"\nresource = \"novel\"\n"
+            ),
+            "rule 1: unknown resource type `novel`"
+        );
+        assert_eq!(
+            load_error("library", "[web_api]\nlibraries = [\"users/me\"]\n"),
+            "web_api library `users/me` should look like `users/<id>` or `groups/<id>`"
+        );
+        assert!(
+            load_error("typo", "file_name = \"{title}\"\n").contains("unknown field `file_name`")
+        );
+    }
+}
@@ -0,0 +1,416 @@
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use super::json_parser::{Creator, Item, Tag, ZoteroData};
+
+// CSL type, Zotero item type. Types without an entry keep their CSL name and end up
+// as generic notes.
+const ITEM_TYPES: &[(&str, &str)] = &[
+    ("article", "preprint"),
+    ("article-journal", "journalArticle"),
+    ("article-magazine", "magazineArticle"),
+    ("article-newspaper", "newspaperArticle"),
+    ("bill", "bill"),
+    ("book", "book"),
+    ("broadcast", "tvBroadcast"),
+    ("chapter", "bookSection"),
+    ("dataset", "dataset"),
+    ("entry-dictionary", "dictionaryEntry"),
+    ("entry-encyclopedia", "encyclopediaArticle"),
+    ("interview", "interview"),
+    ("legal_case", "case"),
+    ("legislation", "statute"),
+    ("manuscript", "manuscript"),
+    ("map", "map"),
+    ("motion_picture", "videoRecording"),
+    ("paper-conference", "conferencePaper"),
+    ("patent", "patent"),
+    ("personal_communication", "letter"),
+    ("post", "forumPost"),
+    ("post-weblog", "blogPost"),
+    ("report", "report"),
+    ("software", "computerProgram"),
+    ("song", "audioRecording"),
+    ("speech", "presentation"),
+    ("thesis", "thesis"),
+    ("webpage", "webpage"),
+];
+
+// CSL name variable, Zotero creator type
+const CREATOR_TYPES: &[(&str, &str)] = &[
+    ("author", "author"),
+    ("editor", "editor"),
+    ("translator", "translator"),
+    ("container-author", "bookAuthor"),
+    ("collection-editor", "seriesEditor"),
+    ("director", "director"),
+    ("interviewer", "interviewer"),
+    ("composer", "composer"),
+    ("recipient", "recipient"),
+    ("reviewed-author", "reviewedAuthor"),
+];
+
+// CSL-JSON is an array of items, a single item is accepted as well
+pub fn is_csl_json(value: &Value) -> bool {
+    match value {
+        Value::Array(items) => items.iter().all(|item| item.get("type").is_some()),
+        Value::Object(item) => item.contains_key("type") && !item.contains_key("items"),
+        _ => false,
+    }
+}
+
+pub fn read(value: &Value) -> ZoteroData {
+    let items = match value {
+        Value::Array(items) => items
+            .iter()
+            .filter_map(Value::as_object)
+            .map(item)
+            .collect(),
+        Value::Object(csl_item) => vec![item(csl_item)],
+        _ => Vec::new(),
+    };
+    ZoteroData {
+        collections: HashMap::new(),
+        config: None,
+        items,
+    }
+}
+
+fn item(csl: &Map<String, Value>) -> Item {
+    let csl_type = text(csl, "type").unwrap_or_default();
+    let item_type = ITEM_TYPES
+        .iter()
+        .find(|(csl_name, _)| *csl_name == csl_type)
+        .map(|(_, zotero_name)| zotero_name.to_string())
+        .unwrap_or(csl_type);
+
+    let mut item = Item {
+        item_type,
+        doi: text(csl, "DOI"),
+        title: text(csl, "title"),
+        library_catalog: text(csl, "source"),
+        url: text(csl, "URL"),
+        creators: creators(csl),
+        isbn: text(csl, "ISBN"),
+        short_title: text(csl, "title-short").or_else(|| text(csl, "shortTitle")),
+        published_date: csl.get("issued").and_then(date),
+        journal: text(csl, "container-title"),
+        tags: text(csl, "keyword")
+            .map(|keywords| {
+                keywords
+                    .split([',', ';'])
+                    .map(str::trim)
+                    .filter(|keyword| !keyword.is_empty())
+                    .map(|keyword| Tag {
+                        tag: keyword.to_string(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        publisher: text(csl, "publisher"),
+        place: text(csl, "publisher-place"),
+        pages: text(csl, "page"),
+        conference_name: text(csl, "event-title").or_else(|| text(csl, "event")),
+        series_title: text(csl, "collection-title"),
+        episode_number: text(csl, "collection-number"),
+        running_time: text(csl, "dimensions"),
+        section: text(csl, "section"),
+        edition: text(csl, "edition"),
+        extra: text(csl, "note"),
+        version_number: text(csl, "version"),
+        ..Item::default()
+    };
+
+    // CSL has one container title, publisher and genre where Zotero has a field per type
+    match item.item_type.as_str() {
+        "bookSection" => item.book_title = item.journal.take(),
+        "conferencePaper" => item.proceedings_title = item.journal.take(),
+        "thesis" => {
+            item.university = item.publisher.take();
+            item.thesis_type = text(csl, "genre");
+        }
+        "preprint" => {
+            item.repository = item.publisher.take();
+            item.archive_id = text(csl, "number");
+        }
+        "computerProgram" => item.company = item.publisher.take(),
+        "dataset" => item.repository = item.publisher.take(),
+        "case" => {
+            item.court = text(csl, "authority");
+            item.reporter = item.journal.take();
+            item.reporter_volume = text(csl, "volume");
+            item.first_page = item.pages.take();
+            item.docket_number = text(csl, "number");
+            item.jurisdiction = text(csl, "jurisdiction");
+        }
+        "statute" => {
+            item.code = item.journal.take();
+            item.code_number = text(csl, "volume");
+            item.public_law_number = text(csl, "number");
+            item.section = text(csl, "section");
+            item.session = text(csl, "chapter-number");
+            item.jurisdiction = text(csl, "jurisdiction");
+        }
+        "bill" => {
+            item.code = item.journal.take();
+            item.code_volume = text(csl, "volume");
+            item.code_pages = item.pages.take();
+            item.bill_number = text(csl, "number");
+            item.legislative_body = text(csl, "authority");
+            item.session = text(csl, "chapter-number");
+            item.jurisdiction = text(csl, "jurisdiction");
+        }
+        "report" => {
+            item.institution = item.publisher.take();
+            item.report_number = text(csl, "number");
+            item.report_type = text(csl, "genre");
+        }
+        _ => {}
+    }
+
+    // Better BibTeX puts the citekey in `id` (newer versions also in `citation-key`),
+    // Zotero's own exporter uses the item's URI, which we can turn into links back to it
+    let id = text(csl, "id").unwrap_or_default();
+    match text(csl, "citation-key") {
+        Some(citekey) => item.id = citekey,
+        None if !id.contains('/') => item.id = id.clone(),
+        None => {}
+    }
+    if let Some(select) = select_link(&id) {
+        item.uri = Some(id);
+        item.select = Some(select);
+    }
+    item
+}
+
+fn text(csl: &Map<String, Value>, field: &str) -> Option<String> {
+    match csl.get(field)? {
+        Value::String(value) if !value.trim().is_empty() => Some(value.trim().to_string()),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+fn creators(csl: &Map<String, Value>) -> Vec<Creator> {
+    CREATOR_TYPES
+        .iter()
+        .flat_map(|(variable, creator_type)| {
+            csl.get(*variable)
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_object)
+                .map(move |name| creator(name, creator_type))
+        })
+        .collect()
+}
+
+fn creator(name: &Map<String, Value>, creator_type: &str) -> Creator {
+    let particle = text(name, "non-dropping-particle");
+    Creator {
+        creator_type: Some(creator_type.to_string()),
+        first_name: text(name, "given"),
+        last_name: text(name, "family").map(|family| match &particle {
+            Some(particle) => format!("{} {}", particle, family),
+            None => family,
+        }),
+        name: text(name, "literal"),
+    }
+}
+
+// `{"date-parts": [[2019, 3, 5]]}`, or the `raw`/`literal` string when there are no parts
+fn date(issued: &Value) -> Option<String> {
+    let parts: Vec<u32> = issued
+        .get("date-parts")
+        .and_then(|parts| parts.get(0))
+        .and_then(Value::as_array)
+        .map(|parts| {
+            parts
+                .iter()
+                .filter_map(|part| match part {
+                    Value::Number(number) => number.as_u64().map(|number| number as u32),
+                    Value::String(number) => number.trim().parse().ok(),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    match parts.as_slice() {
+        [year, month, day, ..] => Some(format!("{:04}-{:02}-{:02}", year, month, day)),
+        [year, month] => Some(format!("{:04}-{:02}", year, month)),
+        [year] => Some(format!("{:04}", year)),
+        [] => issued
+            .as_object()
+            .and_then(|issued| text(issued, "raw").or_else(|| text(issued, "literal"))),
+    }
+}
+
+// http://zotero.org/users/123/items/ABCD2345 -> zotero://select/library/items/ABCD2345
+pub fn select_link(uri: &str) -> Option<String> {
+    let path = uri
+        .strip_prefix("http://zotero.org/")
+        .or_else(|| uri.strip_prefix("https://zotero.org/"))?;
+    let parts: Vec<&str> = path.split('/').collect();
+    match parts.as_slice() {
+        ["users", .., "items", key] => Some(format!("zotero://select/library/items/{}", key)),
+        ["groups", group, "items", key] => {
+            Some(format!("zotero://select/groups/{}/items/{}", group, key))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn only_item(value: Value) -> Item {
+        read(&value).items.remove(0)
+    }
+
+    #[test]
+    fn recognises_csl_json() {
+        assert!(is_csl_json(&json!([{"type": "book"}, {"type": "article"}])));
+        assert!(is_csl_json(&json!({"type": "book", "title": "One item"})));
+        assert!(!is_csl_json(
+            &json!([{"type": "book"}, {"title": "no type"}])
+        ));
+        // a Better BibTeX export
+        assert!(!is_csl_json(
+            &json!({"config": {}, "collections": {}, "items": [{"type": "book"}]})
+        ));
+        assert!(!is_csl_json(&json!({"type": "book", "items": []})));
+        assert!(!is_csl_json(&json!("book")));
+
+        assert_eq!(
+            read(&json!([{"type": "book"}, {"type": "book"}]))
+                .items
+                .len(),
+            2
+        );
+        assert_eq!(read(&json!({"type": "book"})).items.len(), 1);
+    }
+
+    #[test]
+    fn reads_dates() {
+        let issued = |issued: Value| date(&issued);
+        assert_eq!(
+            issued(json!({"date-parts": [[2019, 3, 5]]})),
+            Some("2019-03-05".to_string())
+        );
+        assert_eq!(
+            issued(json!({"date-parts": [["2019", "3"]]})),
+            Some("2019-03".to_string())
+        );
+        assert_eq!(
+            issued(json!({"date-parts": [[2019]]})),
+            Some("2019".to_string())
+        );
+        assert_eq!(
+            issued(json!({"raw": "Spring 2019"})),
+            Some("Spring 2019".to_string())
+        );
+        assert_eq!(
+            issued(json!({"date-parts": [[]], "literal": "n.d."})),
+            Some("n.d.".to_string())
+        );
+        assert_eq!(issued(json!({})), None);
+    }
+
+    #[test]
+    fn reads_creators() {
+        let item = only_item(json!({
+            "type": "book",
+            "author": [
+                {"family": "Beethoven", "given": "Ludwig", "non-dropping-particle": "van"},
+                {"literal": "World Health Organization"}
+            ],
+            "editor": [{"family": "Lee", "given": "Ann"}]
+        }));
+        let creators: Vec<String> = item
+            .creators
+            .iter()
+            .map(|creator| {
+                format!(
+                    "{}: {} | {} | {}",
+                    creator.creator_type.as_deref().unwrap_or(""),
+                    creator.last_name.as_deref().unwrap_or(""),
+                    creator.first_name.as_deref().unwrap_or(""),
+                    creator.name.as_deref().unwrap_or("")
+                )
+            })
+            .collect();
+        assert_eq!(
+            creators,
+            [
+                "author: van Beethoven | Ludwig | ",
+                "author:  |  | World Health Organization",
+                "editor: Lee | Ann | "
+            ]
+        );
+    }
+
+    #[test]
+    fn links_back_to_zotero() {
+        assert_eq!(
+            select_link("http://zotero.org/users/123/items/ABCD2345"),
+            Some("zotero://select/library/items/ABCD2345".to_string())
+        );
+        assert_eq!(
+            select_link("https://zotero.org/users/local/x1y2/items/ABCD2345"),
+            Some("zotero://select/library/items/ABCD2345".to_string())
+        );
+        assert_eq!(
+            select_link("http://zotero.org/groups/456/items/WXYZ6789"),
+            Some("zotero://select/groups/456/items/WXYZ6789".to_string())
+        );
+        assert_eq!(select_link("smith2020"), None);
+
+        // Zotero's own export has the URI as its id, Better BibTeX the citekey
+        let item =
+            only_item(json!({"type": "book", "id": "http://zotero.org/groups/456/items/WXYZ6789"}));
+        assert_eq!(item.id, "");
+        assert_eq!(
+            item.select.as_deref(),
+            Some("zotero://select/groups/456/items/WXYZ6789")
+        );
+        let item = only_item(json!({"type": "book", "id": "smith2020"}));
+        assert_eq!((item.id.as_str(), item.uri), ("smith2020", None));
+        let item = only_item(json!({"type": "book", "id": "x", "citation-key": "lee2019"}));
+        assert_eq!(item.id, "lee2019");
+    }
+
+    #[test]
+    fn moves_fields_to_where_zotero_keeps_them() {
+        let chapter = only_item(json!({
+            "type": "chapter",
+            "container-title": "The Collection",
+            "publisher": "Springer"
+        }));
+        assert_eq!(chapter.item_type, "bookSection");
+        assert_eq!(chapter.book_title.as_deref(), Some("The Collection"));
+        assert_eq!(chapter.journal, None);
+        assert_eq!(chapter.publisher.as_deref(), Some("Springer"));
+
+        let case = only_item(json!({
+            "type": "legal_case",
+            "authority": "Supreme Court",
+            "container-title": "U.S.",
+            "volume": 347,
+            "page": "483",
+            "number": "1"
+        }));
+        assert_eq!(case.item_type, "case");
+        assert_eq!(case.court.as_deref(), Some("Supreme Court"));
+        assert_eq!(case.reporter.as_deref(), Some("U.S."));
+        assert_eq!(case.reporter_volume.as_deref(), Some("347"));
+        assert_eq!(case.first_page.as_deref(), Some("483"));
+        assert_eq!(case.docket_number.as_deref(), Some("1"));
+        assert_eq!((case.journal, case.pages), (None, None));
+
+        // types Zotero has no name for keep their CSL name
+        assert_eq!(only_item(json!({"type": "figure"})).item_type, "figure");
+    }
+}
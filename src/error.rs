@@ -0,0 +1,136 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use super::json_parser::Item;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Library {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    Config {
+        path: PathBuf,
+        message: String,
+    },
+    Database {
+        path: PathBuf,
+        source: rusqlite::Error,
+    },
+    Http {
+        url: String,
+        message: String,
+    },
+    // a library file in a plain text format that could not be parsed
+    Syntax {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    MissingField {
+        citekey: String,
+        item_type: String,
+        field: &'static str,
+    },
+    InvalidField {
+        citekey: String,
+        item_type: String,
+        field: &'static str,
+        reason: String,
+    },
+}
+
+impl Error {
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Error {
+        Error::Io {
+            path: path.into(),
+            source,
+        }
+    }
+
+    pub fn missing(item: &Item, field: &'static str) -> Error {
+        Error::MissingField {
+            citekey: item.id.clone(),
+            item_type: item.item_type.clone(),
+            field,
+        }
+    }
+
+    pub fn invalid(item: &Item, field: &'static str, reason: &str) -> Error {
+        Error::InvalidField {
+            citekey: item.id.clone(),
+            item_type: item.item_type.clone(),
+            field,
+            reason: reason.to_string(),
+        }
+    }
+
+    // the item an error is about, if it is about one
+    pub fn citekey(&self) -> Option<&String> {
+        match self {
+            Error::MissingField { citekey, .. } | Error::InvalidField { citekey, .. } => {
+                Some(citekey)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Library { path, source } => {
+                write!(f, "failed to parse {}: {}", path.display(), source)
+            }
+            Error::Config { path, message } => write!(f, "{}: {}", path.display(), message),
+            Error::Database { path, source } => {
+                write!(f, "failed to read {}: {}", path.display(), source)
+            }
+            Error::Http { url, message } => write!(f, "{}: {}", url, message),
+            Error::Syntax {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+            Error::MissingField {
+                citekey,
+                item_type,
+                field,
+            } => write!(f, "{} ({}): missing field `{}`", citekey, item_type, field),
+            Error::InvalidField {
+                citekey,
+                item_type,
+                field,
+                reason,
+            } => write!(
+                f,
+                "{} ({}): invalid field `{}`: {}",
+                citekey, item_type, field, reason
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Library { source, .. } => Some(source),
+            Error::Database { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+// turns an optional item field into a value or a `MissingField` error
+pub fn required(item: &Item, field: &'static str, value: &Option<String>) -> Result<String> {
+    value.clone().ok_or_else(|| Error::missing(item, field))
+}
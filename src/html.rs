@@ -0,0 +1,792 @@
+use regex::Regex;
+use serde_json::Value;
+use std::sync::OnceLock;
+
+use super::csl_json::select_link;
+
+// Zotero keeps notes as HTML. They are turned into Markdown that keeps what the note was
+// written with: headings, paragraphs, emphasis, nested lists, quotes, code, tables, links
+// and citations. Anything Markdown has no syntax for is kept as its text.
+
+// elements that have no content and no closing tag
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "wbr",
+];
+
+// elements that start a block of their own
+const BLOCKS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "ul",
+];
+
+// elements whose content is never shown
+const HIDDEN: &[&str] = &["head", "script", "style", "template", "title"];
+
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+impl Element {
+    fn new(name: &str, attributes: Vec<(String, String)>) -> Element {
+        Element {
+            name: name.to_string(),
+            attributes,
+            children: Vec::new(),
+        }
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn has_class(&self, class: &str) -> bool {
+        self.attribute("class")
+            .is_some_and(|classes| classes.split_whitespace().any(|name| name == class))
+    }
+
+    fn is_block(&self) -> bool {
+        BLOCKS.contains(&self.name.as_str())
+    }
+
+    // the child elements, skipping text
+    fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|child| match child {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+}
+
+fn tag_pattern() -> &'static Regex {
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    TAG.get_or_init(|| {
+        Regex::new(
+            r#"^<(/?)([a-zA-Z][a-zA-Z0-9-]*)((?:\s+[^\s"'/>=]+(?:\s*=\s*(?:"[^"]*"|'[^']*'|[^\s"'>]+))?)*)\s*(/?)>"#,
+        )
+        .unwrap()
+    })
+}
+
+fn attribute_pattern() -> &'static Regex {
+    static ATTRIBUTE: OnceLock<Regex> = OnceLock::new();
+    ATTRIBUTE.get_or_init(|| {
+        Regex::new(r#"([^\s"'/>=]+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+)))?"#).unwrap()
+    })
+}
+
+pub fn to_markdown(html: &str) -> String {
+    // notes that came in as plain text, e.g. a BibTeX annotation, are kept as they are
+    if !html.contains('<') {
+        return html.trim().to_string();
+    }
+    let root = parse(html);
+    blocks(&root.children).join("\n\n")
+}
+
+// Reads the HTML into a tree the way a browser would for the markup Zotero writes:
+// missing end tags are implied, stray ones are ignored.
+fn parse(html: &str) -> Element {
+    let mut open = vec![Element::new("", Vec::new())];
+    let mut rest = html;
+    while !rest.is_empty() {
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+        } else if let Some(tag) = tag_pattern().captures(rest) {
+            let name = tag[2].to_lowercase();
+            if &tag[1] == "/" {
+                close(&mut open, &name);
+            } else {
+                let attributes = attribute_pattern()
+                    .captures_iter(&tag[3])
+                    .map(|attribute| {
+                        let value = attribute
+                            .get(2)
+                            .or_else(|| attribute.get(3))
+                            .or_else(|| attribute.get(4))
+                            .map_or("", |value| value.as_str());
+                        (attribute[1].to_lowercase(), decode_entities(value))
+                    })
+                    .collect();
+                imply_end_tags(&mut open, &name);
+                let element = Element::new(&name, attributes);
+                if VOID.contains(&name.as_str()) || &tag[4] == "/" {
+                    append(&mut open, Node::Element(element));
+                } else {
+                    open.push(element);
+                }
+            }
+            rest = &rest[tag[0].len()..];
+        } else {
+            // a `<` that starts no tag is text, and so is everything up to the next `<`
+            let first = rest.chars().next().map_or(1, char::len_utf8);
+            let end = rest[first..]
+                .find('<')
+                .map_or(rest.len(), |end| end + first);
+            append(&mut open, Node::Text(decode_entities(&rest[..end])));
+            rest = &rest[end..];
+        }
+    }
+    while open.len() > 1 {
+        pop(&mut open);
+    }
+    open.pop().unwrap()
+}
+
+fn append(open: &mut [Element], node: Node) {
+    open.last_mut().unwrap().children.push(node);
+}
+
+fn pop(open: &mut Vec<Element>) {
+    let element = open.pop().unwrap();
+    append(open, Node::Element(element));
+}
+
+// closes the innermost open element with this name and everything opened inside it
+fn close(open: &mut Vec<Element>, name: &str) {
+    if let Some(index) = open
+        .iter()
+        .skip(1)
+        .rposition(|element| element.name == name)
+    {
+        while open.len() > index + 1 {
+            pop(open);
+        }
+    }
+}
+
+// closes the elements that a new `name` ends without saying so, e.g. `<li>` ends the
+// item before it and any block ends an open paragraph
+fn imply_end_tags(open: &mut Vec<Element>, name: &str) {
+    let (ends, within): (&[&str], &[&str]) = match name {
+        "li" => (&["li"], &["ol", "ul", "table"]),
+        "dt" | "dd" => (&["dt", "dd"], &["dl", "table"]),
+        "td" | "th" => (&["td", "th"], &["tr", "table"]),
+        "tr" => (&["tr"], &["table"]),
+        "thead" | "tbody" | "tfoot" => (&["thead", "tbody", "tfoot"], &["table"]),
+        _ if BLOCKS.contains(&name) => (&["p"], &["li", "dd", "td", "th", "blockquote", "div"]),
+        _ => return,
+    };
+    for index in (1..open.len()).rev() {
+        let open_name = open[index].name.as_str();
+        if ends.contains(&open_name) {
+            while open.len() > index {
+                pop(open);
+            }
+            return;
+        }
+        if within.contains(&open_name) {
+            return;
+        }
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 32)
+            .and_then(|end| Some((entity(&rest[1..=end])?, end + 2)));
+        match entity {
+            Some((character, length)) => {
+                decoded.push(character);
+                rest = &rest[length..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "laquo" => '«',
+        "raquo" => '»',
+        "middot" => '·',
+        "bull" => '•',
+        "copy" => '©',
+        "reg" => '®',
+        "deg" => '°',
+        "times" => '×',
+        _ => return None,
+    })
+}
+
+// the Markdown blocks of a container, e.g. the note itself, a list item or a quote
+fn blocks(children: &[Node]) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut line = String::new();
+    for child in children {
+        match child {
+            Node::Element(element) if element.is_block() => {
+                push_paragraph(&mut blocks, &mut line);
+                blocks.extend(block(element));
+            }
+            _ => inline(child, &mut line),
+        }
+    }
+    push_paragraph(&mut blocks, &mut line);
+    blocks
+}
+
+fn block(element: &Element) -> Vec<String> {
+    match element.name.as_str() {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = element.name[1..].parse().unwrap_or(1);
+            let title = inline_text(&element.children).replace('\n', " ");
+            if title.is_empty() {
+                return Vec::new();
+            }
+            vec![format!("{} {}", "#".repeat(level), title)]
+        }
+        "blockquote" => {
+            let quote = blocks(&element.children).join("\n\n");
+            if quote.is_empty() {
+                return Vec::new();
+            }
+            let lines: Vec<String> = quote
+                .lines()
+                .map(|line| match line {
+                    "" => ">".to_string(),
+                    line => format!("> {}", line),
+                })
+                .collect();
+            vec![lines.join("\n")]
+        }
+        "ul" | "ol" => non_empty(list(element)),
+        "pre" => non_empty(code_block(element)),
+        "table" => non_empty(table(element)),
+        "hr" => vec!["---".to_string()],
+        _ => blocks(&element.children),
+    }
+}
+
+fn non_empty(block: String) -> Vec<String> {
+    if block.is_empty() {
+        Vec::new()
+    } else {
+        vec![block]
+    }
+}
+
+// Items are written tight, one line each, with what belongs to an item (its other
+// paragraphs and nested lists) indented under its marker.
+fn list(element: &Element) -> String {
+    let ordered = element.name == "ol";
+    let mut number: u64 = element
+        .attribute("start")
+        .and_then(|start| start.trim().parse().ok())
+        .unwrap_or(1);
+    let mut items: Vec<String> = Vec::new();
+    for child in element.elements() {
+        match child.name.as_str() {
+            "li" => {
+                let marker = if ordered {
+                    format!("{}. ", number)
+                } else {
+                    "- ".to_string()
+                };
+                number += 1;
+                let item = blocks(&child.children).join("\n");
+                items.push(indent(&marker, &item));
+            }
+            // a list put straight into a list instead of into an item belongs to the
+            // item before it
+            "ul" | "ol" => {
+                let nested = list(child);
+                match items.last_mut() {
+                    Some(item) if !nested.is_empty() => {
+                        let width = item.find(' ').map_or(2, |space| space + 1);
+                        item.push('\n');
+                        item.push_str(&indent(&" ".repeat(width), &nested));
+                    }
+                    _ => items.push(nested),
+                }
+            }
+            _ => {}
+        }
+    }
+    items.join("\n")
+}
+
+// puts `marker` before the first line and lines the others up under it
+fn indent(marker: &str, text: &str) -> String {
+    if text.is_empty() {
+        return marker.trim_end().to_string();
+    }
+    let padding = " ".repeat(marker.chars().count());
+    text.lines()
+        .enumerate()
+        .map(|(index, line)| match (index, line) {
+            (0, line) => format!("{}{}", marker, line),
+            (_, "") => String::new(),
+            (_, line) => format!("{}{}", padding, line),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn code_block(element: &Element) -> String {
+    let code = text_content(&element.children);
+    let code = code.strip_prefix('\n').unwrap_or(&code).trim_end();
+    if code.is_empty() {
+        return String::new();
+    }
+    // <pre><code class="language-rust">
+    let language = element
+        .elements()
+        .filter(|child| child.name == "code")
+        .filter_map(|child| child.attribute("class"))
+        .flat_map(str::split_whitespace)
+        .find_map(|class| class.strip_prefix("language-"))
+        .unwrap_or("");
+    let fence = "`".repeat(longest_run(code, '`').max(2) + 1);
+    format!("{}{}\n{}\n{}", fence, language, code, fence)
+}
+
+// The first row is the header, which is what Markdown tables need and what tables in
+// notes almost always have. Cells keep their inline formatting on a single line.
+fn table(element: &Element) -> String {
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    collect_rows(element, &mut rows);
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        return String::new();
+    }
+    let line = |cells: &[String]| {
+        let cells: Vec<&str> = (0..columns)
+            .map(|column| cells.get(column).map_or("", String::as_str))
+            .collect();
+        format!("| {} |", cells.join(" | "))
+    };
+    let mut lines = vec![line(&rows[0]), line(&vec!["---".to_string(); columns])];
+    lines.extend(rows[1..].iter().map(|row| line(row)));
+    lines.join("\n")
+}
+
+// the rows of a table, looking through `thead`, `tbody` and `tfoot` but not into tables
+// inside its cells
+fn collect_rows(element: &Element, rows: &mut Vec<Vec<String>>) {
+    for child in element.elements() {
+        match child.name.as_str() {
+            "tr" => rows.push(
+                child
+                    .elements()
+                    .filter(|cell| cell.name == "td" || cell.name == "th")
+                    .map(|cell| {
+                        blocks(&cell.children)
+                            .join("<br>")
+                            .replace('\n', "<br>")
+                            .replace('|', "\\|")
+                    })
+                    .collect(),
+            ),
+            "table" => {}
+            _ => collect_rows(child, rows),
+        }
+    }
+}
+
+fn inline_text(children: &[Node]) -> String {
+    let mut text = String::new();
+    children.iter().for_each(|child| inline(child, &mut text));
+    clean_lines(&text)
+}
+
+fn inline(node: &Node, out: &mut String) {
+    let element = match node {
+        Node::Text(text) => return push_text(out, &escape(&collapse(text))),
+        Node::Element(element) => element,
+    };
+    match element.name.as_str() {
+        "br" => {
+            while out.ends_with(' ') {
+                out.pop();
+            }
+            out.push('\n');
+        }
+        "strong" | "b" => wrap(out, element, "**", "**"),
+        "em" | "i" | "cite" => wrap(out, element, "*", "*"),
+        "s" | "del" | "strike" => wrap(out, element, "~~", "~~"),
+        "mark" => wrap(out, element, "==", "=="),
+        // Markdown has none of these, Obsidian shows them as HTML
+        "u" => wrap(out, element, "<u>", "</u>"),
+        "sup" => wrap(out, element, "<sup>", "</sup>"),
+        "sub" => wrap(out, element, "<sub>", "</sub>"),
+        "code" | "kbd" | "samp" | "tt" => {
+            let code = collapse(&text_content(&element.children));
+            let code = code.trim();
+            if !code.is_empty() {
+                let fence = "`".repeat(longest_run(code, '`') + 1);
+                let padding = if code.starts_with('`') || code.ends_with('`') {
+                    " "
+                } else {
+                    ""
+                };
+                push_text(out, &format!("{0}{1}{2}{1}{0}", fence, padding, code));
+            }
+        }
+        "a" => {
+            let mut text = String::new();
+            element
+                .children
+                .iter()
+                .for_each(|child| inline(child, &mut text));
+            match element.attribute("href").map(str::trim) {
+                Some(href) if !href.is_empty() => {
+                    push_link(out, text.trim(), href);
+                }
+                _ => push_text(out, &text),
+            }
+        }
+        "img" => {
+            if let Some(source) = element.attribute("src").filter(|source| !source.is_empty()) {
+                let alt = escape(&collapse(element.attribute("alt").unwrap_or("")));
+                push_text(out, &format!("![{}]({})", alt.trim(), destination(source)));
+            }
+        }
+        "span" if element.attribute("data-citation").is_some() => citation(element, out),
+        name if HIDDEN.contains(&name) => {}
+        _ => element.children.iter().for_each(|child| inline(child, out)),
+    }
+}
+
+// Zotero keeps what a citation points to in `data-citation`, URL-encoded JSON with one
+// entry per cited item. Each item's text links to the item in Zotero.
+fn citation(element: &Element, out: &mut String) {
+    let links: Vec<Option<String>> = element
+        .attribute("data-citation")
+        .and_then(|data| serde_json::from_str::<Value>(&percent_decode(data)).ok())
+        .and_then(|data| data.get("citationItems").and_then(Value::as_array).cloned())
+        .unwrap_or_default()
+        .iter()
+        .map(|item| {
+            item.get("uris")
+                .and_then(Value::as_array)
+                .and_then(|uris| uris.first())
+                .and_then(Value::as_str)
+                .and_then(select_link)
+        })
+        .collect();
+    if has_citation_items(element) {
+        let mut next = 0;
+        citation_items(&element.children, &links, &mut next, out);
+    } else {
+        let text = inline_text(&element.children);
+        match links.first() {
+            Some(Some(link)) if !text.is_empty() => push_link(out, &text, link),
+            _ => push_text(out, &text),
+        }
+    }
+}
+
+fn has_citation_items(element: &Element) -> bool {
+    element
+        .elements()
+        .any(|child| child.has_class("citation-item") || has_citation_items(child))
+}
+
+// writes the citation's text with the n-th `citation-item` linked to the n-th item
+fn citation_items(children: &[Node], links: &[Option<String>], next: &mut usize, out: &mut String) {
+    for child in children {
+        match child {
+            Node::Element(element) if element.has_class("citation-item") => {
+                let text = inline_text(&element.children);
+                match links.get(*next) {
+                    Some(Some(link)) if !text.is_empty() => push_link(out, &text, link),
+                    _ => push_text(out, &text),
+                }
+                *next += 1;
+            }
+            Node::Element(element) if has_citation_items(element) => {
+                citation_items(&element.children, links, next, out)
+            }
+            _ => inline(child, out),
+        }
+    }
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        let byte = text
+            .get(index + 1..index + 3)
+            .filter(|_| bytes[index] == b'%')
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match byte {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// Emphasis only works when the markers touch the text, so spaces at the edges of the
+// element are moved outside of them.
+fn wrap(out: &mut String, element: &Element, open: &str, close: &str) {
+    let mut text = String::new();
+    element
+        .children
+        .iter()
+        .for_each(|child| inline(child, &mut text));
+    let trimmed = text.trim();
+    if text.starts_with(char::is_whitespace) {
+        push_text(out, " ");
+    }
+    if !trimmed.is_empty() {
+        push_text(out, &format!("{}{}{}", open, trimmed, close));
+        if text.ends_with(char::is_whitespace) {
+            push_text(out, " ");
+        }
+    }
+}
+
+fn push_link(out: &mut String, text: &str, href: &str) {
+    let text = if text.is_empty() {
+        escape(href)
+    } else {
+        text.to_string()
+    };
+    push_text(out, &format!("[{}]({})", text, destination(href)));
+}
+
+fn destination(href: &str) -> String {
+    if href.contains([' ', '(', ')', '<', '>']) {
+        format!("<{}>", href.replace('<', "%3C").replace('>', "%3E"))
+    } else {
+        href.to_string()
+    }
+}
+
+// adds text without doubling the space between two pieces of it
+fn push_text(out: &mut String, text: &str) {
+    let text = if out.is_empty() || out.ends_with([' ', '\n']) {
+        text.trim_start_matches(' ')
+    } else {
+        text
+    };
+    out.push_str(text);
+}
+
+fn push_paragraph(blocks: &mut Vec<String>, line: &mut String) {
+    let paragraph = clean_lines(line);
+    line.clear();
+    if !paragraph.is_empty() {
+        blocks.push(paragraph);
+    }
+}
+
+// trims every line of a paragraph and keeps lines that start like a heading, list or
+// quote from becoming one
+fn clean_lines(text: &str) -> String {
+    static LINE_START: OnceLock<Regex> = OnceLock::new();
+    static NUMBERED: OnceLock<Regex> = OnceLock::new();
+    let line_start = LINE_START
+        .get_or_init(|| Regex::new(r"^(#{1,6}(\s|$)|>|[-+=](\s|$)|-{2,}\s*$|={2,}\s*$)").unwrap());
+    let numbered = NUMBERED.get_or_init(|| Regex::new(r"^(\d+)([.)])(\s|$)").unwrap());
+    text.trim()
+        .lines()
+        .map(str::trim)
+        .map(|line| {
+            if line_start.is_match(line) {
+                format!("\\{}", line)
+            } else {
+                numbered.replace(line, "$1\\$2$3").into_owned()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// HTML shows any run of whitespace as a single space
+fn collapse(text: &str) -> String {
+    let mut collapsed = String::new();
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !collapsed.ends_with(' ') {
+                collapsed.push(' ');
+            }
+        } else {
+            collapsed.push(c);
+        }
+    }
+    collapsed
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if "\\`*_[]<".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// the text as it is, for code
+fn text_content(children: &[Node]) -> String {
+    let mut text = String::new();
+    for child in children {
+        match child {
+            Node::Text(content) => text.push_str(content),
+            Node::Element(element) if element.name == "br" => text.push('\n'),
+            Node::Element(element) => text.push_str(&text_content(&element.children)),
+        }
+    }
+    text
+}
+
+fn longest_run(text: &str, character: char) -> usize {
+    text.split(|c| c != character)
+        .map(str::len)
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_structure_of_a_note() {
+        let note = r#"<div data-schema-version="8"><h1>Reading notes</h1>
+<p>The <strong>main</strong> claim is <em>not</em> new &amp; see <a href="https://example.org/a b">this post</a>.<br>Second line</p>
+<ul>
+<li>First point
+<ul><li>nested <code>x_1</code></li><li>another</li></ul>
+</li>
+<li>Second point</li>
+</ul>
+<ol start="3"><li>three<li>four</ol>
+<blockquote><p>Quoted</p><p>text</p></blockquote>
+<pre><code class="language-python">if a &lt; b:
+    print("*")</code></pre>
+<p>1. not a list, # not a heading</p>
+</div>"#;
+        assert_eq!(
+            to_markdown(note),
+            "# Reading notes\n\n\
+             The **main** claim is *not* new & see [this post](<https://example.org/a b>).\n\
+             Second line\n\n\
+             - First point\n  - nested `x_1`\n  - another\n- Second point\n\n\
+             3. three\n4. four\n\n\
+             > Quoted\n>\n> text\n\n\
+             ```python\nif a < b:\n    print(\"*\")\n```\n\n\
+             1\\. not a list, # not a heading"
+        );
+    }
+
+    #[test]
+    fn keeps_multibyte_text_after_tags_and_stray_brackets() {
+        assert_eq!(
+            to_markdown("<p>x<span>\u{a0}é</span> a < b<em>“quoted”</em> x <é</p>"),
+            "x é a \\< b*“quoted”* x \\<é"
+        );
+    }
+
+    #[test]
+    fn writes_tables() {
+        let note = "<table><thead><tr><th>Model</th><th>Score</th></tr></thead>\
+                    <tbody><tr><td><b>A</b></td><td>1 | 2</td></tr><tr><td>B</td></tr></tbody></table>";
+        assert_eq!(
+            to_markdown(note),
+            "| Model | Score |\n| --- | --- |\n| **A** | 1 \\| 2 |\n| B |  |"
+        );
+    }
+
+    #[test]
+    fn links_citations_to_zotero() {
+        let data = "%7B%22citationItems%22%3A%5B%7B%22uris%22%3A%5B%22http%3A%2F%2Fzotero.org%2Fusers%2F123%2Fitems%2FABCD2345%22%5D%7D%2C%7B%22uris%22%3A%5B%22http%3A%2F%2Fzotero.org%2Fgroups%2F9%2Fitems%2FWXYZ6789%22%5D%7D%5D%2C%22properties%22%3A%7B%7D%7D";
+        let note = format!(
+            "<p>As shown <span class=\"citation\" data-citation=\"{}\">(<span class=\"citation-item\">Smith, 2020</span>; <span class=\"citation-item\">Lee, 2021</span>)</span>.</p>",
+            data
+        );
+        assert_eq!(
+            to_markdown(&note),
+            "As shown ([Smith, 2020](zotero://select/library/items/ABCD2345); \
+             [Lee, 2021](zotero://select/groups/9/items/WXYZ6789))."
+        );
+    }
+
+    #[test]
+    fn keeps_plain_text_notes_as_they_are() {
+        assert_eq!(
+            to_markdown("Read *twice*.\nThen summarise."),
+            "Read *twice*.\nThen summarise."
+        );
+    }
+}
@@ -1,12 +1,20 @@
-use std::collections::HashMap;
+use chrono::NaiveDate;
+use serde::de::{DeserializeOwned, Deserializer};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::path::Path;
 
-
+use super::citekey;
 
 // use option type to get every single possible thing you want
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Item {
-    #[serde(rename(serialize = "citationKey", deserialize = "citationKey"))]
+    #[serde(
+        rename(serialize = "citationKey", deserialize = "citationKey"),
+        default
+    )]
     pub id: String,
     #[serde(rename(serialize = "itemType", deserialize = "itemType"))]
     pub item_type: String,
@@ -27,8 +35,72 @@ pub struct Item {
     pub published_date: Option<String>,
     #[serde(rename(serialize = "publicationTitle", deserialize = "publicationTitle"))]
     pub journal: Option<String>,
+    #[serde(rename(serialize = "bookTitle", deserialize = "bookTitle"))]
+    pub book_title: Option<String>,
+    #[serde(rename(serialize = "proceedingsTitle", deserialize = "proceedingsTitle"))]
+    pub proceedings_title: Option<String>,
+    #[serde(rename(serialize = "conferenceName", deserialize = "conferenceName"))]
+    pub conference_name: Option<String>,
+    pub publisher: Option<String>,
+    pub place: Option<String>,
+    pub pages: Option<String>,
+    pub university: Option<String>,
+    #[serde(rename(serialize = "thesisType", deserialize = "thesisType"))]
+    pub thesis_type: Option<String>,
+    pub institution: Option<String>,
+    #[serde(rename(serialize = "reportNumber", deserialize = "reportNumber"))]
+    pub report_number: Option<String>,
+    #[serde(rename(serialize = "reportType", deserialize = "reportType"))]
+    pub report_type: Option<String>,
+    #[serde(rename(serialize = "seriesTitle", deserialize = "seriesTitle"))]
+    pub series_title: Option<String>,
+    #[serde(rename(serialize = "episodeNumber", deserialize = "episodeNumber"))]
+    pub episode_number: Option<String>,
+    #[serde(rename(serialize = "runningTime", deserialize = "runningTime"))]
+    pub running_time: Option<String>,
+    pub section: Option<String>,
+    pub edition: Option<String>,
+    #[serde(rename(serialize = "archiveID", deserialize = "archiveID"))]
+    pub archive_id: Option<String>,
+    pub repository: Option<String>,
+    pub extra: Option<String>,
+    pub court: Option<String>,
+    pub reporter: Option<String>,
+    #[serde(rename(serialize = "reporterVolume", deserialize = "reporterVolume"))]
+    pub reporter_volume: Option<String>,
+    #[serde(rename(serialize = "firstPage", deserialize = "firstPage"))]
+    pub first_page: Option<String>,
+    #[serde(rename(serialize = "docketNumber", deserialize = "docketNumber"))]
+    pub docket_number: Option<String>,
+    pub jurisdiction: Option<String>,
+    pub code: Option<String>,
+    #[serde(rename(serialize = "codeNumber", deserialize = "codeNumber"))]
+    pub code_number: Option<String>,
+    #[serde(rename(serialize = "codeVolume", deserialize = "codeVolume"))]
+    pub code_volume: Option<String>,
+    #[serde(rename(serialize = "codePages", deserialize = "codePages"))]
+    pub code_pages: Option<String>,
+    #[serde(rename(serialize = "publicLawNumber", deserialize = "publicLawNumber"))]
+    pub public_law_number: Option<String>,
+    #[serde(rename(serialize = "billNumber", deserialize = "billNumber"))]
+    pub bill_number: Option<String>,
+    #[serde(rename(serialize = "legislativeBody", deserialize = "legislativeBody"))]
+    pub legislative_body: Option<String>,
+    pub session: Option<String>,
+    #[serde(rename(serialize = "versionNumber", deserialize = "versionNumber"))]
+    pub version_number: Option<String>,
+    #[serde(rename(serialize = "programmingLanguage", deserialize = "programmingLanguage"))]
+    pub programming_language: Option<String>,
+    pub system: Option<String>,
+    pub company: Option<String>,
+    pub rights: Option<String>,
+    // URIs of related items, and for preprints sometimes the published version
+    #[serde(default, deserialize_with = "relation_uris")]
+    pub relations: Vec<String>,
     pub tags: Vec<Tag>,
-    pub notes: Vec<Note>
+    pub notes: Vec<Note>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
 }
 
 impl fmt::Display for Item {
@@ -37,8 +109,6 @@ impl fmt::Display for Item {
     }
 }
 
-
-
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Creator {
@@ -50,16 +120,24 @@ pub struct Creator {
 
 impl fmt::Display for Creator {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut output:String = "".to_string();
+        let mut output: String = "".to_string();
         if self.creator_type.is_some() {
-            output.push_str(&format!("creator_type: \"{}\", ", self.creator_type.clone().unwrap()));
+            output.push_str(&format!(
+                "creator_type: \"{}\", ",
+                self.creator_type.clone().unwrap()
+            ));
         }
         if self.first_name.is_some() && self.last_name.is_some() {
-            output.push_str(&format!("first_name: \"{}\", ", self.first_name.clone().unwrap()));
-            output.push_str(&format!("last_name: \"{}\"", self.last_name.clone().unwrap()));
+            output.push_str(&format!(
+                "first_name: \"{}\", ",
+                self.first_name.clone().unwrap()
+            ));
+            output.push_str(&format!(
+                "last_name: \"{}\"",
+                self.last_name.clone().unwrap()
+            ));
         } else if self.name.is_some() {
             output.push_str(&format!("name: \"{}\"", self.name.clone().unwrap()));
-
         }
         write!(f, "{}", output)
     }
@@ -67,7 +145,7 @@ impl fmt::Display for Creator {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 
 pub struct Tag {
-    pub tag: String
+    pub tag: String,
 }
 
 impl fmt::Display for Tag {
@@ -84,7 +162,7 @@ pub struct Note {
     pub date_modified: String,
     #[serde(rename(serialize = "note", deserialize = "note"))]
     pub content: String,
-    pub uri: String
+    pub uri: String,
 }
 
 impl fmt::Display for Note {
@@ -93,6 +171,13 @@ impl fmt::Display for Note {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Attachment {
+    pub title: Option<String>,
+    pub path: Option<String>,
+    pub url: Option<String>,
+}
+
 fn generate_item_string(item: &Item) -> String {
     format!(
         "---\nresouce_type: {}\nID: {}\n{}{}{}{}{}{}{}{}{}{}{}{}{}\n---\n",
@@ -122,154 +207,227 @@ fn generate_display(label: &str, attr: &Option<String>) -> String {
     "".to_string()
 }
 
-fn display_tags(label: &str, tags: &Vec<Tag>) -> String {
-    let mut output = "tags: [".to_string();
-    if tags.len() > 0 {
-        tags.iter().for_each(|tag| {
-            output.push_str(&format!("\"{}\", \"",&tag.tag));
-        });
-    }
-    if !output.eq("tags: [") {
-        output = output[0..output.len()-3].to_string();
+fn display_tags(label: &str, tags: &[Tag]) -> String {
+    let empty = format!("{}: [", label);
+    let mut output = empty.clone();
+    tags.iter().for_each(|tag| {
+        output.push_str(&format!("\"{}\", \"", &tag.tag));
+    });
+    if output != empty {
+        output = output[0..output.len() - 3].to_string();
     }
     output.push_str("]\n");
     output
 }
 
-fn display_creators(label: &str, creators: &Vec<Creator>) -> String {
-    let mut output = "creators: [".to_string();
-    if creators.len() > 0 {
-        creators.iter().for_each(|creator| {
-            output.push_str(&format!("\n{{ {} }},",&creator));
-        });
-    }
-    output.push_str("]");
+fn display_creators(label: &str, creators: &[Creator]) -> String {
+    let mut output = format!("{}: [", label);
+    creators.iter().for_each(|creator| {
+        output.push_str(&format!("\n{{ {} }},", &creator));
+    });
+    output.push(']');
     output
 }
 
-fn display_notes(label: &str, notes: &Vec<Note>) -> String {
-    let mut output = "\nnotes: [".to_string();
-    if notes.len() > 0 {
-        notes.iter().for_each(|note| {
-            output.push_str(&format!("\n\t{{ {} }},",&note.content));
-        });
-    }
+fn display_notes(label: &str, notes: &[Note]) -> String {
+    let mut output = format!("\n{}: [", label);
+    notes.iter().for_each(|note| {
+        output.push_str(&format!("\n\t{{ {} }},", &note.content));
+    });
     output.push_str("\n]");
     output
 }
 
-
-
 #[derive(Serialize, Deserialize, Debug)]
-
-
 pub struct ZoteroData {
+    #[serde(default)]
     pub collections: HashMap<String, Collection>,
-    pub config: Config,
+    // Better BibTeX's export settings; we only need a few of them, so anything missing
+    // or shaped differently from what we expect is ignored rather than failing the export
+    #[serde(default, deserialize_with = "lenient")]
+    pub config: Option<Config>,
     pub items: Vec<Item>,
 }
 
+impl ZoteroData {
+    pub fn preferences(&self) -> Preferences {
+        self.config
+            .as_ref()
+            .and_then(|config| config.preferences.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn locale_date_order(&self) -> Option<&str> {
+        self.config
+            .as_ref()
+            .and_then(|config| config.locale_date_order.as_deref())
+    }
+
+    // fills in what the export leaves to Zotero's preferences: citekeys for items that
+    // have none, dates in the locale's order and attachment paths relative to the base
+    // attachment directory
+    pub fn apply_preferences(&mut self) {
+        let preferences = self.preferences();
+        let date_order = self.locale_date_order().unwrap_or("ymd").to_string();
+        let citekey_format = preferences
+            .citekey_format
+            .clone()
+            .unwrap_or_else(|| citekey::DEFAULT_FORMAT.to_string());
+
+        let mut taken: HashSet<String> = self
+            .items
+            .iter()
+            .map(|item| item.id.clone())
+            .filter(|id| !id.is_empty())
+            .collect();
+
+        self.items.iter_mut().for_each(|item| {
+            if item.id.is_empty() {
+                item.id = citekey::unique(citekey::generate(item, &citekey_format), &mut taken);
+            }
+            item.published_date = item
+                .published_date
+                .as_ref()
+                .map(|date| normalise_date(date, &date_order));
+            item.attachments.iter_mut().for_each(|attachment| {
+                attachment.path = attachment.path.as_ref().map(|path| {
+                    resolve_attachment_path(path, preferences.base_attachment_path.as_deref())
+                });
+            });
+        });
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Collection {
-    collections: Vec<String>,
-    items: Vec<i32>,
-    key: String,
-    name: String,
-    parent: Option<String>,
+    pub collections: Vec<String>,
+    pub items: Vec<i32>,
+    pub key: String,
+    pub name: String,
+    pub parent: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
 pub struct Config {
-    id: String,
-    label: String,
-    localeDateOrder: String,
-    options: HashMap<String, bool>,
-    preferences: Preferences,
+    #[serde(default, deserialize_with = "lenient")]
+    pub id: Option<String>,
+    #[serde(default, deserialize_with = "lenient")]
+    pub label: Option<String>,
+    #[serde(default, deserialize_with = "lenient")]
+    pub locale_date_order: Option<String>,
+    #[serde(default)]
+    pub options: HashMap<String, Value>,
+    #[serde(default, deserialize_with = "lenient")]
+    pub preferences: Option<Preferences>,
+    #[serde(flatten)]
+    pub other: HashMap<String, Value>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+// Better BibTeX has around 90 preferences and adds, drops or retypes some with most
+// releases. The ones we use are picked out, everything else is kept as raw JSON.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
 pub struct Preferences {
-    DOIandURL: String,
-    ascii: String,
-    asciiBibLaTeX: bool,
-    asciiBibTeX: bool,
-    autoAbbrev: bool,
-    autoAbbrevStyle: String,
-    autoExport: String,
-    autoExportDelay: i32,
-    autoExportIdleWait: i32,
-    autoExportPathReplaceDiacritics: bool,
-    autoExportPathReplaceDirSep: String,
-    autoExportPathReplaceSpace: String,
-    autoPinDelay: i32,
-    automaticTags: bool,
-    auxImport: bool,
-    baseAttachmentPath: String,
-    biblatexExtendedDateFormat: bool,
-    biblatexExtendedNameFormat: bool,
-    biblatexExtractEprint: bool,
-    bibtexParticleNoOp: bool,
-    bibtexURL: String,
-    cacheFlushInterval: i32,
-    citeCommand: String,
-    citekeyFold: bool,
-    citekeyFormat: String,
-    citekeySearch: bool,
-    citeprocNoteCitekey: bool,
-    csquotes: String,
-    debugLogDir: String,
-    exportBibTeXStrings: String,
-    exportBraceProtection: bool,
-    exportTitleCase: bool,
-    extraMergeCSL: bool,
-    extraMergeCitekeys: bool,
-    extraMergeTeX: bool,
-    git: String,
-    ignorePostscriptErrors: bool,
-    import: bool,
-    importBibTeXStrings: bool,
-    importCaseProtection: String,
-    importCitationKey: bool,
-    importExtra: bool,
-    importJabRefAbbreviations: bool,
-    importJabRefStrings: bool,
-    importSentenceCase: String,
-    importUnknownTexCommand: String,
-    itemObserverDelay: i32,
-    jabrefFormat: i32,
-    jieba: bool,
-    keyConflictPolicy: String,
-    keyScope: String,
-    kuroshiro: bool,
-    mapMath: String,
-    mapText: String,
-    mapUnicode: String,
-    newTranslatorsAskRestart: bool,
-    parseParticles: bool,
-    patchDates: String,
-    platform: String,
-    postscript: String,
-    postscriptOverride: String,
-    qualityReport: bool,
-    quickCopyEta: String,
-    quickCopyMode: String,
-    quickCopyOrgMode: String,
-    quickCopyPandocBrackets: bool,
-    quickCopySelectLink: String,
-    rawImports: bool,
-    rawLaTag: String,
-    relativeFilePaths: bool,
-    retainCache: bool,
-    scrubDatabase: bool,
-    skipFields: String,
-    skipWords: String,
-    startupProgress: String,
-    strings: String,
-    testing: bool,
-    verbatimFields: String,
-    warnBulkModify: i32,
-    warnTitleCased: bool,
-    workersCache: bool,
-    workersMax: i32,
+    #[serde(default, deserialize_with = "lenient")]
+    pub citekey_format: Option<String>,
+    #[serde(default, deserialize_with = "lenient")]
+    pub base_attachment_path: Option<String>,
+    #[serde(flatten)]
+    pub other: HashMap<String, Value>,
+}
+
+// a value of the wrong shape becomes `None` instead of an error
+fn lenient<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let value = Value::deserialize(deserializer)?;
+    Ok(serde_json::from_value(value).ok())
+}
+
+// Zotero keeps relations as `{"dc:relation": [uri, ...], "owl:sameAs": uri}`; which
+// predicate links the items doesn't matter to us
+fn relation_uris<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut uris = Vec::new();
+    if let Value::Object(relations) = Value::deserialize(deserializer)? {
+        for (_, value) in relations {
+            match value {
+                Value::String(uri) => uris.push(uri),
+                Value::Array(values) => uris.extend(
+                    values
+                        .into_iter()
+                        .filter_map(|value| value.as_str().map(str::to_string)),
+                ),
+                _ => {}
+            }
+        }
+    }
+    Ok(uris)
+}
+
+// Zotero keeps dates as typed, so `03/04/2020` means different things depending on the
+// locale. Purely numeric dates are turned into ISO dates, anything else is left alone.
+fn normalise_date(date: &str, locale_date_order: &str) -> String {
+    let parts: Vec<&str> = date.trim().split(['/', '.', '-']).collect();
+    if parts.len() != 3 || parts.iter().any(|part| part.parse::<u32>().is_err()) {
+        return date.to_string();
+    }
+    let numbers: Vec<u32> = parts.iter().map(|part| part.parse().unwrap_or(0)).collect();
+    let (year, month, day) = if parts[0].len() == 4 {
+        (numbers[0], numbers[1], numbers[2])
+    } else if locale_date_order.starts_with('m') {
+        (numbers[2], numbers[0], numbers[1])
+    } else {
+        (numbers[2], numbers[1], numbers[0])
+    };
+    match NaiveDate::from_ymd_opt(year as i32, month, day) {
+        Some(parsed) => parsed.format("%Y-%m-%d").to_string(),
+        None => date.to_string(),
+    }
+}
+
+// linked files stored relative to Zotero's base directory come out as
+// `attachments:some/file.pdf`
+fn resolve_attachment_path(path: &str, base_attachment_path: Option<&str>) -> String {
+    let relative = path.strip_prefix("attachments:").unwrap_or(path);
+    match base_attachment_path.filter(|base| !base.is_empty()) {
+        Some(base) if !Path::new(relative).is_absolute() => {
+            Path::new(base).join(relative).display().to_string()
+        }
+        _ => relative.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_numeric_dates_in_the_library_locale() {
+        // day first, as in most of the world
+        assert_eq!(normalise_date("03/04/2020", "dmy"), "2020-04-03");
+        assert_eq!(normalise_date("3.4.2020", "dmy"), "2020-04-03");
+        // month first, as in the US
+        assert_eq!(normalise_date("03/04/2020", "mdy"), "2020-03-04");
+        assert_eq!(normalise_date("12-31-2020", "mdy"), "2020-12-31");
+        // year first reads the same whatever the locale
+        assert_eq!(normalise_date("2020/4/3", "mdy"), "2020-04-03");
+        assert_eq!(normalise_date(" 2020-04-03 ", "dmy"), "2020-04-03");
+        // an unknown or missing locale is taken to be day first
+        assert_eq!(normalise_date("03/04/2020", ""), "2020-04-03");
+    }
+
+    #[test]
+    fn leaves_other_dates_as_typed() {
+        assert_eq!(normalise_date("31/02/2020", "dmy"), "31/02/2020");
+        assert_eq!(normalise_date("12/31/2020", "dmy"), "12/31/2020");
+        assert_eq!(normalise_date("April 2020", "mdy"), "April 2020");
+        assert_eq!(normalise_date("2020-04", "mdy"), "2020-04");
+        assert_eq!(normalise_date("", "dmy"), "");
+    }
 }
@@ -1,87 +1,320 @@
+use std::collections::HashSet;
 use std::fs;
-use std::fs::File;
+use std::io;
 use std::path::Path;
+use std::process;
 extern crate serde;
 extern crate serde_json;
 #[macro_use]
 extern crate serde_derive;
 extern crate chrono;
 
+mod bibtex;
+mod citekey;
+mod cli;
+mod config;
+mod csl_json;
+mod error;
+mod html;
 mod json_parser;
+mod regions;
 mod resource_types;
+mod ris;
+mod rules;
+mod sync;
+mod template;
+mod watch;
+mod zotero_api;
+mod zotero_sqlite;
+use clap::Parser;
+use cli::{Cli, Command, Paths};
+use config::VaultConfig;
+use error::{Error, Result};
 use json_parser::ZoteroData;
-use resource_types::{ReferenceList, References};
+use resource_types::{note_names, ReferenceList, References, Templates};
+use serde_json::Value;
+use sync::NoteSync;
+use template::Template;
 
 //https://medium.com/@nightraiser/read-and-parse-json-with-rust-day-1-of-codedaily-9feab54b29e8
 //https://docs.citationstyles.org/en/stable/specification.html
 
 pub const LIBRARY_PATH: &str = ".library/library.json";
+pub const RESOURCES_PATH: &str = "Resources";
+
+const EXIT_FAILURE: i32 = 1;
+// some items could not be turned into notes, the rest were written
+const EXIT_PARTIAL_FAILURE: i32 = 2;
 
 fn main() {
-    let json_file_path = Path::new(LIBRARY_PATH);
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(code) => process::exit(code),
+        Err(error) => {
+            eprintln!("error: {}", error);
+            process::exit(EXIT_FAILURE);
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<i32> {
+    let config = VaultConfig::load(&cli.config_path())?;
+    let paths = Paths::new(&cli, &config);
+
+    // everything that writes or prints notes loads the templates first, so a broken one
+    // stops it before any note is written
+    let command = cli.command.unwrap_or(Command::Sync { force: false });
+    match command {
+        Command::Sync { force } => {
+            let templates = Templates::load(&paths.templates, &config)?;
+            let references = read_references(&paths, &config)?;
+            let mut sync = if force {
+                NoteSync::forced(&paths.output, &paths.sync_state)
+            } else {
+                NoteSync::new(&paths.output, &paths.sync_state)
+            };
+            sync_notes(&references, &config, &templates, &mut sync, None)?;
+            sync.finish()?.print();
+            Ok(report_failures(&references))
+        }
+        Command::Watch => {
+            let templates = Templates::load(&paths.templates, &config)?;
+            watch::watch(&paths, &config, templates);
+            Ok(0)
+        }
+        Command::Check => {
+            let templates = Templates::load(&paths.templates, &config)?;
+            let references = read_references(&paths, &config)?;
+            let mut sync = NoteSync::dry_run(&paths.output, &paths.sync_state);
+            sync_notes(&references, &config, &templates, &mut sync, None)?;
+            println!("Nothing was written, a sync would do the following:");
+            sync.finish()?.print();
+            Ok(report_failures(&references))
+        }
+        Command::List => {
+            let references = read_references(&paths, &config)?;
+            references.notes().iter().for_each(|note| {
+                println!(
+                    "{}\t{}\t{}",
+                    note.details().id,
+                    note.folder(&config),
+                    note.details().full_title
+                )
+            });
+            Ok(report_failures(&references))
+        }
+        Command::Show { citekey } => {
+            let templates = Templates::load(&paths.templates, &config)?;
+            let references = read_references(&paths, &config)?;
+            if let Some(note) = references
+                .notes()
+                .iter()
+                .find(|note| note.details().id == citekey)
+            {
+                print!("{}", templates.render(*note, &config)?);
+                return Ok(0);
+            }
+            match references
+                .failed
+                .iter()
+                .find(|error| error.citekey() == Some(&citekey))
+            {
+                Some(error) => eprintln!("No note for {}", error),
+                None => eprintln!("No item with citekey {}", citekey),
+            }
+            Ok(EXIT_FAILURE)
+        }
+        Command::Explain { citekey } => explain(&paths, &config, &citekey),
+        Command::CheckTemplates { lenient } => check_templates(&paths, &config, lenient),
+    }
+}
+
+// reads a Better BibTeX JSON export, a CSL-JSON file, a RIS file, a BibTeX file or
+// Zotero's own database, whichever the file turns out to be
+pub fn read_library(path: &Path) -> Result<ZoteroData> {
+    let bytes = fs::read(path).map_err(|error| Error::io(path, error))?;
+    if zotero_sqlite::is_sqlite(&bytes) {
+        let mut zotero_data = zotero_sqlite::read(path)?;
+        zotero_data.apply_preferences();
+        return Ok(zotero_data);
+    }
+    let contents = String::from_utf8(bytes)
+        .map_err(|error| Error::io(path, io::Error::new(io::ErrorKind::InvalidData, error)))?;
+
+    let mut zotero_data = if is_json(&contents) {
+        let library_error = |source| Error::Library {
+            path: path.to_path_buf(),
+            source,
+        };
+        let value: Value = serde_json::from_str(&contents).map_err(library_error)?;
+        if csl_json::is_csl_json(&value) {
+            csl_json::read(&value)
+        } else {
+            serde_json::from_value(value).map_err(library_error)?
+        }
+    } else if ris::is_ris(&contents) {
+        ris::read(&contents)
+    } else {
+        bibtex::read(path, &contents)?
+    };
+    zotero_data.apply_preferences();
+    Ok(zotero_data)
+}
+
+fn is_json(contents: &str) -> bool {
+    contents
+        .trim_start_matches('\u{feff}')
+        .trim_start()
+        .starts_with(['{', '['])
+}
+
+fn read_references(paths: &Paths, config: &VaultConfig) -> Result<References> {
+    let mut zotero_data = read_zotero_data(paths)?;
+    zotero_data
+        .items
+        .retain(|item| !config.skips(&item.item_type));
 
-    let file = File::open(json_file_path).expect("Failed to open file");
+    let mut references = References::new();
+    references.populate(&zotero_data.items, config);
+    Ok(references)
+}
+
+fn read_zotero_data(paths: &Paths) -> Result<ZoteroData> {
+    let zotero_data = match &paths.web_api {
+        Some(web_api) => {
+            let mut zotero_data = zotero_api::read(web_api, &paths.api_cache)?;
+            zotero_data.apply_preferences();
+            zotero_data
+        }
+        None => read_library(&paths.library)?,
+    };
+    Ok(zotero_data)
+}
 
-    let zotero_data: ZoteroData = serde_json::from_reader(file).expect("failed to parse json");
+// walks through the classification rules for one item and says where it ends up
+fn explain(paths: &Paths, config: &VaultConfig, citekey: &str) -> Result<i32> {
+    let mut items = read_zotero_data(paths)?.items;
+    items.retain(|item| item.id == citekey);
+    let item = match items.first() {
+        Some(item) => item,
+        None => {
+            eprintln!("No item with citekey {}", citekey);
+            return Ok(EXIT_FAILURE);
+        }
+    };
+    println!(
+        "{} ({}): {}",
+        item.id,
+        item.item_type,
+        item.title.as_deref().unwrap_or("")
+    );
+    if config.skips(&item.item_type) {
+        println!("  skipped, {} is listed in `skip`", item.item_type);
+        return Ok(0);
+    }
 
+    let (lines, rule) = rules::explain(&config.rules, item);
+    lines.iter().for_each(|line| println!("  {}", line));
+    if rule.is_none() {
+        println!("  no rule matches");
+    }
     let mut references = References::new();
+    references.populate(&items, config);
+    if let Some(note) = references.notes().first() {
+        println!(
+            "=> {} note in {} using {}",
+            note.kind(),
+            note.folder(config),
+            note.details()
+                .template
+                .clone()
+                .unwrap_or_else(|| config.template(note.kind()))
+        );
+        return Ok(0);
+    }
+    if let Some(error) = references.failed.first() {
+        println!("=> no note: {}", error);
+    }
+    Ok(EXIT_PARTIAL_FAILURE)
+}
 
-    references.populate(&zotero_data.items);
+// Goes through every template the vault uses: fields it asks for that its resource type
+// does not have are errors, and so are fields it leaves out unless `lenient`.
+fn check_templates(paths: &Paths, config: &VaultConfig, lenient: bool) -> Result<i32> {
+    let mut failed = false;
+    for (kind, name) in config.template_uses() {
+        let template = match Template::load(&paths.templates, &name) {
+            Ok(template) => template,
+            Err(error) => {
+                println!("{} ({}): {}", name, kind, error);
+                failed = true;
+                continue;
+            }
+        };
+        let context = resource_types::specimen(&kind)?.context(config);
+        let check = template.check(&context);
+        let unused: Vec<String> = context
+            .names()
+            .filter(|field| !check.used.contains(*field))
+            .cloned()
+            .collect();
+        if check.unknown.is_empty() && unused.is_empty() {
+            println!("{} ({}): ok", name, kind);
+            continue;
+        }
+        println!("{} ({}):", name, kind);
+        check
+            .unknown
+            .iter()
+            .for_each(|(line, field)| println!("  line {}: no field `{}`", line, field));
+        if !unused.is_empty() {
+            println!("  not used: {}", unused.join(", "));
+        }
+        failed |= !check.unknown.is_empty() || (!lenient && !unused.is_empty());
+    }
+    Ok(if failed { EXIT_PARTIAL_FAILURE } else { 0 })
+}
 
-    //deletes existing Resource folder if it exists
-    if std::fs::metadata("Resources").is_ok() && std::fs::metadata("Resources").unwrap().is_dir() {
-        fs::remove_dir_all("Resources").expect("failed to removed directory");
-        println!("Deleted old Resources folder and creating a new one");
-    } else {
-        println!("No Resources folder found - creating one now");
+// prints the items that didn't make it into the vault and picks the exit code
+pub fn report_failures(references: &References) -> i32 {
+    let generics = &references.generics.generic_list;
+    if !generics.is_empty() {
+        eprintln!(
+            "{} items have no resource type of their own and went through the generic note:",
+            generics.len()
+        );
+        generics.iter().for_each(|generic| {
+            eprintln!("  {} ({})", generic.resource_details.id, generic.item_type)
+        });
     }
+    if references.failed.is_empty() {
+        return 0;
+    }
+    eprintln!("{} items failed:", references.failed.len());
+    references
+        .failed
+        .iter()
+        .for_each(|error| eprintln!("  {}", error));
+    EXIT_PARTIAL_FAILURE
+}
 
-    fs::create_dir("Resources").expect("failed to create resource directory");
-    fs::create_dir("Resources/Articles").expect("failed to create Articles directory");
-    fs::create_dir("Resources/Academic Papers")
-        .expect("failed to create AcademicPapers directory");
-    fs::create_dir("Resources/Books").expect("failed to create Books directory");
-    fs::create_dir("Resources/TED Talks").expect("failed to create TEDTalks directory");
-    fs::create_dir("Resources/Youtube Videos")
-        .expect("failed to create YoutubeVideos directory");
-
-    references.articles.article_list.iter().for_each(|article| {
-        fs::write(
-            &format!("Resources/Articles/{}.md", article.resource_details.id),
-            article.to_string(),
-        )
-        .expect("failed to create article notes");
-    });
-
-    references.academic_papers.academic_paper_list.iter().for_each(|academic_paper| {
-        fs::write(
-            &format!("Resources/Academic Papers/{}.md", academic_paper.resource_details.id),
-            academic_paper.to_string(),
-        )
-        .expect("failed to create academic paper notes");
-    });
-
-    references.books.book_list.iter().for_each(|book| {
-        fs::write(
-            &format!("Resources/Books/{}.md", book.resource_details.id),
-            book.to_string(),
-        )
-        .expect("failed to create book notes");
-    });
-
-    references.ted_talks.ted_talk_list.iter().for_each(|ted_talk| {
-        fs::write(
-            &format!("Resources/TED Talks/{}.md", ted_talk.resource_details.id),
-            ted_talk.to_string(),
-        )
-        .expect("failed to create TED Talk notes.md");
-    });
-
-    references.youtube_videos.youtube_video_list.iter().for_each(|youtube_video| {
-        fs::write(
-            &format!("Resources/Youtube Videos/{}.md", youtube_video.resource_details.id),
-            youtube_video.to_string(),
-        )
-        .expect("failed to create Youtube video notes");
-    });
+// writes every note, or when `affected` is given only the notes of those citekeys; the
+// rest are carried over untouched so they are not retired
+pub fn sync_notes(
+    references: &References,
+    config: &VaultConfig,
+    templates: &Templates,
+    sync: &mut NoteSync,
+    affected: Option<&HashSet<String>>,
+) -> Result<()> {
+    let notes = references.notes();
+    for (note, (folder, file_name)) in notes.iter().zip(note_names(&notes, config)) {
+        let citekey = &note.details().id;
+        if affected.is_none_or(|affected| affected.contains(citekey)) {
+            sync.note(&folder, &file_name, &templates.render(*note, config)?)?;
+        } else {
+            sync.keep(&folder, &file_name);
+        }
+    }
+    Ok(())
 }
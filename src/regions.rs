@@ -0,0 +1,155 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+// Templates mark the parts of a note the tool owns with a pair of marker lines, e.g.
+//
+//   %% zotero:begin links %%
+//   - [Open in Zotero (local)]({{zotero_local_link}})
+//   %% zotero:end links %%
+//
+// Whatever comment syntax wraps the marker is kept as is, so inside front matter a
+// YAML comment (`# zotero:begin frontmatter`) works just as well as an Obsidian one.
+const BEGIN_MARKER: &str = r"zotero:begin\s+([\w-]+)";
+const END_MARKER: &str = r"zotero:end\s+([\w-]+)";
+
+pub struct Region<'a> {
+    pub name: &'a str,
+    // byte range from the start of the begin marker line to the end of the end marker
+    // line, not including its line break
+    pub start: usize,
+    pub end: usize,
+}
+
+fn begin_marker() -> &'static Regex {
+    static BEGIN: OnceLock<Regex> = OnceLock::new();
+    BEGIN.get_or_init(|| Regex::new(BEGIN_MARKER).expect("invalid begin marker pattern"))
+}
+
+fn end_marker() -> &'static Regex {
+    static END: OnceLock<Regex> = OnceLock::new();
+    END.get_or_init(|| Regex::new(END_MARKER).expect("invalid end marker pattern"))
+}
+
+pub fn find_regions(note: &str) -> Vec<Region<'_>> {
+    let (begin, end) = (begin_marker(), end_marker());
+
+    let mut regions = Vec::new();
+    let mut open: Option<(&str, usize)> = None;
+    let mut offset = 0;
+
+    for line in note.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+
+        match open {
+            None => {
+                if let Some(captures) = begin.captures(line) {
+                    let name = captures.get(1).expect("marker without a name").as_str();
+                    open = Some((name, line_start));
+                }
+            }
+            Some((name, start)) => {
+                if let Some(captures) = end.captures(line) {
+                    if &captures[1] == name {
+                        regions.push(Region {
+                            name,
+                            start,
+                            end: line_start + line.trim_end_matches(&['\r', '\n'][..]).len(),
+                        });
+                        open = None;
+                    }
+                }
+            }
+        }
+    }
+    regions
+}
+
+pub fn has_regions(note: &str) -> bool {
+    !find_regions(note).is_empty()
+}
+
+// rewrites every managed region of `existing` with the same region from `rendered`,
+// leaving everything outside the markers (and regions the template no longer has) alone
+pub fn merge(existing: &str, rendered: &str) -> String {
+    let fresh: HashMap<&str, &str> = find_regions(rendered)
+        .into_iter()
+        .map(|region| (region.name, &rendered[region.start..region.end]))
+        .collect();
+
+    let mut output = String::with_capacity(existing.len());
+    let mut last = 0;
+    find_regions(existing).iter().for_each(|region| {
+        output.push_str(&existing[last..region.start]);
+        output.push_str(
+            fresh
+                .get(region.name)
+                .copied()
+                .unwrap_or(&existing[region.start..region.end]),
+        );
+        last = region.end;
+    });
+    output.push_str(&existing[last..]);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXISTING: &str =
+        "---\r\n# zotero:begin frontmatter\r\ntitle: Old\r\n# zotero:end frontmatter\r\n---\r\n\
+                            My  own notes\t\r\n\
+                            %% zotero:begin links %%\n- old link\n%% zotero:end links %%\n\
+                            trailing text without a line break";
+
+    #[test]
+    fn keeps_text_outside_the_markers_byte_for_byte() {
+        let rendered = "# zotero:begin frontmatter\ntitle: New\n# zotero:end frontmatter\n\
+                        %% zotero:begin links %%\n- new link\n%% zotero:end links %%\n";
+        assert_eq!(
+            merge(EXISTING, rendered),
+            "---\r\n# zotero:begin frontmatter\ntitle: New\n# zotero:end frontmatter\r\n---\r\n\
+             My  own notes\t\r\n\
+             %% zotero:begin links %%\n- new link\n%% zotero:end links %%\n\
+             trailing text without a line break"
+        );
+    }
+
+    #[test]
+    fn keeps_regions_the_new_note_does_not_have() {
+        let rendered = "%% zotero:begin links %%\n- new link\n%% zotero:end links %%\n";
+        let merged = merge(EXISTING, rendered);
+        assert!(merged.contains("# zotero:begin frontmatter\r\ntitle: Old\r\n"));
+        assert!(merged.contains("- new link\n"));
+        // regions only the new note has are not added
+        assert_eq!(merge("text\n", rendered), "text\n");
+        assert_eq!(merge(EXISTING, EXISTING), EXISTING);
+    }
+
+    #[test]
+    fn leaves_unmatched_and_nested_markers_alone() {
+        let existing = "%% zotero:end links %%\n\
+                        %% zotero:begin notes %%\nold\n%% zotero:begin links %%\n%% zotero:end links %%\nstill notes\n%% zotero:end notes %%\n\
+                        %% zotero:begin tags %%\nnever closed\n";
+        let regions: Vec<&str> = find_regions(existing)
+            .iter()
+            .map(|region| region.name)
+            .collect();
+        assert_eq!(regions, ["notes"]);
+
+        let rendered = "%% zotero:begin notes %%\nnew\n%% zotero:end notes %%\n\
+                        %% zotero:begin links %%\nnew links\n%% zotero:end links %%\n\
+                        %% zotero:begin tags %%\nnew tags\n%% zotero:end tags %%\n";
+        assert_eq!(
+            merge(existing, rendered),
+            "%% zotero:end links %%\n\
+             %% zotero:begin notes %%\nnew\n%% zotero:end notes %%\n\
+             %% zotero:begin tags %%\nnever closed\n"
+        );
+        assert!(!has_regions(
+            "%% zotero:begin tags %%\n%% zotero:end links %%\n"
+        ));
+    }
+}
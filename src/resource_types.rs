@@ -1,10 +1,59 @@
-use super::json_parser::{Creator, Item, Note, Tag};
-use dissolve::strip_html_tags;
-use std::fmt::{self, Display};
-use std::fs::File;
-use std::io::Read;
+use super::config::{sanitise_file_name, VaultConfig};
+use super::error::{required, Error, Result};
+use super::html;
+use super::json_parser::{Attachment, Creator, Item, Note, Tag};
+use super::rules::{self, Rule};
+use super::template::{Context, Template, Value};
+use regex::Regex;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-const TEMPLATE_PATH:&str = "Meta/Templates/Resource";
+pub const TEMPLATE_PATH: &str = "Meta/Templates/Resource";
+
+// Every template the vault uses by file name, parsed once before the first note is
+// rendered and handed to whatever writes notes.
+pub struct Templates {
+    template_dir: PathBuf,
+    by_name: HashMap<String, Template>,
+}
+
+impl Templates {
+    // Reads and parses the templates of every resource type and every rule up front, so a
+    // missing or broken template stops the run before any note is written.
+    pub fn load(template_dir: &Path, config: &VaultConfig) -> Result<Templates> {
+        let mut by_name = HashMap::new();
+        for (_, name) in config.template_uses() {
+            if let Entry::Vacant(entry) = by_name.entry(name) {
+                let template = Template::load(template_dir, entry.key())?;
+                entry.insert(template);
+            }
+        }
+        Ok(Templates {
+            template_dir: template_dir.to_path_buf(),
+            by_name,
+        })
+    }
+
+    // the note's template, filled in; only templates read by `load` are used, so a sync
+    // never goes back to the disk halfway through
+    pub fn render(&self, note: &dyn ResourceNote, config: &VaultConfig) -> Result<String> {
+        let name = note
+            .details()
+            .template
+            .clone()
+            .unwrap_or_else(|| config.template(note.kind()));
+        let context = note.context(config);
+        match self.by_name.get(&name) {
+            Some(template) => Ok(template.render(&context)),
+            None => Err(Error::Config {
+                path: self.template_dir.join(&name),
+                message: "template not loaded".to_string(),
+            }),
+        }
+    }
+}
 
 pub trait ResourceList<T> {
     fn add(&mut self, resource: T) -> ();
@@ -12,68 +61,155 @@ pub trait ResourceList<T> {
 }
 
 pub trait New<T> {
-    fn new(item: &Item) -> Option<T>;
+    fn new(item: &Item, rule: Option<&Rule>) -> Result<T>;
 }
 
 pub trait ReferenceList {
-    fn populate(&mut self, items: &Vec<Item>);
+    fn populate(&mut self, items: &Vec<Item>, config: &VaultConfig);
     fn print(&self);
     fn new() -> References;
+    fn notes(&self) -> Vec<&dyn ResourceNote>;
+}
+
+// anything that ends up as a note in the vault
+pub trait ResourceNote {
+    // key for this kind of resource in the vault config, e.g. `academic_paper`
+    fn kind(&self) -> &'static str;
+    fn details(&self) -> &Resource;
+    // the fields its template is filled in with
+    fn context(&self, config: &VaultConfig) -> Context;
+
+    // the short citation legal items are cited by, empty for everything else
+    fn citation(&self) -> &str {
+        ""
+    }
+
+    fn folder(&self, config: &VaultConfig) -> String {
+        self.details()
+            .folder
+            .clone()
+            .unwrap_or_else(|| config.folder(self.kind()))
+    }
 }
 
 pub struct References {
     pub articles: Articles,
     pub books: Books,
     pub academic_papers: AcademicPapers,
+    pub preprints: Preprints,
+    pub press_articles: PressArticles,
     pub youtube_videos: YoutubeVideos,
     pub ted_talks: TEDTalks,
+    pub podcasts: Podcasts,
+    pub book_sections: BookSections,
+    pub theses: Theses,
+    pub conference_papers: ConferencePapers,
+    pub reports: Reports,
+    pub legal_cases: LegalCases,
+    pub statutes: Statutes,
+    pub bills: Bills,
+    pub software: SoftwareList,
+    pub datasets: Datasets,
+    // items no rule gave a resource type, written as generic notes
+    pub generics: Generics,
+    // items that could not be turned into a note because of missing or bad fields
+    pub failed: Vec<Error>,
 }
 
 impl ReferenceList for References {
-    fn populate(&mut self, items: &Vec<Item>) {
-        items
-            .iter()
-            .for_each(|item: &Item| match &item.item_type[..] {
-                "blogPost" => self
-                    .articles
-                    .add(Article::new(item).expect("failed to parse blogpost")),
-                "webpage" => self
-                    .articles
-                    .add(Article::new(item).expect("failed to parse webpage")),
-                "journalArticle" => self
-                    .academic_papers
-                    .add(AcademicPaper::new(item).expect("failed to parse academic paper")),
-                "videoRecording" => {
-                    if item.library_catalog.is_some()
-                        && item.library_catalog.as_ref().unwrap() == "YouTube"
-                    {
-                        self.youtube_videos
-                            .add(YoutubeVideo::new(item).expect("failed to parse youtube video"))
-                    } else if item.library_catalog.is_some()
-                        && item.library_catalog.as_ref().unwrap() == "www.ted.com"
-                    {
-                        self.ted_talks
-                            .add(TEDTalk::new(item).expect("failed to parse TED talk"))
-                    }
+    fn populate(&mut self, items: &Vec<Item>, config: &VaultConfig) {
+        items.iter().for_each(|item: &Item| {
+            // the vault's rules decide, items no rule matches get a generic note
+            let rule = rules::classify(&config.rules, item);
+            let added = match rule.map(|rule| rule.resource.as_str()) {
+                Some("article") => {
+                    Article::new(item, rule).map(|article| self.articles.add(article))
                 }
-                "book" => {
-                    if item.isbn.is_some() {
-                        self.books
-                            .add(Book::new(item).expect("failed to parse book"))
-                    }
+                Some("academic_paper") => AcademicPaper::new(item, rule)
+                    .map(|academic_paper| self.academic_papers.add(academic_paper)),
+                Some("preprint") => {
+                    Preprint::new(item, rule).map(|preprint| self.preprints.add(preprint))
                 }
-                _ => println!("{}", &format!("failed to get reference from {} of type {}", item.id, item.item_type)),
-            })
+                Some("press_article") => PressArticle::new(item, rule)
+                    .map(|press_article| self.press_articles.add(press_article)),
+                Some("youtube_video") => YoutubeVideo::new(item, rule)
+                    .map(|youtube_video| self.youtube_videos.add(youtube_video)),
+                Some("ted_talk") => {
+                    TEDTalk::new(item, rule).map(|ted_talk| self.ted_talks.add(ted_talk))
+                }
+                Some("podcast") => {
+                    Podcast::new(item, rule).map(|podcast| self.podcasts.add(podcast))
+                }
+                Some("book") => Book::new(item, rule).map(|book| self.books.add(book)),
+                Some("book_section") => BookSection::new(item, rule)
+                    .map(|book_section| self.book_sections.add(book_section)),
+                Some("thesis") => Thesis::new(item, rule).map(|thesis| self.theses.add(thesis)),
+                Some("conference_paper") => ConferencePaper::new(item, rule)
+                    .map(|conference_paper| self.conference_papers.add(conference_paper)),
+                Some("report") => Report::new(item, rule).map(|report| self.reports.add(report)),
+                Some("legal_case") => {
+                    LegalCase::new(item, rule).map(|legal_case| self.legal_cases.add(legal_case))
+                }
+                Some("statute") => {
+                    Statute::new(item, rule).map(|statute| self.statutes.add(statute))
+                }
+                Some("bill") => Bill::new(item, rule).map(|bill| self.bills.add(bill)),
+                Some("software") => {
+                    Software::new(item, rule).map(|software| self.software.add(software))
+                }
+                Some("dataset") => {
+                    Dataset::new(item, rule).map(|dataset| self.datasets.add(dataset))
+                }
+                _ => Generic::new(item, rule).map(|generic| self.generics.add(generic)),
+            };
+            if let Err(error) = added {
+                self.failed.push(error);
+            }
+        });
+
+        // preprints link to their published version by the name its note ends up with
+        let notes = self.notes();
+        let names: HashMap<String, String> = notes
+            .iter()
+            .zip(note_names(&notes, config))
+            .map(|(note, (_, name))| (note.details().id.clone(), name))
+            .collect();
+        let published = published_versions(items, &names);
+        let relations: HashMap<&str, &Vec<String>> = items
+            .iter()
+            .map(|item| (item.id.as_str(), &item.relations))
+            .collect();
+        for preprint in self.preprints.preprint_list.iter_mut() {
+            preprint.published_note = relations
+                .get(preprint.resource_details.id.as_str())
+                .into_iter()
+                .flat_map(|relations| relations.iter().cloned())
+                .chain(Some(preprint.published_doi.to_lowercase()))
+                .find_map(|key| published.get(&key).cloned());
+        }
     }
 
     fn print(&self) {
         println!(
-            "\nAcademic Papers\n{}\nArticles\n{}\nBooks\n{}\nYoutube Videos\n{}\nTED Talks\n{}",
+            "\nAcademic Papers\n{}\nPreprints\n{}\nArticles\n{}\nPress Articles\n{}\nBooks\n{}\nYoutube Videos\n{}\nTED Talks\n{}\nPodcasts\n{}\nBook Sections\n{}\nTheses\n{}\nConference Papers\n{}\nReports\n{}\nLegal Cases\n{}\nStatutes\n{}\nBills\n{}\nSoftware\n{}\nDatasets\n{}\nMisc\n{}",
             self.academic_papers.print(),
+            self.preprints.print(),
             self.articles.print(),
+            self.press_articles.print(),
             self.books.print(),
             self.youtube_videos.print(),
-            self.ted_talks.print()
+            self.ted_talks.print(),
+            self.podcasts.print(),
+            self.book_sections.print(),
+            self.theses.print(),
+            self.conference_papers.print(),
+            self.reports.print(),
+            self.legal_cases.print(),
+            self.statutes.print(),
+            self.bills.print(),
+            self.software.print(),
+            self.datasets.print(),
+            self.generics.print()
         );
     }
 
@@ -88,15 +224,232 @@ impl ReferenceList for References {
             academic_papers: AcademicPapers {
                 academic_paper_list: Vec::<AcademicPaper>::new(),
             },
+            preprints: Preprints {
+                preprint_list: Vec::<Preprint>::new(),
+            },
+            press_articles: PressArticles {
+                press_article_list: Vec::<PressArticle>::new(),
+            },
             youtube_videos: YoutubeVideos {
                 youtube_video_list: Vec::<YoutubeVideo>::new(),
             },
             ted_talks: TEDTalks {
                 ted_talk_list: Vec::<TEDTalk>::new(),
             },
+            podcasts: Podcasts {
+                podcast_list: Vec::<Podcast>::new(),
+            },
+            book_sections: BookSections {
+                book_section_list: Vec::<BookSection>::new(),
+            },
+            theses: Theses {
+                thesis_list: Vec::<Thesis>::new(),
+            },
+            conference_papers: ConferencePapers {
+                conference_paper_list: Vec::<ConferencePaper>::new(),
+            },
+            reports: Reports {
+                report_list: Vec::<Report>::new(),
+            },
+            legal_cases: LegalCases {
+                legal_case_list: Vec::<LegalCase>::new(),
+            },
+            statutes: Statutes {
+                statute_list: Vec::<Statute>::new(),
+            },
+            bills: Bills {
+                bill_list: Vec::<Bill>::new(),
+            },
+            software: SoftwareList {
+                software_list: Vec::<Software>::new(),
+            },
+            datasets: Datasets {
+                dataset_list: Vec::<Dataset>::new(),
+            },
+            generics: Generics {
+                generic_list: Vec::<Generic>::new(),
+            },
+            failed: Vec::new(),
+        }
+    }
+
+    fn notes(&self) -> Vec<&dyn ResourceNote> {
+        let mut notes: Vec<&dyn ResourceNote> = Vec::new();
+        self.articles
+            .article_list
+            .iter()
+            .for_each(|article| notes.push(article));
+        self.academic_papers
+            .academic_paper_list
+            .iter()
+            .for_each(|academic_paper| notes.push(academic_paper));
+        self.preprints
+            .preprint_list
+            .iter()
+            .for_each(|preprint| notes.push(preprint));
+        self.press_articles
+            .press_article_list
+            .iter()
+            .for_each(|press_article| notes.push(press_article));
+        self.books
+            .book_list
+            .iter()
+            .for_each(|book| notes.push(book));
+        self.ted_talks
+            .ted_talk_list
+            .iter()
+            .for_each(|ted_talk| notes.push(ted_talk));
+        self.youtube_videos
+            .youtube_video_list
+            .iter()
+            .for_each(|youtube_video| notes.push(youtube_video));
+        self.podcasts
+            .podcast_list
+            .iter()
+            .for_each(|podcast| notes.push(podcast));
+        self.book_sections
+            .book_section_list
+            .iter()
+            .for_each(|book_section| notes.push(book_section));
+        self.theses
+            .thesis_list
+            .iter()
+            .for_each(|thesis| notes.push(thesis));
+        self.conference_papers
+            .conference_paper_list
+            .iter()
+            .for_each(|conference_paper| notes.push(conference_paper));
+        self.reports
+            .report_list
+            .iter()
+            .for_each(|report| notes.push(report));
+        self.legal_cases
+            .legal_case_list
+            .iter()
+            .for_each(|legal_case| notes.push(legal_case));
+        self.statutes
+            .statute_list
+            .iter()
+            .for_each(|statute| notes.push(statute));
+        self.bills
+            .bill_list
+            .iter()
+            .for_each(|bill| notes.push(bill));
+        self.software
+            .software_list
+            .iter()
+            .for_each(|software| notes.push(software));
+        self.datasets
+            .dataset_list
+            .iter()
+            .for_each(|dataset| notes.push(dataset));
+        self.generics
+            .generic_list
+            .iter()
+            .for_each(|generic| notes.push(generic));
+        notes
+    }
+}
+// The folder and file name of each note. Items that would share a note all get their
+// citekey added, so which of them keeps the plain name never depends on the order of the
+// library. Names differing only in case clash too, as they do on macOS and Windows.
+pub fn note_names(notes: &[&dyn ResourceNote], config: &VaultConfig) -> Vec<(String, String)> {
+    let names: Vec<(String, String)> = notes
+        .iter()
+        .map(|note| {
+            let details = note.details();
+            (
+                note.folder(config),
+                config.file_name(&details.id, &details.full_title, note.citation()),
+            )
+        })
+        .collect();
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    names.iter().for_each(|(folder, name)| {
+        *counts
+            .entry(format!("{}/{}", folder, name).to_lowercase())
+            .or_default() += 1
+    });
+    notes
+        .iter()
+        .zip(names)
+        .map(|(note, (folder, name))| {
+            match counts[&format!("{}/{}", folder, name).to_lowercase()] {
+                1 => (folder, name),
+                _ => (
+                    folder,
+                    format!("{} ({})", name, sanitise_file_name(&note.details().id)),
+                ),
+            }
+        })
+        .collect()
+}
+
+// The note names of everything that has a DOI and a note, by DOI and by Zotero URI, so a
+// preprint can link to its published version
+fn published_versions(items: &[Item], names: &HashMap<String, String>) -> HashMap<String, String> {
+    let mut published = HashMap::new();
+    items
+        .iter()
+        .filter(|item| item.item_type != "preprint")
+        .for_each(|item| {
+            if let (Some(doi), Some(note)) = (&item.doi, names.get(&item.id)) {
+                published.insert(doi.to_lowercase(), note.clone());
+                if let Some(uri) = &item.uri {
+                    published.insert(uri.clone(), note.clone());
+                }
+            }
+        });
+    published
+}
+
+// A note of the given kind made from an item that has every field filled in, to see which
+// fields the kind gives its template without needing a library.
+pub fn specimen(kind: &str) -> Result<Box<dyn ResourceNote>> {
+    let mut fields = rules::fields(&Item::default());
+    fields.remove("relations");
+    fields.iter_mut().for_each(|(name, value)| {
+        if value.is_null() || value.is_string() {
+            *value = serde_json::Value::String(format!("specimen {}", name));
         }
+    });
+    let filled_in = serde_json::json!({
+        "citationKey": "specimen",
+        "date": "2020-01-01",
+        "url": "https://www.youtube.com/watch?v=specimen",
+        "DOI": "10.5555/specimen",
+        "creators": [{"creatorType": "author", "firstName": "Ann", "lastName": "Specimen", "name": "Ann Specimen"}],
+        "tags": [{"tag": "specimen"}],
+        "notes": [{"dateAdded": "", "dateModified": "", "note": "specimen", "uri": ""}],
+        "attachments": [{"title": "specimen", "url": "https://example.org/specimen.pdf"}],
+    });
+    if let serde_json::Value::Object(filled_in) = filled_in {
+        fields.extend(filled_in);
     }
+    let item: Item = serde_json::from_value(serde_json::Value::Object(fields))
+        .expect("specimen item should deserialize");
+    Ok(match kind {
+        "article" => Box::new(Article::new(&item, None)?),
+        "academic_paper" => Box::new(AcademicPaper::new(&item, None)?),
+        "preprint" => Box::new(Preprint::new(&item, None)?),
+        "press_article" => Box::new(PressArticle::new(&item, None)?),
+        "youtube_video" => Box::new(YoutubeVideo::new(&item, None)?),
+        "ted_talk" => Box::new(TEDTalk::new(&item, None)?),
+        "podcast" => Box::new(Podcast::new(&item, None)?),
+        "book" => Box::new(Book::new(&item, None)?),
+        "book_section" => Box::new(BookSection::new(&item, None)?),
+        "thesis" => Box::new(Thesis::new(&item, None)?),
+        "conference_paper" => Box::new(ConferencePaper::new(&item, None)?),
+        "report" => Box::new(Report::new(&item, None)?),
+        "legal_case" => Box::new(LegalCase::new(&item, None)?),
+        "statute" => Box::new(Statute::new(&item, None)?),
+        "bill" => Box::new(Bill::new(&item, None)?),
+        "software" => Box::new(Software::new(&item, None)?),
+        "dataset" => Box::new(Dataset::new(&item, None)?),
+        _ => Box::new(Generic::new(&item, None)?),
+    })
 }
+
 pub struct Resource {
     pub id: String,
     pub full_title: String,
@@ -105,6 +458,52 @@ pub struct Resource {
     pub zotero_cloud_link: String,
     pub zotero_local_link: String,
     pub creators: Vec<Creator>,
+    pub attachments: Vec<Attachment>,
+    // set by the classification rule that matched, over the resource type's defaults
+    pub folder: Option<String>,
+    pub template: Option<String>,
+}
+
+impl Resource {
+    // the details every kind of resource shares
+    pub fn new(item: &Item, rule: Option<&Rule>) -> Result<Resource> {
+        Ok(Resource {
+            id: item.id.clone(),
+            full_title: required(item, "title", &item.title)?,
+            tags: item.tags.clone(),
+            notes: item
+                .notes
+                .clone()
+                .into_iter()
+                .map(|mut note: Note| {
+                    note.content = html::to_markdown(&note.content);
+                    note
+                })
+                .collect(),
+            // items that were not exported from Zotero have no way back to it
+            zotero_cloud_link: item.uri.clone().unwrap_or_default(),
+            zotero_local_link: item.select.clone().unwrap_or_default(),
+            creators: item.creators.clone(),
+            attachments: item.attachments.clone(),
+            folder: rule.and_then(|rule| rule.folder.clone()),
+            template: rule.and_then(|rule| rule.template.clone()),
+        })
+    }
+
+    // the fields every template can use
+    pub fn context(&self, config: &VaultConfig) -> Context {
+        let mut context = Context::default();
+        context
+            .set("id", &self.id)
+            .set("full_title", &self.full_title)
+            .set("zotero_local_link", &self.zotero_local_link)
+            .set("zotero_cloud_link", &self.zotero_cloud_link)
+            .set("authors", people(&self.creators))
+            .set("tags", tag_list(&self.tags, &config.tag_prefix))
+            .set("notes", note_list(&self.notes))
+            .set("attachments", attachment_list(&self.attachments));
+        context
+    }
 }
 
 pub struct Book {
@@ -114,83 +513,34 @@ pub struct Book {
     pub publish_date: String,
 }
 
-impl fmt::Display for Book {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut template_file =
-            File::open(&format!("{}/Book.md", TEMPLATE_PATH)).expect("Failed to open book template file");
-        let mut book_template = String::new();
-
-        template_file
-            .read_to_string(&mut book_template)
-            .expect("failed to parse book template file");
-
-        write!(
-            f,
-            r##"{}"##,
-            book_template
-                .replace("{{short_title}}", &self.short_title)
-                .replace("{{publish_date}}", &self.publish_date)
-                .replace("{{id}}", &self.resource_details.id)
-                .replace("{{full_title}}", &self.resource_details.full_title)
-                .replace("{{isbn_13}}", &self.isbn_13)
-                .replace(
-                    "{{zotero_local_link}}",
-                    &self.resource_details.zotero_local_link
-                )
-                .replace(
-                    "{{zotero_cloud_link}}",
-                    &self.resource_details.zotero_cloud_link
-                )
-                .replace(
-                    "{{authors}}",
-                    &display_authors(&self.resource_details.creators)
-                )
-                .replace("{{tags}}", &display_tags(&self.resource_details.tags))
-                .replace("{{notes}}", &display_notes(&self.resource_details.notes))
-        )
+impl ResourceNote for Book {
+    fn kind(&self) -> &'static str {
+        "book"
+    }
+
+    fn details(&self) -> &Resource {
+        &self.resource_details
+    }
+
+    fn context(&self, config: &VaultConfig) -> Context {
+        let mut context = self.resource_details.context(config);
+        context
+            .set("short_title", &self.short_title)
+            .set("publish_date", &self.publish_date)
+            .set("isbn_13", &self.isbn_13);
+        context
     }
 }
 
 impl New<Book> for Book {
-    fn new(item: &Item) -> Option<Book> {
-        if item.isbn.is_some() {
-            Some(Book {
-                resource_details: Resource {
-                    id: item.id.clone(),
-                    full_title: item.title.clone().expect("Failed to find book title"),
-                    tags: item.tags.clone(),
-                    notes: item
-                        .notes
-                        .clone()
-                        .into_iter()
-                        .map(|mut note: Note| {
-                            note.content = strip_html_tags(&note.content).concat();
-                            note
-                        })
-                        .collect(),
-                    zotero_cloud_link: item
-                        .uri
-                        .clone()
-                        .expect("Failed to get book's zotero cloud link"),
-                    zotero_local_link: item
-                        .select
-                        .clone()
-                        .expect("Failed to get book's zotero local link"),
-                    creators: item.creators.clone(),
-                },
-                isbn_13: item.isbn.clone().expect("failed to get ISBN"),
-                short_title: item
-                    .short_title
-                    .clone()
-                    .expect("Failed to find book short_title"),
-                publish_date: item
-                    .published_date
-                    .clone()
-                    .expect("Failed to find book's publish_date"),
-            })
-        } else {
-            None
-        }
+    fn new(item: &Item, rule: Option<&Rule>) -> Result<Book> {
+        // the built-in rule only takes books with an ISBN, a vault's own rule may not
+        Ok(Book {
+            resource_details: Resource::new(item, rule)?,
+            isbn_13: item.isbn.clone().unwrap_or_default(),
+            short_title: item.short_title.clone().unwrap_or_default(),
+            publish_date: item.published_date.clone().unwrap_or_default(),
+        })
     }
 }
 
@@ -206,7 +556,7 @@ impl ResourceList<Book> for Books {
     fn print(&self) -> String {
         let mut output = "".to_string();
         self.book_list.iter().for_each(|book| {
-            output.push_str(&book.to_string());
+            output.push_str(&format!("{}\n", book.resource_details.id));
         });
         output
     }
@@ -218,72 +568,27 @@ pub struct Article {
 }
 
 impl New<Article> for Article {
-    fn new(item: &Item) -> Option<Article> {
-        if item.url.is_some() && (item.item_type == "webpage" || item.item_type == "blogPost") {
-            Some(Article {
-                resource_details: Resource {
-                    id: item.id.clone(),
-                    full_title: item.title.clone().expect("Failed to find book title"),
-                    tags: item.tags.clone(),
-                    notes: item
-                        .notes
-                        .clone()
-                        .into_iter()
-                        .map(|mut note: Note| {
-                            note.content = strip_html_tags(&note.content).concat();
-                            note
-                        })
-                        .collect(),
-                    zotero_cloud_link: item
-                        .uri
-                        .clone()
-                        .expect("Failed to get book's zotero cloud link"),
-                    zotero_local_link: item
-                        .select
-                        .clone()
-                        .expect("Failed to get book's zotero local link"),
-                    creators: item.creators.clone(),
-                },
-                url: item.url.clone().expect("Article url not found"),
-            })
-        } else {
-            None
-        }
+    fn new(item: &Item, rule: Option<&Rule>) -> Result<Article> {
+        Ok(Article {
+            resource_details: Resource::new(item, rule)?,
+            url: required(item, "url", &item.url)?,
+        })
     }
 }
 
-impl fmt::Display for Article {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut template_file =
-            File::open(&format!("{}/Article.md", TEMPLATE_PATH)).expect("Failed to open article template file");
-        let mut article_template = String::new();
-
-        template_file
-            .read_to_string(&mut article_template)
-            .expect("failed to parse book template file");
-
-        write!(
-            f,
-            r##"{}"##,
-            article_template
-                .replace("{{id}}", &self.resource_details.id)
-                .replace("{{full_title}}", &self.resource_details.full_title)
-                .replace(
-                    "{{zotero_local_link}}",
-                    &self.resource_details.zotero_local_link
-                )
-                .replace(
-                    "{{zotero_cloud_link}}",
-                    &self.resource_details.zotero_cloud_link
-                )
-                .replace(
-                    "{{authors}}",
-                    &display_authors(&self.resource_details.creators)
-                )
-                .replace("{{tags}}", &display_tags(&self.resource_details.tags))
-                .replace("{{notes}}", &display_notes(&self.resource_details.notes))
-                .replace("{{url}}", &self.url)
-        )
+impl ResourceNote for Article {
+    fn kind(&self) -> &'static str {
+        "article"
+    }
+
+    fn details(&self) -> &Resource {
+        &self.resource_details
+    }
+
+    fn context(&self, config: &VaultConfig) -> Context {
+        let mut context = self.resource_details.context(config);
+        context.set("url", &self.url);
+        context
     }
 }
 
@@ -299,7 +604,261 @@ impl ResourceList<Article> for Articles {
     fn print(&self) -> String {
         let mut output = "".to_string();
         self.article_list.iter().for_each(|article| {
-            output.push_str(&article.to_string());
+            output.push_str(&format!("{}\n", article.resource_details.id));
+        });
+        output
+    }
+}
+
+pub struct Preprint {
+    pub resource_details: Resource,
+    pub repository: String,
+    pub archive_id: String,
+    pub version: String,
+    pub category: String,
+    pub url: String,
+    pub pdf_url: String,
+    pub doi: String,
+    pub publish_date: String,
+    // from `extra` or the item's relations
+    pub published_doi: String,
+    // note name of the published version, when it is in the library as well
+    pub published_note: Option<String>,
+}
+
+impl New<Preprint> for Preprint {
+    fn new(item: &Item, rule: Option<&Rule>) -> Result<Preprint> {
+        let url = required(item, "url", &item.url)?;
+        let arxiv = arxiv_id(item);
+        let repository = item.repository.clone().unwrap_or_else(|| {
+            if arxiv.is_some() {
+                "arXiv".to_string()
+            } else if url.contains("biorxiv.org") {
+                "bioRxiv".to_string()
+            } else if url.contains("medrxiv.org") {
+                "medRxiv".to_string()
+            } else {
+                item.library_catalog.clone().unwrap_or_default()
+            }
+        });
+        let pdf_url = match &arxiv {
+            Some((id, version)) => format!("https://arxiv.org/pdf/{}{}", id, version),
+            // bioRxiv and medRxiv serve the PDF next to the abstract page
+            None if url.contains("rxiv.org/content/") => {
+                format!("{}.full.pdf", url.trim_end_matches(".full"))
+            }
+            None => String::new(),
+        };
+        let doi = item.doi.clone().unwrap_or_default();
+        Ok(Preprint {
+            resource_details: Resource::new(item, rule)?,
+            repository,
+            archive_id: match &arxiv {
+                Some((id, _)) => format!("arXiv:{}", id),
+                None => item.archive_id.clone().unwrap_or_default(),
+            },
+            version: arxiv
+                .map(|(_, version)| version.trim_start_matches('v').to_string())
+                .unwrap_or_default(),
+            category: arxiv_category(item).unwrap_or_default(),
+            url,
+            pdf_url,
+            published_doi: published_doi(item)
+                .filter(|published| !published.eq_ignore_ascii_case(&doi))
+                .unwrap_or_default(),
+            doi,
+            publish_date: item.published_date.clone().unwrap_or_default(),
+            published_note: None,
+        })
+    }
+}
+
+// `2101.00001` and `v2` (or an empty version) from the abstract URL or the archive ID;
+// old style IDs look like `hep-th/9901001`
+fn arxiv_id(item: &Item) -> Option<(String, String)> {
+    static FROM_URL: OnceLock<Regex> = OnceLock::new();
+    let from_url = FROM_URL.get_or_init(|| {
+        Regex::new(r"arxiv\.org/(?:abs|pdf)/(.+?)(v\d+)?(?:\.pdf)?/?$")
+            .expect("invalid arXiv URL pattern")
+    });
+    static FROM_ARCHIVE_ID: OnceLock<Regex> = OnceLock::new();
+    let from_archive_id = FROM_ARCHIVE_ID.get_or_init(|| {
+        Regex::new(r"(?i)^arxiv:\s*(\S+?)(v\d+)?$").expect("invalid arXiv archive ID pattern")
+    });
+    item.url
+        .as_deref()
+        .and_then(|url| from_url.captures(url))
+        .or_else(|| {
+            item.archive_id
+                .as_deref()
+                .and_then(|archive_id| from_archive_id.captures(archive_id.trim()))
+        })
+        .map(|captures| {
+            (
+                captures[1].to_string(),
+                captures
+                    .get(2)
+                    .map_or(String::new(), |version| version.as_str().to_string()),
+            )
+        })
+}
+
+// Zotero's arXiv translator writes `arXiv:2101.00001 [cs]` into the publication title,
+// BibTeX files carry `eprintclass`
+fn arxiv_category(item: &Item) -> Option<String> {
+    static BRACKETED: OnceLock<Regex> = OnceLock::new();
+    let bracketed = BRACKETED.get_or_init(|| {
+        Regex::new(r"arXiv:\S+\s+\[([\w.\-]+)\]").expect("invalid arXiv category pattern")
+    });
+    static EXTRA_LINE: OnceLock<Regex> = OnceLock::new();
+    let extra_line = EXTRA_LINE.get_or_init(|| {
+        Regex::new(r"(?im)^tex\.(?:eprintclass|primaryclass):\s*(\S+)")
+            .expect("invalid eprint class pattern")
+    });
+    [&item.journal, &item.archive_id]
+        .iter()
+        .filter_map(|field| field.as_deref())
+        .find_map(|field| bracketed.captures(field))
+        .or_else(|| {
+            item.extra
+                .as_deref()
+                .and_then(|extra| extra_line.captures(extra))
+        })
+        .map(|captures| captures[1].to_string())
+}
+
+// a `DOI:` line in `extra`, or a relation pointing at doi.org
+fn published_doi(item: &Item) -> Option<String> {
+    static EXTRA_LINE: OnceLock<Regex> = OnceLock::new();
+    let extra_line = EXTRA_LINE.get_or_init(|| {
+        Regex::new(r"(?im)^(?:published\s+)?DOI:\s*(10\.\S+)")
+            .expect("invalid published DOI pattern")
+    });
+    static DOI_URL: OnceLock<Regex> = OnceLock::new();
+    let doi_url = DOI_URL.get_or_init(|| {
+        Regex::new(r"^https?://(?:dx\.)?doi\.org/(10\..+)$").expect("invalid DOI URL pattern")
+    });
+    item.extra
+        .as_deref()
+        .and_then(|extra| extra_line.captures(extra))
+        .or_else(|| {
+            item.relations
+                .iter()
+                .find_map(|relation| doi_url.captures(relation))
+        })
+        .map(|captures| captures[1].to_string())
+}
+
+impl ResourceNote for Preprint {
+    fn kind(&self) -> &'static str {
+        "preprint"
+    }
+
+    fn details(&self) -> &Resource {
+        &self.resource_details
+    }
+
+    fn context(&self, config: &VaultConfig) -> Context {
+        let published_version = match (&self.published_note, &self.published_doi[..]) {
+            (Some(citekey), _) => format!("[[{}]]", citekey),
+            (None, "") => String::new(),
+            (None, doi) => format!("[{}](https://doi.org/{})", doi, doi),
+        };
+
+        let mut context = self.resource_details.context(config);
+        context
+            .set("repository", &self.repository)
+            .set("archive_id", &self.archive_id)
+            .set("version", &self.version)
+            .set("category", &self.category)
+            .set("url", &self.url)
+            .set("pdf_url", &self.pdf_url)
+            .set("doi", &self.doi)
+            .set("published_doi", &self.published_doi)
+            .set("published_version", &published_version)
+            .set("publish_date", &self.publish_date);
+        context
+    }
+}
+
+pub struct Preprints {
+    pub preprint_list: Vec<Preprint>,
+}
+
+impl ResourceList<Preprint> for Preprints {
+    fn add(&mut self, preprint: Preprint) {
+        self.preprint_list.push(preprint);
+    }
+
+    fn print(&self) -> String {
+        let mut output = "".to_string();
+        self.preprint_list.iter().for_each(|preprint| {
+            output.push_str(&format!("{}\n", preprint.resource_details.id));
+        });
+        output
+    }
+}
+
+pub struct PressArticle {
+    pub resource_details: Resource,
+    pub publication: String,
+    pub section: String,
+    pub edition: String,
+    pub pages: String,
+    pub url: String,
+    pub publish_date: String,
+}
+
+impl New<PressArticle> for PressArticle {
+    fn new(item: &Item, rule: Option<&Rule>) -> Result<PressArticle> {
+        Ok(PressArticle {
+            resource_details: Resource::new(item, rule)?,
+            publication: required(item, "publicationTitle", &item.journal)?,
+            section: item.section.clone().unwrap_or_default(),
+            edition: item.edition.clone().unwrap_or_default(),
+            pages: item.pages.clone().unwrap_or_default(),
+            url: item.url.clone().unwrap_or_default(),
+            publish_date: item.published_date.clone().unwrap_or_default(),
+        })
+    }
+}
+
+impl ResourceNote for PressArticle {
+    fn kind(&self) -> &'static str {
+        "press_article"
+    }
+
+    fn details(&self) -> &Resource {
+        &self.resource_details
+    }
+
+    fn context(&self, config: &VaultConfig) -> Context {
+        let mut context = self.resource_details.context(config);
+        context
+            // one note per outlet collects everything published there
+            .set("publication", format!("[[{}]]", &self.publication))
+            .set("section", &self.section)
+            .set("edition", &self.edition)
+            .set("pages", &self.pages)
+            .set("url", &self.url)
+            .set("publish_date", &self.publish_date);
+        context
+    }
+}
+
+pub struct PressArticles {
+    pub press_article_list: Vec<PressArticle>,
+}
+
+impl ResourceList<PressArticle> for PressArticles {
+    fn add(&mut self, press_article: PressArticle) {
+        self.press_article_list.push(press_article);
+    }
+
+    fn print(&self) -> String {
+        let mut output = "".to_string();
+        self.press_article_list.iter().for_each(|press_article| {
+            output.push_str(&format!("{}\n", press_article.resource_details.id));
         });
         output
     }
@@ -313,82 +872,32 @@ pub struct AcademicPaper {
 }
 
 impl New<AcademicPaper> for AcademicPaper {
-    fn new(item: &Item) -> Option<AcademicPaper> {
-        if item.doi.is_some() && (item.item_type == "journalArticle") {
-            Some(AcademicPaper {
-                resource_details: Resource {
-                    id: item.id.clone(),
-                    full_title: item.title.clone().expect("Failed to find book title"),
-                    tags: item.tags.clone(),
-                    notes: item
-                        .notes
-                        .clone()
-                        .into_iter()
-                        .map(|mut note: Note| {
-                            note.content = strip_html_tags(&note.content).concat();
-                            note
-                        })
-                        .collect(),
-                    zotero_cloud_link: item
-                        .uri
-                        .clone()
-                        .expect("Failed to get book's zotero cloud link"),
-                    zotero_local_link: item
-                        .select
-                        .clone()
-                        .expect("Failed to get book's zotero local link"),
-                    creators: item.creators.clone(),
-                },
-                doi: item.doi.clone().expect("Failed to get paper's DOI"),
-                publish_date: item
-                    .published_date
-                    .clone()
-                    .expect("Failed to get paper's published date"),
-                journal: item
-                    .journal
-                    .clone()
-                    .expect("Failed to get journal name for paper"),
-            })
-        } else {
-            None
-        }
+    fn new(item: &Item, rule: Option<&Rule>) -> Result<AcademicPaper> {
+        Ok(AcademicPaper {
+            resource_details: Resource::new(item, rule)?,
+            doi: required(item, "DOI", &item.doi)?,
+            publish_date: required(item, "date", &item.published_date)?,
+            journal: required(item, "publicationTitle", &item.journal)?,
+        })
     }
 }
 
-impl fmt::Display for AcademicPaper {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut template_file = File::open(&format!("{}/Academic Paper.md", TEMPLATE_PATH))
-            .expect("Failed to open academic paper template file");
-        let mut academic_paper_template = String::new();
-
-        template_file
-            .read_to_string(&mut academic_paper_template)
-            .expect("failed to parse academic paper template file");
-
-        write!(
-            f,
-            r##"{}"##,
-            academic_paper_template
-                .replace("{{id}}", &self.resource_details.id)
-                .replace("{{full_title}}", &self.resource_details.full_title)
-                .replace(
-                    "{{zotero_local_link}}",
-                    &self.resource_details.zotero_local_link
-                )
-                .replace(
-                    "{{zotero_cloud_link}}",
-                    &self.resource_details.zotero_cloud_link
-                )
-                .replace(
-                    "{{authors}}",
-                    &display_authors(&self.resource_details.creators)
-                )
-                .replace("{{tags}}", &display_tags(&self.resource_details.tags))
-                .replace("{{notes}}", &display_notes(&self.resource_details.notes))
-                .replace("{{doi}}", &self.doi)
-                .replace("{{publish_date}}", &self.publish_date)
-                .replace("{{journal}}", &self.journal)
-        )
+impl ResourceNote for AcademicPaper {
+    fn kind(&self) -> &'static str {
+        "academic_paper"
+    }
+
+    fn details(&self) -> &Resource {
+        &self.resource_details
+    }
+
+    fn context(&self, config: &VaultConfig) -> Context {
+        let mut context = self.resource_details.context(config);
+        context
+            .set("doi", &self.doi)
+            .set("publish_date", &self.publish_date)
+            .set("journal", &self.journal);
+        context
     }
 }
 
@@ -404,7 +913,7 @@ impl ResourceList<AcademicPaper> for AcademicPapers {
     fn print(&self) -> String {
         let mut output = "".to_string();
         self.academic_paper_list.iter().for_each(|academic_paper| {
-            output.push_str(&academic_paper.to_string());
+            output.push_str(&format!("{}\n", academic_paper.resource_details.id));
         });
         output
     }
@@ -417,48 +926,18 @@ pub struct YoutubeVideo {
 }
 
 impl New<YoutubeVideo> for YoutubeVideo {
-    fn new(item: &Item) -> Option<YoutubeVideo> {
-        if item.url.is_some()
-            && item.library_catalog.is_some()
-            && (item.library_catalog.as_ref().unwrap() == "YouTube"
-                && item.item_type == "videoRecording")
-        {
-            Some(YoutubeVideo {
-                resource_details: Resource {
-                    id: item.id.clone(),
-                    full_title: item.title.clone().expect("Failed to find book title"),
-                    tags: item.tags.clone(),
-                    notes: item
-                        .notes
-                        .clone()
-                        .into_iter()
-                        .map(|mut note: Note| {
-                            note.content = strip_html_tags(&note.content).concat();
-                            note
-                        })
-                        .collect(),
-                    zotero_cloud_link: item
-                        .uri
-                        .clone()
-                        .expect("Failed to get book's zotero cloud link"),
-                    zotero_local_link: item
-                        .select
-                        .clone()
-                        .expect("Failed to get book's zotero local link"),
-                    creators: item.creators.clone(),
-                },
-                url_query_string: get_youtube_query_string(
-                    &item.url.clone().expect("Article url not found"),
-                )
-                .expect("Failed to parse URL Query String"),
-                channel: item.creators[0]
-                    .name
-                    .clone()
-                    .expect("Youtube channel not found"),
-            })
-        } else {
-            None
-        }
+    fn new(item: &Item, rule: Option<&Rule>) -> Result<YoutubeVideo> {
+        let url = required(item, "url", &item.url)?;
+        Ok(YoutubeVideo {
+            resource_details: Resource::new(item, rule)?,
+            url_query_string: get_youtube_query_string(&url)
+                .ok_or_else(|| Error::invalid(item, "url", "not a YouTube watch URL"))?,
+            channel: item
+                .creators
+                .first()
+                .and_then(|creator| creator.name.clone())
+                .ok_or_else(|| Error::missing(item, "creators"))?,
+        })
     }
 }
 
@@ -470,35 +949,21 @@ fn get_youtube_query_string(url: &str) -> Option<String> {
     }
 }
 
-impl fmt::Display for YoutubeVideo {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut template_file =
-            File::open(&format!("{}/Youtube Video.md", TEMPLATE_PATH)).expect("Failed to open article template file");
-        let mut youtube_template = String::new();
-
-        template_file
-            .read_to_string(&mut youtube_template)
-            .expect("failed to parse YouTube Video template file");
-
-        write!(
-            f,
-            r##"{}"##,
-            youtube_template
-                .replace("{{id}}", &self.resource_details.id)
-                .replace("{{full_title}}", &self.resource_details.full_title)
-                .replace(
-                    "{{zotero_local_link}}",
-                    &self.resource_details.zotero_local_link
-                )
-                .replace(
-                    "{{zotero_cloud_link}}",
-                    &self.resource_details.zotero_cloud_link
-                )
-                .replace("{{channel}}", &self.channel)
-                .replace("{{tags}}", &display_tags(&self.resource_details.tags))
-                .replace("{{notes}}", &display_notes(&self.resource_details.notes))
-                .replace("{{url_query_string}}", &self.url_query_string)
-        )
+impl ResourceNote for YoutubeVideo {
+    fn kind(&self) -> &'static str {
+        "youtube_video"
+    }
+
+    fn details(&self) -> &Resource {
+        &self.resource_details
+    }
+
+    fn context(&self, config: &VaultConfig) -> Context {
+        let mut context = self.resource_details.context(config);
+        context
+            .set("channel", &self.channel)
+            .set("url_query_string", &self.url_query_string);
+        context
     }
 }
 
@@ -516,7 +981,7 @@ impl ResourceList<YoutubeVideo> for YoutubeVideos {
         self.youtube_video_list
             .iter()
             .for_each(|youtube_video: &YoutubeVideo| {
-                output.push_str(&youtube_video.to_string());
+                output.push_str(&format!("{}\n", youtube_video.resource_details.id));
             });
         output
     }
@@ -529,82 +994,36 @@ pub struct TEDTalk {
 }
 
 impl New<TEDTalk> for TEDTalk {
-    fn new(item: &Item) -> Option<TEDTalk> {
-        if item.url.is_some()
-            && item.library_catalog.is_some()
-            && (item.library_catalog.as_ref().unwrap() == "www.ted.com"
-                && item.item_type == "videoRecording")
-        {
-            Some(TEDTalk {
-                resource_details: Resource {
-                    id: item.id.clone(),
-                    full_title: item.title.clone().expect("Failed to find book title"),
-                    tags: item.tags.clone(),
-                    notes: item
-                        .notes
-                        .clone()
-                        .into_iter()
-                        .map(|mut note: Note| {
-                            note.content = strip_html_tags(&note.content).concat();
-                            note
-                        })
-                        .collect(),
-                    zotero_cloud_link: item
-                        .uri
-                        .clone()
-                        .expect("Failed to get TED Talks's zotero cloud link"),
-                    zotero_local_link: item
-                        .select
-                        .clone()
-                        .expect("Failed to get TED Talk's zotero local link"),
-                    creators: item.creators.clone(),
-                },
-                url: item.url.clone().expect("TED talk url not found"),
-                speaker: item.creators[0]
-                    .first_name
-                    .clone()
-                    .expect("couldn't get TED speaker first name")
-                    + " "
-                    + &item.creators[0]
-                        .last_name
-                        .clone()
-                        .expect("couldn't get TED Speaker last name"),
-            })
-        } else {
-            None
-        }
+    fn new(item: &Item, rule: Option<&Rule>) -> Result<TEDTalk> {
+        let speaker = item
+            .creators
+            .first()
+            .ok_or_else(|| Error::missing(item, "creators"))?;
+        Ok(TEDTalk {
+            resource_details: Resource::new(item, rule)?,
+            url: required(item, "url", &item.url)?,
+            speaker: required(item, "creators.firstName", &speaker.first_name)?
+                + " "
+                + &required(item, "creators.lastName", &speaker.last_name)?,
+        })
     }
 }
 
-impl fmt::Display for TEDTalk {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut template_file =
-            File::open(&format!("{}/TED Talk.md", TEMPLATE_PATH)).expect("Failed to open TED Talk template file");
-        let mut ted_talk_template = String::new();
-
-        template_file
-            .read_to_string(&mut ted_talk_template)
-            .expect("failed to parse TED Talk Video template file");
-
-        write!(
-            f,
-            r##"{}"##,
-            ted_talk_template
-                .replace("{{id}}", &self.resource_details.id)
-                .replace("{{full_title}}", &self.resource_details.full_title)
-                .replace(
-                    "{{zotero_local_link}}",
-                    &self.resource_details.zotero_local_link
-                )
-                .replace(
-                    "{{zotero_cloud_link}}",
-                    &self.resource_details.zotero_cloud_link
-                )
-                .replace("{{speaker}}", &format!("[[{}]]", &self.speaker))
-                .replace("{{tags}}", &display_tags(&self.resource_details.tags))
-                .replace("{{notes}}", &display_notes(&self.resource_details.notes))
-                .replace("{{url}}", &self.url)
-        )
+impl ResourceNote for TEDTalk {
+    fn kind(&self) -> &'static str {
+        "ted_talk"
+    }
+
+    fn details(&self) -> &Resource {
+        &self.resource_details
+    }
+
+    fn context(&self, config: &VaultConfig) -> Context {
+        let mut context = self.resource_details.context(config);
+        context
+            .set("speaker", format!("[[{}]]", &self.speaker))
+            .set("url", &self.url);
+        context
     }
 }
 
@@ -620,66 +1039,1624 @@ impl ResourceList<TEDTalk> for TEDTalks {
     fn print(&self) -> String {
         let mut output = "".to_string();
         self.ted_talk_list.iter().for_each(|ted_talk: &TEDTalk| {
-            output.push_str(&ted_talk.to_string());
+            output.push_str(&format!("{}\n", ted_talk.resource_details.id));
         });
         output
     }
 }
 
-pub struct Person {
-    //later
+pub struct Podcast {
+    pub resource_details: Resource,
+    pub url: String,
+    pub series: String,
+    pub episode: String,
+    pub hosts: Vec<Creator>,
+    pub guests: Vec<Creator>,
+    pub running_time: String,
+    pub publish_date: String,
 }
 
-pub struct Quote {
-    //later
+impl New<Podcast> for Podcast {
+    fn new(item: &Item, rule: Option<&Rule>) -> Result<Podcast> {
+        Ok(Podcast {
+            resource_details: Resource::new(item, rule)?,
+            url: required(item, "url", &item.url)?,
+            series: item.series_title.clone().unwrap_or_default(),
+            episode: item.episode_number.clone().unwrap_or_default(),
+            // Zotero calls podcast hosts podcasters and the people on a recording performers
+            hosts: creators_of(
+                &item.creators,
+                &["podcaster", "host", "performer", "author"],
+            ),
+            guests: creators_of(&item.creators, &["guest"]),
+            running_time: item.running_time.clone().unwrap_or_default(),
+            publish_date: item.published_date.clone().unwrap_or_default(),
+        })
+    }
 }
 
-fn display_authors(authors: &Vec<Creator>) -> String {
-    let mut output = "".to_string();
-    authors.iter().for_each(|author| {
-        if author.name.is_some() {
-            output.push_str(&format!(
-                "\n- [[{}]],",
-                author
-                    .name
-                    .clone()
-                    .expect("failed to get first name for author of {}")
-            ));
-        } else if author.first_name.is_some() && author.last_name.is_some() {
-            output.push_str(&format!(
-                "\n- [[{} {}]],",
-                author.first_name.clone().expect(&format!(
-                    "failed to get first name for author of {}",
-                    &author
-                        .creator_type
-                        .clone()
-                        .expect("failed to get creator even")
-                )),
-                author.last_name.clone().expect("failed to get last name")
-            ))
-        } else {
-            output.push_str("Failed to get creator's name!")
-        }
-    });
-    output.pop();
-    output
+impl ResourceNote for Podcast {
+    fn kind(&self) -> &'static str {
+        "podcast"
+    }
+
+    fn details(&self) -> &Resource {
+        &self.resource_details
+    }
+
+    fn context(&self, config: &VaultConfig) -> Context {
+        let mut context = self.resource_details.context(config);
+        context
+            .set("hosts", people(&self.hosts))
+            .set("guests", people(&self.guests))
+            .set("url", &self.url)
+            .set("series", &self.series)
+            .set("episode", &self.episode)
+            .set("running_time", &self.running_time)
+            .set("publish_date", &self.publish_date);
+        context
+    }
 }
 
-fn display_tags(tags: &Vec<Tag>) -> String {
-    let mut output = "".to_string();
-    tags.iter().for_each(|tag| {
-        output.push_str(&format!("\n- #{},", tag.tag));
-    });
-    output.pop();
-    output
+pub struct Podcasts {
+    pub podcast_list: Vec<Podcast>,
 }
 
-fn display_notes(notes: &Vec<Note>) -> String {
-    let mut output = "".to_string();
-    notes.iter().rev().for_each(|note| {
-        // figure out way to order notes
-        output.push_str(&format!("{}\n---\n", note.content));
-    });
-    output.pop();
-    output
+impl ResourceList<Podcast> for Podcasts {
+    fn add(&mut self, podcast: Podcast) {
+        self.podcast_list.push(podcast);
+    }
+
+    fn print(&self) -> String {
+        let mut output = "".to_string();
+        self.podcast_list.iter().for_each(|podcast| {
+            output.push_str(&format!("{}\n", podcast.resource_details.id));
+        });
+        output
+    }
+}
+
+pub struct BookSection {
+    pub resource_details: Resource,
+    pub book_title: String,
+    pub editors: Vec<Creator>,
+    pub publisher: String,
+    pub pages: String,
+    pub publish_date: String,
+}
+
+impl New<BookSection> for BookSection {
+    fn new(item: &Item, rule: Option<&Rule>) -> Result<BookSection> {
+        Ok(BookSection {
+            resource_details: Resource::new(item, rule)?,
+            book_title: required(item, "bookTitle", &item.book_title)?,
+            editors: creators_of(&item.creators, &["editor"]),
+            publisher: item.publisher.clone().unwrap_or_default(),
+            pages: item.pages.clone().unwrap_or_default(),
+            publish_date: item.published_date.clone().unwrap_or_default(),
+        })
+    }
+}
+
+impl ResourceNote for BookSection {
+    fn kind(&self) -> &'static str {
+        "book_section"
+    }
+
+    fn details(&self) -> &Resource {
+        &self.resource_details
+    }
+
+    fn context(&self, config: &VaultConfig) -> Context {
+        let mut context = self.resource_details.context(config);
+        context
+            .set(
+                "authors",
+                people(&creators_of(&self.resource_details.creators, &["author"])),
+            )
+            .set("editors", people(&self.editors))
+            .set("book_title", &self.book_title)
+            .set("publisher", &self.publisher)
+            .set("pages", &self.pages)
+            .set("publish_date", &self.publish_date);
+        context
+    }
+}
+
+pub struct BookSections {
+    pub book_section_list: Vec<BookSection>,
+}
+
+impl ResourceList<BookSection> for BookSections {
+    fn add(&mut self, book_section: BookSection) {
+        self.book_section_list.push(book_section);
+    }
+
+    fn print(&self) -> String {
+        let mut output = "".to_string();
+        self.book_section_list.iter().for_each(|book_section| {
+            output.push_str(&format!("{}\n", book_section.resource_details.id));
+        });
+        output
+    }
+}
+
+pub struct Thesis {
+    pub resource_details: Resource,
+    pub university: String,
+    pub degree: String,
+    pub place: String,
+    pub publish_date: String,
+}
+
+impl New<Thesis> for Thesis {
+    fn new(item: &Item, rule: Option<&Rule>) -> Result<Thesis> {
+        Ok(Thesis {
+            resource_details: Resource::new(item, rule)?,
+            university: required(item, "university", &item.university)?,
+            degree: item.thesis_type.clone().unwrap_or_default(),
+            place: item.place.clone().unwrap_or_default(),
+            publish_date: item.published_date.clone().unwrap_or_default(),
+        })
+    }
+}
+
+impl ResourceNote for Thesis {
+    fn kind(&self) -> &'static str {
+        "thesis"
+    }
+
+    fn details(&self) -> &Resource {
+        &self.resource_details
+    }
+
+    fn context(&self, config: &VaultConfig) -> Context {
+        let mut context = self.resource_details.context(config);
+        context
+            .set("university", &self.university)
+            .set("degree", &self.degree)
+            .set("place", &self.place)
+            .set("publish_date", &self.publish_date);
+        context
+    }
+}
+
+pub struct Theses {
+    pub thesis_list: Vec<Thesis>,
+}
+
+impl ResourceList<Thesis> for Theses {
+    fn add(&mut self, thesis: Thesis) {
+        self.thesis_list.push(thesis);
+    }
+
+    fn print(&self) -> String {
+        let mut output = "".to_string();
+        self.thesis_list.iter().for_each(|thesis| {
+            output.push_str(&format!("{}\n", thesis.resource_details.id));
+        });
+        output
+    }
+}
+
+pub struct ConferencePaper {
+    pub resource_details: Resource,
+    pub proceedings: String,
+    pub conference: String,
+    pub doi: String,
+    pub pages: String,
+    pub publish_date: String,
+}
+
+impl New<ConferencePaper> for ConferencePaper {
+    fn new(item: &Item, rule: Option<&Rule>) -> Result<ConferencePaper> {
+        // a paper that was presented but never published only has the conference
+        if item.proceedings_title.is_none() && item.conference_name.is_none() {
+            return Err(Error::missing(item, "proceedingsTitle"));
+        }
+        Ok(ConferencePaper {
+            resource_details: Resource::new(item, rule)?,
+            proceedings: item.proceedings_title.clone().unwrap_or_default(),
+            conference: item.conference_name.clone().unwrap_or_default(),
+            doi: item.doi.clone().unwrap_or_default(),
+            pages: item.pages.clone().unwrap_or_default(),
+            publish_date: item.published_date.clone().unwrap_or_default(),
+        })
+    }
+}
+
+impl ResourceNote for ConferencePaper {
+    fn kind(&self) -> &'static str {
+        "conference_paper"
+    }
+
+    fn details(&self) -> &Resource {
+        &self.resource_details
+    }
+
+    fn context(&self, config: &VaultConfig) -> Context {
+        let mut context = self.resource_details.context(config);
+        context
+            .set("proceedings", &self.proceedings)
+            .set("conference", &self.conference)
+            .set("doi", &self.doi)
+            .set("pages", &self.pages)
+            .set("publish_date", &self.publish_date);
+        context
+    }
+}
+
+pub struct ConferencePapers {
+    pub conference_paper_list: Vec<ConferencePaper>,
+}
+
+impl ResourceList<ConferencePaper> for ConferencePapers {
+    fn add(&mut self, conference_paper: ConferencePaper) {
+        self.conference_paper_list.push(conference_paper);
+    }
+
+    fn print(&self) -> String {
+        let mut output = "".to_string();
+        self.conference_paper_list
+            .iter()
+            .for_each(|conference_paper| {
+                output.push_str(&format!("{}\n", conference_paper.resource_details.id));
+            });
+        output
+    }
+}
+
+pub struct Report {
+    pub resource_details: Resource,
+    pub institution: String,
+    pub report_number: String,
+    pub report_type: String,
+    pub url: String,
+    pub publish_date: String,
+}
+
+impl New<Report> for Report {
+    fn new(item: &Item, rule: Option<&Rule>) -> Result<Report> {
+        Ok(Report {
+            resource_details: Resource::new(item, rule)?,
+            institution: required(item, "institution", &item.institution)?,
+            report_number: item.report_number.clone().unwrap_or_default(),
+            report_type: item.report_type.clone().unwrap_or_default(),
+            url: item.url.clone().unwrap_or_default(),
+            publish_date: item.published_date.clone().unwrap_or_default(),
+        })
+    }
+}
+
+impl ResourceNote for Report {
+    fn kind(&self) -> &'static str {
+        "report"
+    }
+
+    fn details(&self) -> &Resource {
+        &self.resource_details
+    }
+
+    fn context(&self, config: &VaultConfig) -> Context {
+        let mut context = self.resource_details.context(config);
+        context
+            .set("institution", &self.institution)
+            .set("report_number", &self.report_number)
+            .set("report_type", &self.report_type)
+            .set("url", &self.url)
+            .set("publish_date", &self.publish_date);
+        context
+    }
+}
+
+pub struct Reports {
+    pub report_list: Vec<Report>,
+}
+
+impl ResourceList<Report> for Reports {
+    fn add(&mut self, report: Report) {
+        self.report_list.push(report);
+    }
+
+    fn print(&self) -> String {
+        let mut output = "".to_string();
+        self.report_list.iter().for_each(|report| {
+            output.push_str(&format!("{}\n", report.resource_details.id));
+        });
+        output
+    }
+}
+
+pub struct LegalCase {
+    pub resource_details: Resource,
+    pub court: String,
+    pub jurisdiction: String,
+    pub reporter: String,
+    pub docket_number: String,
+    pub publish_date: String,
+    // e.g. `Brown v. Board of Education, 347 U.S. 483 (1954)`
+    pub citation: String,
+}
+
+impl New<LegalCase> for LegalCase {
+    fn new(item: &Item, rule: Option<&Rule>) -> Result<LegalCase> {
+        let resource_details = Resource::new(item, rule)?;
+        let court = required(item, "court", &item.court)?;
+        let publish_date = item.published_date.clone().unwrap_or_default();
+        let reporter = [&item.reporter_volume, &item.reporter, &item.first_page]
+            .iter()
+            .filter_map(|part| part.as_deref())
+            .collect::<Vec<&str>>()
+            .join(" ");
+        // a case is cited by its reporter, or by its docket number until it is reported
+        let location = match (&reporter[..], &item.docket_number) {
+            ("", Some(docket_number)) => format!("No. {}", docket_number),
+            _ => reporter.clone(),
+        };
+        let citation = short_citation(
+            &resource_details.full_title,
+            &[location],
+            &[court.clone(), year_of(&publish_date)],
+        );
+        Ok(LegalCase {
+            resource_details,
+            court,
+            jurisdiction: jurisdiction_of(item),
+            reporter,
+            docket_number: item.docket_number.clone().unwrap_or_default(),
+            publish_date,
+            citation,
+        })
+    }
+}
+
+impl ResourceNote for LegalCase {
+    fn kind(&self) -> &'static str {
+        "legal_case"
+    }
+
+    fn details(&self) -> &Resource {
+        &self.resource_details
+    }
+
+    fn citation(&self) -> &str {
+        &self.citation
+    }
+
+    fn context(&self, config: &VaultConfig) -> Context {
+        let mut context = self.resource_details.context(config);
+        context
+            .set("citation", &self.citation)
+            .set("court", &self.court)
+            .set("jurisdiction", &self.jurisdiction)
+            .set("reporter", &self.reporter)
+            .set("docket_number", &self.docket_number)
+            .set("publish_date", &self.publish_date);
+        context
+    }
+}
+
+pub struct LegalCases {
+    pub legal_case_list: Vec<LegalCase>,
+}
+
+impl ResourceList<LegalCase> for LegalCases {
+    fn add(&mut self, legal_case: LegalCase) {
+        self.legal_case_list.push(legal_case);
+    }
+
+    fn print(&self) -> String {
+        let mut output = "".to_string();
+        self.legal_case_list.iter().for_each(|legal_case| {
+            output.push_str(&format!("{}\n", legal_case.resource_details.id));
+        });
+        output
+    }
+}
+
+pub struct Statute {
+    pub resource_details: Resource,
+    pub jurisdiction: String,
+    pub code: String,
+    pub section: String,
+    pub public_law_number: String,
+    pub publish_date: String,
+    // e.g. `Civil Rights Act of 1964, Pub. L. No. 88-352, 42 U.S.C. § 2000e (1964)`
+    pub citation: String,
+}
+
+impl New<Statute> for Statute {
+    fn new(item: &Item, rule: Option<&Rule>) -> Result<Statute> {
+        let resource_details = Resource::new(item, rule)?;
+        let publish_date = item.published_date.clone().unwrap_or_default();
+        let code = [&item.code_number, &item.code]
+            .iter()
+            .filter_map(|part| part.as_deref())
+            .collect::<Vec<&str>>()
+            .join(" ");
+        let section = item.section.clone().unwrap_or_default();
+        let public_law_number = item.public_law_number.clone().unwrap_or_default();
+        let citation = short_citation(
+            &resource_details.full_title,
+            &[
+                match &public_law_number[..] {
+                    "" => String::new(),
+                    number => format!("Pub. L. No. {}", number),
+                },
+                match &section[..] {
+                    "" => code.clone(),
+                    section => format!("{} § {}", code, section).trim().to_string(),
+                },
+            ],
+            &[year_of(&publish_date)],
+        );
+        Ok(Statute {
+            resource_details,
+            jurisdiction: jurisdiction_of(item),
+            code,
+            section,
+            public_law_number,
+            publish_date,
+            citation,
+        })
+    }
+}
+
+impl ResourceNote for Statute {
+    fn kind(&self) -> &'static str {
+        "statute"
+    }
+
+    fn details(&self) -> &Resource {
+        &self.resource_details
+    }
+
+    fn citation(&self) -> &str {
+        &self.citation
+    }
+
+    fn context(&self, config: &VaultConfig) -> Context {
+        let mut context = self.resource_details.context(config);
+        context
+            .set("citation", &self.citation)
+            .set("jurisdiction", &self.jurisdiction)
+            .set("code", &self.code)
+            .set("section", &self.section)
+            .set("public_law_number", &self.public_law_number)
+            .set("publish_date", &self.publish_date);
+        context
+    }
+}
+
+pub struct Statutes {
+    pub statute_list: Vec<Statute>,
+}
+
+impl ResourceList<Statute> for Statutes {
+    fn add(&mut self, statute: Statute) {
+        self.statute_list.push(statute);
+    }
+
+    fn print(&self) -> String {
+        let mut output = "".to_string();
+        self.statute_list.iter().for_each(|statute| {
+            output.push_str(&format!("{}\n", statute.resource_details.id));
+        });
+        output
+    }
+}
+
+pub struct Bill {
+    pub resource_details: Resource,
+    pub jurisdiction: String,
+    pub bill_number: String,
+    pub legislative_body: String,
+    pub session: String,
+    pub code: String,
+    pub section: String,
+    pub publish_date: String,
+    // e.g. `For the People Act, H.R. 1, 117th Cong. (2021)`
+    pub citation: String,
+}
+
+impl New<Bill> for Bill {
+    fn new(item: &Item, rule: Option<&Rule>) -> Result<Bill> {
+        let resource_details = Resource::new(item, rule)?;
+        let bill_number = required(item, "billNumber", &item.bill_number)?;
+        let publish_date = item.published_date.clone().unwrap_or_default();
+        let session = item.session.clone().unwrap_or_default();
+        // Zotero keeps the chamber's abbreviation (`H.R.`, `S.`) in the code field
+        let code = item.code.clone().unwrap_or_default();
+        let citation = short_citation(
+            &resource_details.full_title,
+            &[
+                format!("{} {}", code, bill_number).trim().to_string(),
+                session.clone(),
+            ],
+            &[year_of(&publish_date)],
+        );
+        Ok(Bill {
+            resource_details,
+            jurisdiction: jurisdiction_of(item),
+            bill_number,
+            legislative_body: item.legislative_body.clone().unwrap_or_default(),
+            session,
+            code,
+            section: item.section.clone().unwrap_or_default(),
+            publish_date,
+            citation,
+        })
+    }
+}
+
+impl ResourceNote for Bill {
+    fn kind(&self) -> &'static str {
+        "bill"
+    }
+
+    fn details(&self) -> &Resource {
+        &self.resource_details
+    }
+
+    fn citation(&self) -> &str {
+        &self.citation
+    }
+
+    fn context(&self, config: &VaultConfig) -> Context {
+        let mut context = self.resource_details.context(config);
+        context
+            .set("citation", &self.citation)
+            .set("jurisdiction", &self.jurisdiction)
+            .set("bill_number", &self.bill_number)
+            .set("legislative_body", &self.legislative_body)
+            .set("session", &self.session)
+            .set("code", &self.code)
+            .set("section", &self.section)
+            .set("publish_date", &self.publish_date);
+        context
+    }
+}
+
+pub struct Bills {
+    pub bill_list: Vec<Bill>,
+}
+
+impl ResourceList<Bill> for Bills {
+    fn add(&mut self, bill: Bill) {
+        self.bill_list.push(bill);
+    }
+
+    fn print(&self) -> String {
+        let mut output = "".to_string();
+        self.bill_list.iter().for_each(|bill| {
+            output.push_str(&format!("{}\n", bill.resource_details.id));
+        });
+        output
+    }
+}
+
+// `Title, location, location (parenthetical)`, leaving out whatever is empty
+fn short_citation(title: &str, locations: &[String], parenthetical: &[String]) -> String {
+    let mut citation = title.to_string();
+    locations
+        .iter()
+        .filter(|location| !location.is_empty())
+        .for_each(|location| citation.push_str(&format!(", {}", location)));
+    let parenthetical: Vec<&str> = parenthetical
+        .iter()
+        .map(String::as_str)
+        .filter(|part| !part.is_empty())
+        .collect();
+    if !parenthetical.is_empty() {
+        citation.push_str(&format!(" ({})", parenthetical.join(" ")));
+    }
+    citation
+}
+
+fn year_of(date: &str) -> String {
+    static YEAR: OnceLock<Regex> = OnceLock::new();
+    YEAR.get_or_init(|| Regex::new(r"\b\d{4}\b").expect("invalid year pattern"))
+        .find(date)
+        .map_or(String::new(), |year| year.as_str().to_string())
+}
+
+// Zotero has no jurisdiction field for every legal type, so it often lives in `extra`
+fn jurisdiction_of(item: &Item) -> String {
+    static EXTRA_LINE: OnceLock<Regex> = OnceLock::new();
+    let extra_line = EXTRA_LINE.get_or_init(|| {
+        Regex::new(r"(?im)^jurisdiction:\s*(.+)$").expect("invalid jurisdiction pattern")
+    });
+    item.jurisdiction
+        .clone()
+        .or_else(|| {
+            item.extra
+                .as_deref()
+                .and_then(|extra| extra_line.captures(extra))
+                .map(|captures| captures[1].trim().to_string())
+        })
+        .unwrap_or_default()
+}
+
+pub struct Software {
+    pub resource_details: Resource,
+    pub version: String,
+    pub repository: String,
+    pub programming_language: String,
+    pub licence: String,
+    pub url: String,
+    pub doi: String,
+    pub publish_date: String,
+    pub cite_as: String,
+}
+
+impl New<Software> for Software {
+    fn new(item: &Item, rule: Option<&Rule>) -> Result<Software> {
+        let resource_details = Resource::new(item, rule)?;
+        let url = item.url.clone().unwrap_or_default();
+        // Zotero has nowhere to put a code repository but the URL; software that was never
+        // published anywhere still gets a note, just without a repository link
+        let repository = item.repository.clone().unwrap_or_else(|| url.clone());
+        let version = item.version_number.clone().unwrap_or_default();
+        let doi = item.doi.clone().unwrap_or_default();
+        let publish_date = item.published_date.clone().unwrap_or_default();
+        let cite_as = cite_as(
+            &resource_details,
+            &publish_date,
+            &version,
+            "Computer software",
+            &[
+                item.company.clone().unwrap_or_default(),
+                link_to(&doi, &url),
+            ],
+        );
+        Ok(Software {
+            resource_details,
+            version,
+            repository,
+            programming_language: item.programming_language.clone().unwrap_or_default(),
+            licence: item.rights.clone().unwrap_or_default(),
+            url,
+            doi,
+            publish_date,
+            cite_as,
+        })
+    }
+}
+
+impl ResourceNote for Software {
+    fn kind(&self) -> &'static str {
+        "software"
+    }
+
+    fn details(&self) -> &Resource {
+        &self.resource_details
+    }
+
+    fn context(&self, config: &VaultConfig) -> Context {
+        let mut context = self.resource_details.context(config);
+        context
+            .set("version", &self.version)
+            .set("repository", &self.repository)
+            .set("programming_language", &self.programming_language)
+            .set("licence", &self.licence)
+            .set("url", &self.url)
+            .set("doi", &self.doi)
+            .set("publish_date", &self.publish_date)
+            .set("cite_as", &self.cite_as);
+        context
+    }
+}
+
+pub struct SoftwareList {
+    pub software_list: Vec<Software>,
+}
+
+impl ResourceList<Software> for SoftwareList {
+    fn add(&mut self, software: Software) {
+        self.software_list.push(software);
+    }
+
+    fn print(&self) -> String {
+        let mut output = "".to_string();
+        self.software_list.iter().for_each(|software| {
+            output.push_str(&format!("{}\n", software.resource_details.id));
+        });
+        output
+    }
+}
+
+pub struct Dataset {
+    pub resource_details: Resource,
+    pub version: String,
+    pub repository: String,
+    pub licence: String,
+    pub url: String,
+    pub doi: String,
+    // the DOI when there is one, it outlives the URL
+    pub link: String,
+    pub publish_date: String,
+    pub cite_as: String,
+}
+
+impl New<Dataset> for Dataset {
+    fn new(item: &Item, rule: Option<&Rule>) -> Result<Dataset> {
+        let resource_details = Resource::new(item, rule)?;
+        let url = item.url.clone().unwrap_or_default();
+        let doi = item.doi.clone().unwrap_or_default();
+        let link = link_to(&doi, &url);
+        if link.is_empty() {
+            return Err(Error::missing(item, "DOI"));
+        }
+        let version = item.version_number.clone().unwrap_or_default();
+        let repository = item
+            .repository
+            .clone()
+            .or_else(|| item.publisher.clone())
+            .unwrap_or_default();
+        let publish_date = item.published_date.clone().unwrap_or_default();
+        let cite_as = cite_as(
+            &resource_details,
+            &publish_date,
+            &version,
+            "Data set",
+            &[repository.clone(), link.clone()],
+        );
+        Ok(Dataset {
+            resource_details,
+            version,
+            repository,
+            licence: item.rights.clone().unwrap_or_default(),
+            url,
+            doi,
+            link,
+            publish_date,
+            cite_as,
+        })
+    }
+}
+
+impl ResourceNote for Dataset {
+    fn kind(&self) -> &'static str {
+        "dataset"
+    }
+
+    fn details(&self) -> &Resource {
+        &self.resource_details
+    }
+
+    fn context(&self, config: &VaultConfig) -> Context {
+        let mut context = self.resource_details.context(config);
+        context
+            .set("version", &self.version)
+            .set("repository", &self.repository)
+            .set("licence", &self.licence)
+            .set("url", &self.url)
+            .set("doi", &self.doi)
+            .set("link", &self.link)
+            .set("publish_date", &self.publish_date)
+            .set("cite_as", &self.cite_as);
+        context
+    }
+}
+
+pub struct Datasets {
+    pub dataset_list: Vec<Dataset>,
+}
+
+impl ResourceList<Dataset> for Datasets {
+    fn add(&mut self, dataset: Dataset) {
+        self.dataset_list.push(dataset);
+    }
+
+    fn print(&self) -> String {
+        let mut output = "".to_string();
+        self.dataset_list.iter().for_each(|dataset| {
+            output.push_str(&format!("{}\n", dataset.resource_details.id));
+        });
+        output
+    }
+}
+
+fn link_to(doi: &str, url: &str) -> String {
+    match doi {
+        "" => url.to_string(),
+        doi => format!("https://doi.org/{}", doi),
+    }
+}
+
+// APA style, e.g. `Harris, C. R. (2020). NumPy (Version 1.19) [Computer software].
+// https://doi.org/...`, which is what most software and data citation guides ask for
+fn cite_as(
+    resource: &Resource,
+    date: &str,
+    version: &str,
+    medium: &str,
+    sources: &[String],
+) -> String {
+    let authors = resource
+        .creators
+        .iter()
+        .filter_map(
+            |creator| match (&creator.name, &creator.first_name, &creator.last_name) {
+                (Some(name), _, _) => Some(name.clone()),
+                (None, Some(first_name), Some(last_name)) => Some(format!(
+                    "{}, {}",
+                    last_name,
+                    first_name
+                        .split_whitespace()
+                        .filter_map(|name| name.chars().next())
+                        .map(|initial| format!("{}.", initial))
+                        .collect::<Vec<String>>()
+                        .join(" ")
+                )),
+                (None, None, Some(last_name)) => Some(last_name.clone()),
+                _ => None,
+            },
+        )
+        .collect::<Vec<String>>();
+    let year = match year_of(date).as_str() {
+        "" => "n.d.".to_string(),
+        year => year.to_string(),
+    };
+    let mut work = resource.full_title.clone();
+    if !version.is_empty() {
+        work.push_str(&format!(" (Version {})", version));
+    }
+    work.push_str(&format!(" [{}]", medium));
+    // without authors the work itself goes first
+    let mut citation = match authors.len() {
+        0 => format!("{}. ({}).", work, year),
+        1 => format!("{} ({}). {}.", authors[0], year, work),
+        count => format!(
+            "{}, & {} ({}). {}.",
+            authors[..count - 1].join(", "),
+            authors[count - 1],
+            year,
+            work
+        ),
+    };
+    let sources = sources
+        .iter()
+        .filter(|source| !source.is_empty())
+        .cloned()
+        .collect::<Vec<String>>();
+    if !sources.is_empty() {
+        citation.push_str(&format!(" {}", sources.join(". ")));
+    }
+    citation
+}
+
+// Anything without a resource type of its own, so that no item goes missing from the
+// vault. Every field Zotero filled in is listed under its Zotero name.
+pub struct Generic {
+    pub resource_details: Resource,
+    pub item_type: String,
+    pub fields: Vec<(String, String)>,
+}
+
+// fields the generic template already shows in its own place
+const GENERIC_SHOWN_FIELDS: &[&str] = &["citationKey", "itemType", "title", "select", "uri"];
+
+impl New<Generic> for Generic {
+    fn new(item: &Item, rule: Option<&Rule>) -> Result<Generic> {
+        let fields = match serde_json::to_value(item) {
+            Ok(serde_json::Value::Object(fields)) => fields
+                .into_iter()
+                .filter(|(name, _)| !GENERIC_SHOWN_FIELDS.contains(&name.as_str()))
+                .filter_map(|(name, value)| match value {
+                    serde_json::Value::String(value) if !value.is_empty() => Some((name, value)),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        Ok(Generic {
+            resource_details: Resource::new(item, rule)?,
+            item_type: item.item_type.clone(),
+            fields,
+        })
+    }
+}
+
+impl ResourceNote for Generic {
+    fn kind(&self) -> &'static str {
+        "generic"
+    }
+
+    fn details(&self) -> &Resource {
+        &self.resource_details
+    }
+
+    fn context(&self, config: &VaultConfig) -> Context {
+        let mut context = self.resource_details.context(config);
+        context
+            .set("item_type", &self.item_type)
+            .set("fields", field_list(&self.fields));
+        context
+    }
+}
+
+pub struct Generics {
+    pub generic_list: Vec<Generic>,
+}
+
+impl ResourceList<Generic> for Generics {
+    fn add(&mut self, generic: Generic) {
+        self.generic_list.push(generic);
+    }
+
+    fn print(&self) -> String {
+        let mut output = "".to_string();
+        self.generic_list.iter().for_each(|generic| {
+            output.push_str(&format!("{}\n", generic.resource_details.id));
+        });
+        output
+    }
+}
+
+pub struct Person {
+    //later
+}
+
+pub struct Quote {
+    //later
+}
+
+fn display_authors(authors: &Vec<Creator>) -> String {
+    let mut output = "".to_string();
+    authors.iter().for_each(
+        |author| match (&author.name, &author.first_name, &author.last_name) {
+            (Some(name), _, _) => output.push_str(&format!("\n- [[{}]],", name)),
+            (None, Some(first_name), Some(last_name)) => {
+                output.push_str(&format!("\n- [[{} {}]],", first_name, last_name))
+            }
+            (None, None, Some(last_name)) => output.push_str(&format!("\n- [[{}]],", last_name)),
+            _ => output.push_str("Failed to get creator's name!"),
+        },
+    );
+    output.pop();
+    output
+}
+
+// creators with their `name`, `first_name`, `last_name` and `creator_type`, each printed
+// as the name
+fn people(creators: &[Creator]) -> Value {
+    let people = creators
+        .iter()
+        .map(|creator| {
+            let name = match (&creator.name, &creator.first_name, &creator.last_name) {
+                (Some(name), _, _) => name.clone(),
+                (None, Some(first_name), Some(last_name)) => {
+                    format!("{} {}", first_name, last_name)
+                }
+                (None, None, Some(last_name)) => last_name.clone(),
+                _ => String::new(),
+            };
+            Value::object(
+                vec![
+                    ("name", name.clone()),
+                    ("first_name", creator.first_name.clone().unwrap_or_default()),
+                    ("last_name", creator.last_name.clone().unwrap_or_default()),
+                    (
+                        "creator_type",
+                        creator
+                            .creator_type
+                            .clone()
+                            .unwrap_or_else(|| "author".to_string()),
+                    ),
+                ],
+                name,
+            )
+        })
+        .collect();
+    Value::List(people, display_authors(&creators.to_vec()))
+}
+
+// creators in one role; Zotero leaves the type off authors in some exports
+fn creators_of(creators: &[Creator], creator_types: &[&str]) -> Vec<Creator> {
+    creators
+        .iter()
+        .filter(|creator| {
+            creator_types.contains(&creator.creator_type.as_deref().unwrap_or("author"))
+        })
+        .cloned()
+        .collect()
+}
+
+fn display_fields(fields: &[(String, String)]) -> String {
+    fields
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn field_list(fields: &[(String, String)]) -> Value {
+    let items = fields
+        .iter()
+        .map(|(name, value)| {
+            Value::object(
+                vec![("name", name.clone()), ("value", value.clone())],
+                value.clone(),
+            )
+        })
+        .collect();
+    Value::List(items, display_fields(fields))
+}
+
+fn tag_list(tags: &Vec<Tag>, prefix: &str) -> Value {
+    let items = tags
+        .iter()
+        .map(|tag| Value::from(format!("{}{}", prefix, tag.tag)))
+        .collect();
+    Value::List(items, display_tags(tags, prefix))
+}
+
+fn display_tags(tags: &Vec<Tag>, prefix: &str) -> String {
+    let mut output = "".to_string();
+    tags.iter().for_each(|tag| {
+        output.push_str(&format!("\n- {}{},", prefix, tag.tag));
+    });
+    output.pop();
+    output
+}
+
+fn note_list(notes: &Vec<Note>) -> Value {
+    let items = notes
+        .iter()
+        .rev()
+        .map(|note| Value::from(&note.content))
+        .collect();
+    Value::List(items, display_notes(notes))
+}
+
+fn display_notes(notes: &Vec<Note>) -> String {
+    let mut output = "".to_string();
+    notes.iter().rev().for_each(|note| {
+        // figure out way to order notes; the blank line keeps `---` from turning the
+        // note's last line into a heading
+        output.push_str(&format!("{}\n\n---\n", note.content));
+    });
+    output.pop();
+    output
+}
+
+// each attachment printed as a link to it
+fn attachment_list(attachments: &[Attachment]) -> Value {
+    let items = attachments
+        .iter()
+        .filter_map(|attachment| {
+            let title = attachment
+                .title
+                .clone()
+                .unwrap_or_else(|| "attachment".to_string());
+            let link = match (&attachment.path, &attachment.url) {
+                (Some(path), _) => format!("file://{}", path),
+                (None, Some(url)) => url.clone(),
+                (None, None) => return None,
+            };
+            let shown = format!("[Open {}](<{}>)", title, link);
+            Some(Value::object(vec![("title", title), ("link", link)], shown))
+        })
+        .collect();
+    Value::List(items, display_attachments(attachments))
+}
+
+// linked files, with paths already resolved against Zotero's base attachment directory
+fn display_attachments(attachments: &[Attachment]) -> String {
+    let mut output = "".to_string();
+    attachments.iter().for_each(|attachment| {
+        let title = attachment
+            .title
+            .clone()
+            .unwrap_or_else(|| "attachment".to_string());
+        if let Some(path) = &attachment.path {
+            output.push_str(&format!("\n- [Open {}](<file://{}>)", title, path));
+        } else if let Some(url) = &attachment.url {
+            output.push_str(&format!("\n- [Open {}](<{}>)", title, url));
+        }
+    });
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    // an item with empty creators, tags and notes unless it has some
+    fn item(mut fields: serde_json::Value) -> Item {
+        let object = fields
+            .as_object_mut()
+            .expect("test item should be an object");
+        for list in ["creators", "tags", "notes"] {
+            object.entry(list).or_insert_with(|| json!([]));
+        }
+        serde_json::from_value(fields).expect("test item should deserialize")
+    }
+
+    fn config(toml: &str) -> VaultConfig {
+        toml::from_str(toml).expect("test config should parse")
+    }
+
+    // a template folder of its own where every template says which file it is
+    fn template_dir(name: &str, config: &VaultConfig) -> PathBuf {
+        let dir = env::temp_dir().join(format!(
+            "zotero-to-obsidian-templates-{}-{}",
+            name,
+            process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (_, template) in config.template_uses() {
+            let source = format!("{}: {{{{full_title}}}} {{{{tags | join:\" \"}}}}", template);
+            fs::write(dir.join(template), source).unwrap();
+        }
+        dir
+    }
+
+    fn rendered(items: Vec<Item>, config: &VaultConfig, templates: &Templates) -> Vec<String> {
+        let mut references = References::new();
+        references.populate(&items, config);
+        references
+            .notes()
+            .into_iter()
+            .map(|note| templates.render(note, config).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn renders_with_the_templates_and_config_it_is_given() {
+        let book = || {
+            item(json!({
+                "citationKey": "smith2020",
+                "itemType": "book",
+                "title": "Fields",
+                "ISBN": "978-3-16-148410-0",
+                "shortTitle": "Fields",
+                "date": "2020",
+                "tags": [{"tag": "physics"}]
+            }))
+        };
+        let letter =
+            item(json!({"citationKey": "lee2019", "itemType": "letter", "title": "Dear Ann"}));
+
+        let plain = VaultConfig::default();
+        let templates = Templates::load(&template_dir("plain", &plain), &plain).unwrap();
+        assert_eq!(
+            rendered(vec![book(), letter], &plain, &templates),
+            ["Book.md: Fields #physics", "Generic.md: Dear Ann "]
+        );
+
+        let shelved = config(
+            r#"
+            tag_prefix = "topic/"
+            [[rules]]
+            item_type = "book"
+            resource = "book"
+            template = "Shelf.md"
+            "#,
+        );
+        let templates = Templates::load(&template_dir("shelved", &shelved), &shelved).unwrap();
+        assert_eq!(
+            rendered(vec![book()], &shelved, &templates),
+            ["Shelf.md: Fields topic/physics"]
+        );
+    }
+
+    #[test]
+    fn stops_at_a_missing_template() {
+        let plain = VaultConfig::default();
+        let dir = template_dir("missing", &plain);
+        fs::remove_file(dir.join("Thesis.md")).unwrap();
+        assert!(Templates::load(&dir, &plain)
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("Thesis.md"));
+    }
+
+    #[test]
+    fn does_not_read_templates_it_did_not_load() {
+        let plain = VaultConfig::default();
+        let shelved = config(
+            r#"
+            [[rules]]
+            item_type = "book"
+            resource = "book"
+            template = "Shelf.md"
+            "#,
+        );
+        // the template is there, but it was not read up front
+        let templates = Templates::load(&template_dir("unloaded", &shelved), &plain).unwrap();
+        let mut references = References::new();
+        references.populate(
+            &vec![item(json!({
+                "citationKey": "smith2020",
+                "itemType": "book",
+                "title": "Fields",
+                "ISBN": "978-3-16-148410-0",
+                "date": "2020"
+            }))],
+            &shelved,
+        );
+        let error = templates
+            .render(references.notes()[0], &shelved)
+            .unwrap_err();
+        assert!(error.to_string().ends_with("Shelf.md: template not loaded"));
+    }
+
+    fn preprint(mut fields: serde_json::Value) -> Preprint {
+        fields["itemType"] = json!("preprint");
+        fields["citationKey"] = json!("vaswani2017");
+        fields["title"] = json!("Attention Is All You Need");
+        Preprint::new(&item(fields), None).expect("test preprint should be read")
+    }
+
+    #[test]
+    fn reads_new_and_old_style_arxiv_ids() {
+        let new_style = preprint(json!({
+            "url": "https://arxiv.org/abs/1706.03762v5",
+            "publicationTitle": "arXiv:1706.03762 [cs.CL]"
+        }));
+        assert_eq!(
+            (new_style.archive_id.as_str(), new_style.version.as_str()),
+            ("arXiv:1706.03762", "5")
+        );
+        assert_eq!(new_style.pdf_url, "https://arxiv.org/pdf/1706.03762v5");
+        assert_eq!(new_style.repository, "arXiv");
+        assert_eq!(new_style.category, "cs.CL");
+
+        let pdf = preprint(json!({"url": "https://arxiv.org/pdf/1706.03762.pdf"}));
+        assert_eq!(
+            (pdf.archive_id.as_str(), pdf.version.as_str()),
+            ("arXiv:1706.03762", "")
+        );
+
+        let old_style = preprint(json!({
+            "url": "http://arxiv.org/abs/hep-th/9711200v3",
+            "extra": "tex.eprintclass: hep-th"
+        }));
+        assert_eq!(
+            (old_style.archive_id.as_str(), old_style.version.as_str()),
+            ("arXiv:hep-th/9711200", "3")
+        );
+        assert_eq!(old_style.pdf_url, "https://arxiv.org/pdf/hep-th/9711200v3");
+        assert_eq!(old_style.category, "hep-th");
+
+        // an export with the arXiv ID only in `archiveID`
+        let archived = preprint(json!({
+            "url": "https://example.org/mirror/9711200",
+            "archiveID": " arXiv:hep-th/9711200v1 "
+        }));
+        assert_eq!(
+            (archived.archive_id.as_str(), archived.version.as_str()),
+            ("arXiv:hep-th/9711200", "1")
+        );
+    }
+
+    #[test]
+    fn takes_the_published_doi_from_extra_or_relations() {
+        let from_extra = preprint(json!({
+            "url": "https://arxiv.org/abs/1706.03762",
+            "DOI": "10.48550/arXiv.1706.03762",
+            "extra": "tex.eprintclass: cs.CL\nPublished DOI: 10.5555/3295222.3295349"
+        }));
+        assert_eq!(from_extra.published_doi, "10.5555/3295222.3295349");
+        assert_eq!(from_extra.doi, "10.48550/arXiv.1706.03762");
+
+        let from_relation = preprint(json!({
+            "url": "https://arxiv.org/abs/1706.03762",
+            "relations": {"dc:relation": "https://doi.org/10.5555/3295222.3295349"}
+        }));
+        assert_eq!(from_relation.published_doi, "10.5555/3295222.3295349");
+
+        // the preprint's own DOI written into `extra` is not a published version
+        let own_doi = preprint(json!({
+            "url": "https://arxiv.org/abs/1706.03762",
+            "DOI": "10.48550/arXiv.1706.03762",
+            "extra": "DOI: 10.48550/arxiv.1706.03762"
+        }));
+        assert_eq!(own_doi.published_doi, "");
+    }
+
+    #[test]
+    fn links_a_preprint_to_the_name_its_published_version_gets() {
+        let by_title = config(r#"filename = "{title}""#);
+        let items = vec![
+            item(json!({
+                "citationKey": "vaswani2017a",
+                "itemType": "journalArticle",
+                "title": "Attention Is All You Need",
+                "DOI": "10.5555/3295222.3295349",
+                "date": "2017",
+                "publicationTitle": "NeurIPS"
+            })),
+            item(json!({
+                "citationKey": "vaswani2017b",
+                "itemType": "preprint",
+                "title": "Attention Is All You Need (preprint)",
+                "url": "https://arxiv.org/abs/1706.03762",
+                "extra": "Published DOI: 10.5555/3295222.3295349"
+            })),
+            item(json!({
+                "citationKey": "lee2019",
+                "itemType": "journalArticle",
+                "title": "Attention is all you need",
+                "DOI": "10.1000/182",
+                "date": "2019",
+                "publicationTitle": "Mind"
+            })),
+        ];
+        let mut references = References::new();
+        references.populate(&items, &by_title);
+        assert!(references.failed.is_empty());
+        assert_eq!(
+            references.preprints.preprint_list[0]
+                .published_note
+                .as_deref(),
+            Some("Attention Is All You Need (vaswani2017a)")
+        );
+    }
+
+    #[test]
+    fn cites_cases_statutes_and_bills() {
+        let case = LegalCase::new(
+            &item(json!({
+                "citationKey": "brown1954",
+                "itemType": "case",
+                "title": "Brown v. Board of Education",
+                "court": "U.S.",
+                "reporterVolume": "347",
+                "reporter": "U.S.",
+                "firstPage": "483",
+                "date": "1954-05-17"
+            })),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            case.citation,
+            "Brown v. Board of Education, 347 U.S. 483 (U.S. 1954)"
+        );
+        let unreported = LegalCase::new(
+            &item(json!({
+                "citationKey": "doe2024",
+                "itemType": "case",
+                "title": "Doe v. Roe",
+                "court": "S.D.N.Y.",
+                "docketNumber": "1:24-cv-01234",
+                "extra": "Jurisdiction: US"
+            })),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            unreported.citation,
+            "Doe v. Roe, No. 1:24-cv-01234 (S.D.N.Y.)"
+        );
+        assert_eq!(unreported.jurisdiction, "US");
+
+        let statute = |mut fields: serde_json::Value| {
+            fields["citationKey"] = json!("civilrights1964");
+            fields["itemType"] = json!("statute");
+            fields["title"] = json!("Civil Rights Act of 1964");
+            Statute::new(&item(fields), None).unwrap().citation
+        };
+        assert_eq!(
+            statute(json!({
+                "publicLawNumber": "88-352",
+                "codeNumber": "42",
+                "code": "U.S.C.",
+                "section": "2000e",
+                "date": "1964-07-02"
+            })),
+            "Civil Rights Act of 1964, Pub. L. No. 88-352, 42 U.S.C. § 2000e (1964)"
+        );
+        // a section with no code is cited on its own, without a leading space
+        assert_eq!(
+            statute(json!({"section": "2000e"})),
+            "Civil Rights Act of 1964, § 2000e"
+        );
+        assert_eq!(statute(json!({})), "Civil Rights Act of 1964");
+
+        let bill = Bill::new(
+            &item(json!({
+                "citationKey": "forthepeople2021",
+                "itemType": "bill",
+                "title": "For the People Act",
+                "code": "H.R.",
+                "billNumber": "1",
+                "session": "117th Cong.",
+                "date": "2021"
+            })),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            bill.citation,
+            "For the People Act, H.R. 1, 117th Cong. (2021)"
+        );
+    }
+
+    #[test]
+    fn names_notes_by_their_citation() {
+        let by_citation = config(r#"filename = "{citation}""#);
+        let case = LegalCase::new(
+            &item(json!({
+                "citationKey": "smith2001",
+                "itemType": "case",
+                "title": "Smith v. Jones",
+                "court": "9th Cir.",
+                "docketNumber": "99-123",
+                "date": "2001"
+            })),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            by_citation.file_name(
+                &case.resource_details.id,
+                &case.resource_details.full_title,
+                case.citation()
+            ),
+            "Smith v. Jones, No. 99-123 (9th Cir. 2001)"
+        );
+        // everything else is named by its title
+        assert_eq!(
+            by_citation.file_name("lee2019", "Dear Ann: a letter", ""),
+            "Dear Ann a letter"
+        );
+    }
+
+    fn software(mut fields: serde_json::Value) -> Software {
+        fields["citationKey"] = json!("numpy");
+        fields["itemType"] = json!("computerProgram");
+        fields["title"] = json!("NumPy");
+        Software::new(&item(fields), None).expect("test software should be read")
+    }
+
+    #[test]
+    fn cites_software_and_data_in_apa_style() {
+        let harris = json!({"firstName": "Charles Richard", "lastName": "Harris", "creatorType": "programmer"});
+        let millman =
+            json!({"firstName": "K. Jarrod", "lastName": "Millman", "creatorType": "programmer"});
+        let numfocus = json!({"name": "NumFOCUS", "creatorType": "programmer"});
+
+        let one_author = software(json!({
+            "creators": [harris],
+            "date": "2020-09-16",
+            "versionNumber": "1.19",
+            "company": "NumFOCUS",
+            "url": "https://github.com/numpy/numpy",
+            "DOI": "10.5281/zenodo.4147899"
+        }));
+        assert_eq!(
+            one_author.cite_as,
+            "Harris, C. R. (2020). NumPy (Version 1.19) [Computer software]. NumFOCUS. https://doi.org/10.5281/zenodo.4147899"
+        );
+
+        let two_authors = software(json!({
+            "creators": [harris, millman],
+            "url": "https://github.com/numpy/numpy"
+        }));
+        assert_eq!(
+            two_authors.cite_as,
+            "Harris, C. R., & Millman, K. J. (n.d.). NumPy [Computer software]. https://github.com/numpy/numpy"
+        );
+
+        let three_authors = software(json!({
+            "creators": [harris, millman, numfocus],
+            "date": "2020"
+        }));
+        assert_eq!(
+            three_authors.cite_as,
+            "Harris, C. R., Millman, K. J., & NumFOCUS (2020). NumPy [Computer software]."
+        );
+
+        let dataset = Dataset::new(
+            &item(json!({
+                "citationKey": "era5",
+                "itemType": "dataset",
+                "title": "ERA5 hourly data",
+                "creators": [{"lastName": "Hersbach", "creatorType": "author"}],
+                "date": "2023",
+                "repository": "Copernicus Climate Change Service",
+                "url": "https://cds.climate.copernicus.eu/",
+                "DOI": "10.24381/cds.adbb2d47"
+            })),
+            None,
+        )
+        .unwrap();
+        assert_eq!(dataset.link, "https://doi.org/10.24381/cds.adbb2d47");
+        assert_eq!(
+            dataset.cite_as,
+            "Hersbach (2023). ERA5 hourly data [Data set]. Copernicus Climate Change Service. https://doi.org/10.24381/cds.adbb2d47"
+        );
+    }
+
+    #[test]
+    fn keeps_software_without_a_url() {
+        let unpublished = software(json!({"versionNumber": "0.1"}));
+        assert_eq!(unpublished.repository, "");
+        assert_eq!(
+            unpublished.cite_as,
+            "NumPy (Version 0.1) [Computer software]. (n.d.)."
+        );
+    }
+
+    #[test]
+    fn gives_every_note_in_a_clash_its_citekey() {
+        let by_title = config(r#"filename = "{title}""#);
+        let letter = |citekey: &str, title: &str| {
+            item(json!({"citationKey": citekey, "itemType": "letter", "title": title}))
+        };
+        let names = |items: Vec<Item>| {
+            let mut references = References::new();
+            references.populate(&items, &by_title);
+            note_names(&references.notes(), &by_title)
+                .into_iter()
+                .map(|(folder, name)| format!("{}/{}", folder, name))
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            names(vec![
+                letter("lee2019", "Dear Ann"),
+                letter("lee2020", "Dear ann"),
+                letter("kim2021", "Hello"),
+            ]),
+            [
+                "Misc/Dear Ann (lee2019)",
+                "Misc/Dear ann (lee2020)",
+                "Misc/Hello"
+            ]
+        );
+        // the same names whichever item comes first
+        assert_eq!(
+            names(vec![
+                letter("lee2020", "Dear ann"),
+                letter("lee2019", "Dear Ann"),
+            ]),
+            ["Misc/Dear ann (lee2020)", "Misc/Dear Ann (lee2019)"]
+        );
+        assert_eq!(names(vec![letter("q2020", "???")]), ["Misc/q2020"]);
+        assert_eq!(
+            names(vec![
+                letter("a/b:1", "Dear Ann"),
+                letter("a/b:2", "Dear Ann")
+            ]),
+            ["Misc/Dear Ann (ab1)", "Misc/Dear Ann (ab2)"]
+        );
+    }
+
+    #[test]
+    fn writes_books_with_nothing_but_a_title() {
+        let every_book = config(
+            r#"
+            [[rules]]
+            item_type = "book"
+            resource = "book"
+            "#,
+        );
+        let mut references = References::new();
+        references.populate(
+            &vec![item(
+                json!({"citationKey": "anon", "itemType": "book", "title": "Beowulf"}),
+            )],
+            &every_book,
+        );
+        assert!(references.failed.is_empty());
+        let book = &references.books.book_list[0];
+        assert_eq!(
+            (
+                &book.isbn_13[..],
+                &book.short_title[..],
+                &book.publish_date[..]
+            ),
+            ("", "", "")
+        );
+    }
 }
@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+
+use super::json_parser::{Attachment, Creator, Item, Note, Tag, ZoteroData};
+
+// RIS reference type, Zotero item type. Anything else becomes a `document`.
+const ITEM_TYPES: &[(&str, &str)] = &[
+    ("JOUR", "journalArticle"),
+    ("JFULL", "journalArticle"),
+    ("EJOUR", "journalArticle"),
+    ("ABST", "journalArticle"),
+    ("INPR", "journalArticle"),
+    ("BOOK", "book"),
+    ("EBOOK", "book"),
+    ("EDBOOK", "book"),
+    ("CHAP", "bookSection"),
+    ("ECHAP", "bookSection"),
+    ("CONF", "conferencePaper"),
+    ("CPAPER", "conferencePaper"),
+    ("THES", "thesis"),
+    ("RPRT", "report"),
+    ("ELEC", "webpage"),
+    ("WEB", "webpage"),
+    ("BLOG", "blogPost"),
+    ("NEWS", "newspaperArticle"),
+    ("MGZN", "magazineArticle"),
+    ("VIDEO", "videoRecording"),
+    ("MPCT", "videoRecording"),
+    ("SOUND", "audioRecording"),
+    ("COMP", "computerProgram"),
+    ("DATA", "dataset"),
+    ("CASE", "case"),
+    ("STAT", "statute"),
+    ("BILL", "bill"),
+    ("PAT", "patent"),
+];
+
+// RIS is a file of `XX  - value` lines with records running from `TY` to `ER`
+pub fn is_ris(contents: &str) -> bool {
+    contents
+        .lines()
+        .any(|line| line.trim_start_matches('\u{feff}').starts_with("TY  -"))
+}
+
+// Reads every record in a RIS file. Tags may repeat (one `AU` or `KW` line per author or
+// keyword), and lines without a tag continue the value of the line before, the way
+// PubMed wraps long abstracts.
+pub fn read(contents: &str) -> ZoteroData {
+    let mut items = Vec::new();
+    let mut record: Option<Vec<(String, String)>> = None;
+
+    for line in contents.lines() {
+        let line = line.trim_start_matches('\u{feff}').trim_end();
+        match tag_line(line) {
+            Some(("TY", value)) => {
+                if let Some(fields) = record.take() {
+                    items.push(item(&fields));
+                }
+                record = Some(vec![("TY".to_string(), value.to_string())]);
+            }
+            Some(("ER", _)) => {
+                if let Some(fields) = record.take() {
+                    items.push(item(&fields));
+                }
+            }
+            Some((tag, value)) => {
+                if let Some(fields) = record.as_mut() {
+                    fields.push((tag.to_string(), value.to_string()));
+                }
+            }
+            None if !line.trim().is_empty() => {
+                if let Some((_, value)) = record.as_mut().and_then(|fields| fields.last_mut()) {
+                    value.push(' ');
+                    value.push_str(line.trim());
+                }
+            }
+            None => {}
+        }
+    }
+    // the last record of a file that was cut short
+    if let Some(fields) = record {
+        items.push(item(&fields));
+    }
+
+    ZoteroData {
+        collections: HashMap::new(),
+        config: None,
+        items,
+    }
+}
+
+// `AU  - Jackson, Elizabeth`, some exporters leave out the space after the dash
+fn tag_line(line: &str) -> Option<(&str, &str)> {
+    let tag = line.get(..2)?;
+    let rest = line.get(2..)?;
+    if !tag
+        .chars()
+        .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        || !tag.starts_with(|c: char| c.is_ascii_uppercase())
+    {
+        return None;
+    }
+    let value = rest.trim_start_matches(' ').strip_prefix('-')?;
+    Some((tag, value.trim()))
+}
+
+fn item(fields: &[(String, String)]) -> Item {
+    let values = |tags: &[&str]| -> Vec<String> {
+        fields
+            .iter()
+            .filter(|(tag, value)| tags.contains(&tag.as_str()) && !value.is_empty())
+            .map(|(_, value)| value.clone())
+            .collect()
+    };
+    let first = |tags: &[&str]| -> Option<String> {
+        tags.iter()
+            .find_map(|tag| values(&[tag]).into_iter().next())
+    };
+
+    let reference_type = first(&["TY"]).unwrap_or_default();
+    let item_type = ITEM_TYPES
+        .iter()
+        .find(|(ris_name, _)| *ris_name == reference_type)
+        .map_or("document", |(_, zotero_name)| zotero_name)
+        .to_string();
+
+    let mut creators: Vec<Creator> = values(&["AU", "A1"])
+        .iter()
+        .map(|name| creator(name, "author"))
+        .collect();
+    creators.extend(
+        values(&["A2", "ED"])
+            .iter()
+            .map(|name| creator(name, "editor")),
+    );
+
+    // `SN` holds the ISBN of books and the ISSN of journals
+    let isbn = if item_type == "book" || item_type == "bookSection" {
+        first(&["SN"])
+    } else {
+        None
+    };
+
+    // `T2` is the journal, the book of a chapter or the proceedings of a paper, and `PB`
+    // the university of a thesis or the institution of a report
+    let secondary_title = first(&["T2", "JO", "JF", "JA", "J2"]);
+    let publisher = first(&["PB"]);
+    let pages = match (first(&["SP"]), first(&["EP"])) {
+        (Some(start), Some(end)) => Some(format!("{}-{}", start, end)),
+        (start, _) => start,
+    };
+
+    Item {
+        doi: first(&["DO"]).map(|doi| {
+            doi.trim_start_matches("https://doi.org/")
+                .trim_start_matches("http://dx.doi.org/")
+                .to_string()
+        }),
+        title: first(&["TI", "T1", "CT", "BT"]),
+        library_catalog: first(&["DB", "DP"]),
+        url: first(&["UR"]),
+        creators,
+        isbn,
+        short_title: first(&["ST"]),
+        published_date: first(&["DA", "PY", "Y1"]).and_then(|date| date_from(&date)),
+        journal: secondary_title
+            .clone()
+            .filter(|_| item_type != "bookSection" && item_type != "conferencePaper"),
+        book_title: secondary_title
+            .clone()
+            .filter(|_| item_type == "bookSection"),
+        proceedings_title: secondary_title.filter(|_| item_type == "conferencePaper"),
+        publisher: publisher
+            .clone()
+            .filter(|_| item_type != "thesis" && item_type != "report"),
+        place: first(&["CY"]),
+        section: first(&["SE"]).filter(|_| item_type == "newspaperArticle"),
+        edition: first(&["ET"]),
+        pages,
+        university: publisher.clone().filter(|_| item_type == "thesis"),
+        thesis_type: first(&["M3"]).filter(|_| item_type == "thesis"),
+        institution: publisher.filter(|_| item_type == "report"),
+        report_type: first(&["M3"]).filter(|_| item_type == "report"),
+        item_type,
+        tags: values(&["KW"])
+            .iter()
+            // some exporters put all keywords on one line
+            .flat_map(|keywords| keywords.split(';'))
+            .map(str::trim)
+            .filter(|keyword| !keyword.is_empty())
+            .map(|keyword| Tag {
+                tag: keyword.to_string(),
+            })
+            .collect(),
+        notes: values(&["N1"])
+            .into_iter()
+            .map(|note| Note {
+                date_added: String::new(),
+                date_modified: String::new(),
+                content: note,
+                uri: String::new(),
+            })
+            .collect(),
+        attachments: values(&["L1"])
+            .into_iter()
+            .map(|path| Attachment {
+                title: None,
+                path: Some(path.trim_start_matches("file://").to_string()),
+                url: None,
+            })
+            .collect(),
+        ..Item::default()
+    }
+}
+
+// `Jackson, Elizabeth`, `Jackson, E.M.` or `Jackson, Elizabeth, Jr.`; a name without a
+// comma is taken as it is, e.g. an organisation
+fn creator(name: &str, creator_type: &str) -> Creator {
+    let parts: Vec<&str> = name.split(',').map(str::trim).collect();
+    let (first_name, last_name, name) = match parts.as_slice() {
+        [last, first] => (Some(first.to_string()), Some(last.to_string()), None),
+        [last, first, suffix, ..] => (
+            Some(first.to_string()),
+            Some(format!("{}, {}", last, suffix)),
+            None,
+        ),
+        _ => (None, None, Some(name.to_string())),
+    };
+    Creator {
+        creator_type: Some(creator_type.to_string()),
+        first_name: first_name.filter(|first| !first.is_empty()),
+        last_name,
+        name,
+    }
+}
+
+// `2019`, `2019/03/05/` or `2019/03//Spring`
+fn date_from(date: &str) -> Option<String> {
+    let parts: Vec<u32> = date
+        .split('/')
+        .map_while(|part| part.trim().parse().ok())
+        .collect();
+    match parts.as_slice() {
+        [year, month, day, ..] => Some(format!("{:04}-{:02}-{:02}", year, month, day)),
+        [year, month] => Some(format!("{:04}-{:02}", year, month)),
+        [year] => Some(format!("{:04}", year)),
+        [] => Some(date.trim().to_string()).filter(|date| !date.is_empty()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIBRARY: &str = "\u{feff}TY  - JOUR
+AU  - Jackson, Elizabeth
+AU  - Smith, J., Jr.
+AU  - World Health Organization
+TI  - Quantum Field Theory for
+  Beginners
+T2  - Physics Letters
+KW  - physics
+KW  - teaching; fields
+PY  - 2018
+DA  - 2019/03/05/
+SP  - 10
+EP  - 20
+DO  - https://doi.org/10.1000/xyz
+ER  - 
+
+TY  - BOOK
+AU  - Jackson, Elizabeth
+TI  - Quantum Field Theory for Beginners
+SN  - 978-3-16-148410-0
+PB  - Springer
+PY  - 2019///
+ER  -
+
+TY  - THES
+A1  -Lee, Ann
+TI  - Fields
+PB  - MIT
+PY  - 2020/06//Spring
+ER  -
+TY  - ZZZZ
+TI  - Cut short
+DA  - Spring 2021
+";
+
+    #[test]
+    fn reads_every_record() {
+        let items = read(LIBRARY).items;
+        let types: Vec<&str> = items.iter().map(|item| item.item_type.as_str()).collect();
+        assert_eq!(types, ["journalArticle", "book", "thesis", "document"]);
+
+        let paper = &items[0];
+        assert_eq!(
+            paper.title.as_deref(),
+            Some("Quantum Field Theory for Beginners")
+        );
+        assert_eq!(paper.journal.as_deref(), Some("Physics Letters"));
+        assert_eq!(paper.pages.as_deref(), Some("10-20"));
+        assert_eq!(paper.doi.as_deref(), Some("10.1000/xyz"));
+        let tags: Vec<&str> = paper.tags.iter().map(|tag| tag.tag.as_str()).collect();
+        assert_eq!(tags, ["physics", "teaching", "fields"]);
+        let authors: Vec<(Option<&str>, Option<&str>, Option<&str>)> = paper
+            .creators
+            .iter()
+            .map(|creator| {
+                (
+                    creator.last_name.as_deref(),
+                    creator.first_name.as_deref(),
+                    creator.name.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            authors,
+            [
+                (Some("Jackson"), Some("Elizabeth"), None),
+                (Some("Smith, Jr."), Some("J."), None),
+                (None, None, Some("World Health Organization"))
+            ]
+        );
+
+        assert_eq!(items[1].isbn.as_deref(), Some("978-3-16-148410-0"));
+        assert_eq!(items[1].publisher.as_deref(), Some("Springer"));
+        assert_eq!(items[2].university.as_deref(), Some("MIT"));
+        assert_eq!(items[2].publisher, None);
+        assert_eq!(items[2].creators[0].last_name.as_deref(), Some("Lee"));
+    }
+
+    #[test]
+    fn prefers_the_full_date_over_the_year() {
+        let dates: Vec<Option<String>> = read(LIBRARY)
+            .items
+            .into_iter()
+            .map(|item| item.published_date)
+            .collect();
+        assert_eq!(
+            dates,
+            [
+                Some("2019-03-05".to_string()),
+                Some("2019".to_string()),
+                Some("2020-06".to_string()),
+                Some("Spring 2021".to_string())
+            ]
+        );
+        assert_eq!(date_from("2019/3/5"), Some("2019-03-05".to_string()));
+        assert_eq!(date_from(" "), None);
+    }
+
+    #[test]
+    fn generates_citekeys_for_records_without_one() {
+        let mut zotero_data = read(LIBRARY);
+        zotero_data.apply_preferences();
+        let citekeys: Vec<&str> = zotero_data
+            .items
+            .iter()
+            .map(|item| item.id.as_str())
+            .collect();
+        assert_eq!(
+            citekeys,
+            [
+                "jacksonQuantumFieldTheory2019",
+                "jacksonQuantumFieldTheory2019a",
+                "leeFields2020",
+                "CutShort2021"
+            ]
+        );
+    }
+}
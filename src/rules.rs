@@ -0,0 +1,407 @@
+use regex::Regex;
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use super::json_parser::Item;
+
+// Which resource type an item becomes. The vault config's rules are tried first, in the
+// order they are written, then these; an item no rule matches gets a generic note.
+const BUILT_IN_RULES: &str = r#"
+[[rules]]
+name = "articles"
+item_type = ["blogPost", "webpage"]
+resource = "article"
+
+[[rules]]
+name = "preprints"
+item_type = "preprint"
+resource = "preprint"
+
+[[rules]]
+name = "preprints saved as journal articles"
+item_type = "journalArticle"
+matches = { url = "(arxiv|biorxiv|medrxiv)\\.org" }
+resource = "preprint"
+
+[[rules]]
+name = "academic papers"
+item_type = "journalArticle"
+resource = "academic_paper"
+
+[[rules]]
+name = "press articles"
+item_type = ["newspaperArticle", "magazineArticle"]
+resource = "press_article"
+
+[[rules]]
+name = "YouTube videos"
+item_type = "videoRecording"
+matches = { libraryCatalog = "^YouTube$" }
+resource = "youtube_video"
+
+[[rules]]
+name = "TED talks"
+item_type = "videoRecording"
+matches = { libraryCatalog = "^www\\.ted\\.com$" }
+resource = "ted_talk"
+
+[[rules]]
+name = "podcasts"
+item_type = ["podcast", "audioRecording"]
+resource = "podcast"
+
+[[rules]]
+name = "books"
+item_type = "book"
+has = ["ISBN"]
+resource = "book"
+
+[[rules]]
+name = "book sections"
+item_type = "bookSection"
+resource = "book_section"
+
+[[rules]]
+name = "theses"
+item_type = "thesis"
+resource = "thesis"
+
+[[rules]]
+name = "conference papers"
+item_type = "conferencePaper"
+resource = "conference_paper"
+
+[[rules]]
+name = "reports"
+item_type = "report"
+resource = "report"
+
+[[rules]]
+name = "court cases"
+item_type = "case"
+resource = "legal_case"
+
+[[rules]]
+name = "statutes"
+item_type = "statute"
+resource = "statute"
+
+[[rules]]
+name = "bills"
+item_type = "bill"
+resource = "bill"
+
+[[rules]]
+name = "software"
+item_type = "computerProgram"
+resource = "software"
+
+[[rules]]
+name = "datasets"
+item_type = "dataset"
+resource = "dataset"
+"#;
+
+// e.g.
+//
+//   [[rules]]
+//   name = "Vimeo"
+//   item_type = "videoRecording"
+//   matches = { url = "vimeo\\.com" }
+//   resource = "article"
+//   folder = "Vimeo Videos"
+//   template = "Vimeo Video.md"
+//
+// Every condition that is given has to hold. Fields are named the way Zotero names them.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub name: Option<String>,
+    // any of these item types
+    #[serde(default, deserialize_with = "one_or_more")]
+    pub item_type: Vec<String>,
+    // field name, pattern that has to be found in the field
+    #[serde(default, deserialize_with = "patterns")]
+    pub matches: Vec<(String, Regex)>,
+    // fields that have to be filled in
+    #[serde(default)]
+    pub has: Vec<String>,
+    // resource type the item becomes, e.g. `academic_paper`
+    pub resource: String,
+    // instead of the resource type's folder and template
+    pub folder: Option<String>,
+    pub template: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Rules {
+    rules: Vec<Rule>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMore {
+    One(String),
+    More(Vec<String>),
+}
+
+fn one_or_more<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    Ok(match OneOrMore::deserialize(deserializer)? {
+        OneOrMore::One(one) => vec![one],
+        OneOrMore::More(more) => more,
+    })
+}
+
+fn patterns<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<(String, Regex)>, D::Error> {
+    BTreeMap::<String, String>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(field, pattern)| {
+            Regex::new(&pattern)
+                .map(|regex| (field.clone(), regex))
+                .map_err(|error| {
+                    de::Error::custom(format!("bad pattern for `{}`: {}", field, error))
+                })
+        })
+        .collect()
+}
+
+static BUILT_IN: OnceLock<Vec<Rule>> = OnceLock::new();
+
+pub fn built_in() -> &'static [Rule] {
+    BUILT_IN.get_or_init(|| {
+        toml::from_str::<Rules>(BUILT_IN_RULES)
+            .expect("built-in rules should parse")
+            .rules
+    })
+}
+
+// the fields a rule can look at, under their Zotero names
+pub fn fields(item: &Item) -> Map<String, Value> {
+    match serde_json::to_value(item) {
+        Ok(Value::Object(fields)) => fields,
+        _ => Map::new(),
+    }
+}
+
+impl Rule {
+    // the first condition the item fails, or `None` if the rule matches
+    pub fn mismatch(&self, item: &Item, fields: &Map<String, Value>) -> Option<String> {
+        if !self.item_type.is_empty() && !self.item_type.contains(&item.item_type) {
+            return Some(format!(
+                "item type is {}, not {}",
+                item.item_type,
+                self.item_type.join(" or ")
+            ));
+        }
+        let text = |field: &str| fields.get(field).and_then(Value::as_str).unwrap_or("");
+        if let Some(field) = self.has.iter().find(|field| text(field).is_empty()) {
+            return Some(format!("{} is empty", field));
+        }
+        self.matches
+            .iter()
+            .find(|(field, pattern)| !pattern.is_match(text(field)))
+            .map(|(field, pattern)| format!("{} does not match `{}`", field, pattern))
+    }
+
+    // field names that no item can have, most likely typos
+    pub fn unknown_fields(&self) -> Vec<&String> {
+        let known = fields(&Item::default());
+        self.has
+            .iter()
+            .chain(self.matches.iter().map(|(field, _)| field))
+            .filter(|field| !known.contains_key(field.as_str()))
+            .collect()
+    }
+}
+
+// the vault's rules numbered from 1, then the built-in ones
+fn labelled(rules: &[Rule]) -> impl Iterator<Item = (String, &Rule)> {
+    let own = rules.iter().enumerate().map(|(index, rule)| {
+        let label = format!("rule {}", index + 1);
+        (label, rule)
+    });
+    let built_in = built_in()
+        .iter()
+        .map(|rule| ("built-in rule".to_string(), rule));
+    own.chain(built_in).map(|(label, rule)| match &rule.name {
+        Some(name) => (format!("{} ({})", label, name), rule),
+        None => (label, rule),
+    })
+}
+
+// the first rule that matches
+pub fn classify<'a>(rules: &'a [Rule], item: &Item) -> Option<&'a Rule> {
+    let fields = fields(item);
+    rules
+        .iter()
+        .chain(built_in())
+        .find(|rule| rule.mismatch(item, &fields).is_none())
+}
+
+// every rule that was tried with the reason it did not match, up to the one that did
+pub fn explain<'a>(rules: &'a [Rule], item: &Item) -> (Vec<String>, Option<&'a Rule>) {
+    let fields = fields(item);
+    let mut lines = Vec::new();
+    for (label, rule) in labelled(rules) {
+        match rule.mismatch(item, &fields) {
+            Some(reason) => lines.push(format!("{}: no, {}", label, reason)),
+            None => {
+                lines.push(format!("{}: matches", label));
+                return (lines, Some(rule));
+            }
+        }
+    }
+    (lines, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vault_rules(toml: &str) -> Vec<Rule> {
+        toml::from_str::<Rules>(toml)
+            .expect("rules should parse")
+            .rules
+    }
+
+    fn item(item_type: &str, url: &str, library_catalog: &str, isbn: &str) -> Item {
+        let text = |value: &str| Some(value.to_string()).filter(|value| !value.is_empty());
+        Item {
+            id: "smith2020".to_string(),
+            item_type: item_type.to_string(),
+            url: text(url),
+            library_catalog: text(library_catalog),
+            isbn: text(isbn),
+            ..Item::default()
+        }
+    }
+
+    fn resource(rules: &[Rule], item: &Item) -> Option<String> {
+        classify(rules, item).map(|rule| rule.resource.clone())
+    }
+
+    const VIMEO: &str = r#"
+        [[rules]]
+        name = "Vimeo"
+        item_type = "videoRecording"
+        matches = { url = "vimeo\\.com" }
+        resource = "article"
+        folder = "Vimeo Videos"
+
+        [[rules]]
+        item_type = ["videoRecording", "podcast"]
+        matches = { libraryCatalog = "^YouTube$" }
+        resource = "podcast"
+    "#;
+
+    #[test]
+    fn vault_rules_win_over_built_in_ones() {
+        let rules = vault_rules(VIMEO);
+        let vimeo = item("videoRecording", "https://vimeo.com/1", "", "");
+        let rule = classify(&rules, &vimeo).unwrap();
+        assert_eq!(rule.resource, "article");
+        assert_eq!(rule.folder.as_deref(), Some("Vimeo Videos"));
+
+        let youtube = item(
+            "videoRecording",
+            "https://www.youtube.com/watch?v=1",
+            "YouTube",
+            "",
+        );
+        assert_eq!(resource(&rules, &youtube).as_deref(), Some("podcast"));
+        assert_eq!(resource(&[], &youtube).as_deref(), Some("youtube_video"));
+    }
+
+    #[test]
+    fn built_in_rules_apply_in_order() {
+        let cases = [
+            (
+                item("journalArticle", "https://arxiv.org/abs/2101.00001", "", ""),
+                Some("preprint"),
+            ),
+            (
+                item("journalArticle", "https://doi.org/10.1/x", "", ""),
+                Some("academic_paper"),
+            ),
+            (
+                item("videoRecording", "", "YouTube", ""),
+                Some("youtube_video"),
+            ),
+            (
+                item("videoRecording", "", "www.ted.com", ""),
+                Some("ted_talk"),
+            ),
+            (item("book", "", "", "978-3-16-148410-0"), Some("book")),
+            // nothing matches these, so they get a generic note
+            (item("book", "", "", ""), None),
+            (item("videoRecording", "", "Vimeo", ""), None),
+            (item("letter", "", "", ""), None),
+        ];
+        for (item, expected) in cases {
+            assert_eq!(
+                resource(&[], &item).as_deref(),
+                expected,
+                "{} {:?}",
+                item.item_type,
+                item.url
+            );
+        }
+    }
+
+    #[test]
+    fn explains_every_rule_up_to_the_one_that_matches() {
+        let rules = vault_rules(VIMEO);
+        let (lines, rule) = explain(&rules, &item("book", "", "", ""));
+        assert!(rule.is_none());
+        assert_eq!(lines.len(), 2 + built_in().len());
+        assert_eq!(
+            lines[..3],
+            [
+                "rule 1 (Vimeo): no, item type is book, not videoRecording",
+                "rule 2: no, item type is book, not videoRecording or podcast",
+                "built-in rule (articles): no, item type is book, not blogPost or webpage"
+            ]
+        );
+        assert!(lines.contains(&"built-in rule (books): no, ISBN is empty".to_string()));
+
+        let (lines, rule) = explain(&rules, &item("journalArticle", "https://doi.org/1", "", ""));
+        assert_eq!(
+            rule.map(|rule| rule.resource.as_str()),
+            Some("academic_paper")
+        );
+        assert_eq!(
+            lines[lines.len() - 2..],
+            [
+                "built-in rule (preprints saved as journal articles): no, url does not match `(arxiv|biorxiv|medrxiv)\\.org`",
+                "built-in rule (academic papers): matches"
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_fields_no_item_has() {
+        let rules = vault_rules(
+            r#"
+            [[rules]]
+            has = ["ISBN", "ISBM"]
+            matches = { jurnal = "x" }
+            resource = "book"
+            "#,
+        );
+        assert_eq!(rules[0].unknown_fields(), ["ISBM", "jurnal"]);
+
+        let error = toml::from_str::<Rules>(
+            r#"
+            [[rules]]
+            matches = { url = "(" }
+            resource = "book"
+            "#,
+        )
+        .err()
+        .unwrap();
+        assert!(error.to_string().contains("bad pattern for `url`"));
+    }
+}
@@ -0,0 +1,406 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::error::{Error, Result};
+use super::regions;
+
+pub const SYNC_STATE_PATH: &str = ".library/sync-state.json";
+
+// remembers a fingerprint of what we last wrote to each note so that we can tell
+// our own output apart from text somebody has added to the note since
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SyncState {
+    pub notes: HashMap<String, u64>,
+}
+
+impl SyncState {
+    pub fn load(path: &Path) -> SyncState {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_default(),
+            Err(_) => SyncState::default(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self).expect("sync state is always valid JSON");
+        write_file(path, &contents)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub unchanged: usize,
+    pub retired: Vec<String>,
+    pub kept_edited: Vec<String>,
+    // notes whose item was removed or renamed, kept because they were edited
+    pub left_behind: Vec<String>,
+}
+
+impl SyncReport {
+    pub fn print(&self) {
+        self.created
+            .iter()
+            .for_each(|note| println!("created  {}", note));
+        self.updated
+            .iter()
+            .for_each(|note| println!("updated  {}", note));
+        self.retired
+            .iter()
+            .for_each(|note| println!("retired  {}", note));
+        self.kept_edited
+            .iter()
+            .for_each(|note| println!("kept     {} (edited since last sync)", note));
+        self.left_behind.iter().for_each(|note| {
+            println!(
+                "left     {} (edited, but its item was removed or renamed)",
+                note
+            )
+        });
+        println!(
+            "{} created, {} updated, {} retired, {} unchanged, {} kept because they were edited",
+            self.created.len(),
+            self.updated.len(),
+            self.retired.len(),
+            self.unchanged,
+            self.kept_edited.len()
+        );
+        if !self.left_behind.is_empty() {
+            println!(
+                "{} edited notes no longer belong to any item, move what you want to keep and delete them",
+                self.left_behind.len()
+            );
+        }
+        if !self.kept_edited.is_empty() {
+            println!("`sync --force` overwrites the kept notes with what the library has now");
+        }
+    }
+}
+
+pub struct NoteSync {
+    root: PathBuf,
+    state_path: PathBuf,
+    previous: SyncState,
+    current: SyncState,
+    dry_run: bool,
+    // overwrite notes without regions even when they were edited
+    force: bool,
+    pub report: SyncReport,
+}
+
+impl NoteSync {
+    pub fn new(root: &Path, state_path: &Path) -> NoteSync {
+        NoteSync {
+            root: root.to_path_buf(),
+            state_path: state_path.to_path_buf(),
+            previous: SyncState::load(state_path),
+            current: SyncState::default(),
+            dry_run: false,
+            force: false,
+            report: SyncReport::default(),
+        }
+    }
+
+    // works out the same report as a real sync without touching the vault
+    pub fn dry_run(root: &Path, state_path: &Path) -> NoteSync {
+        NoteSync {
+            dry_run: true,
+            ..NoteSync::new(root, state_path)
+        }
+    }
+
+    // Rewrites notes that were edited by hand, the way to get a note back that was
+    // edited before there was any sync state and so can never be recognised as ours.
+    // Notes with regions keep the text outside them either way.
+    pub fn forced(root: &Path, state_path: &Path) -> NoteSync {
+        NoteSync {
+            force: true,
+            ..NoteSync::new(root, state_path)
+        }
+    }
+
+    fn write(&self, path: &Path, content: &str) -> Result<()> {
+        if self.dry_run {
+            return Ok(());
+        }
+        write_file(path, content)
+    }
+
+    // writes a rendered note to `<root>/<folder>/<name>.md`. Notes that already exist only
+    // get their managed regions rewritten; notes without regions are left alone if they
+    // have been changed by hand since we last wrote them
+    pub fn note(&mut self, folder: &str, name: &str, content: &str) -> Result<()> {
+        let key = format!("{}/{}.md", folder, name);
+        let path = self.root.join(&key);
+        let rendered = fingerprint(content);
+
+        if !path.exists() {
+            self.write(&path, content)?;
+            self.current.notes.insert(key.clone(), rendered);
+            self.report.created.push(key);
+            return Ok(());
+        }
+
+        let on_disk = fs::read_to_string(&path).map_err(|error| Error::io(&path, error))?;
+        if regions::has_regions(&on_disk) {
+            let merged = regions::merge(&on_disk, content);
+            if merged == on_disk {
+                self.report.unchanged += 1;
+            } else {
+                self.write(&path, &merged)?;
+                self.report.updated.push(key.clone());
+            }
+            self.current.notes.insert(key, fingerprint(&merged));
+            return Ok(());
+        }
+
+        if on_disk == content {
+            self.current.notes.insert(key, rendered);
+            self.report.unchanged += 1;
+            return Ok(());
+        }
+
+        let last_written = self.previous.notes.get(&key).copied();
+        if self.force || last_written == Some(fingerprint(&on_disk)) {
+            self.write(&path, content)?;
+            self.current.notes.insert(key.clone(), rendered);
+            self.report.updated.push(key);
+        } else {
+            // keep the old fingerprint so the note is still recognised as edited next time
+            if let Some(last_written) = last_written {
+                self.current.notes.insert(key.clone(), last_written);
+            }
+            self.report.kept_edited.push(key);
+        }
+        Ok(())
+    }
+
+    // carries a note over from the last run without rendering it again
+    pub fn keep(&mut self, folder: &str, name: &str) {
+        let key = format!("{}/{}.md", folder, name);
+        if let Some(&last_written) = self.previous.notes.get(&key) {
+            self.current.notes.insert(key, last_written);
+        }
+        self.report.unchanged += 1;
+    }
+
+    // retires notes we wrote last time that no longer have an item behind them, then
+    // records what was written during this run
+    pub fn finish(mut self) -> Result<SyncReport> {
+        let mut stale: Vec<(&String, &u64)> = self
+            .previous
+            .notes
+            .iter()
+            .filter(|(key, _)| !self.current.notes.contains_key(*key))
+            .collect();
+        stale.sort();
+
+        for (key, &last_written) in stale {
+            let path = self.root.join(key);
+            match fs::read_to_string(&path) {
+                Ok(on_disk) if fingerprint(&on_disk) == last_written => {
+                    if !self.dry_run {
+                        fs::remove_file(&path).map_err(|error| Error::io(&path, error))?;
+                    }
+                    self.report.retired.push(key.clone());
+                }
+                Ok(_) => self.report.left_behind.push(key.clone()),
+                Err(_) => {}
+            }
+        }
+
+        if !self.dry_run {
+            self.current.save(&self.state_path)?;
+        }
+        Ok(self.report)
+    }
+}
+
+fn write_file(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|error| Error::io(parent, error))?;
+    }
+    fs::write(path, content).map_err(|error| Error::io(path, error))
+}
+
+// 64 bit FNV-1a, stable across builds unlike std's DefaultHasher
+fn fingerprint(content: &str) -> u64 {
+    content.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    // an empty vault folder of its own for every test
+    fn vault(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!(
+            "zotero-to-obsidian-sync-{}-{}",
+            name,
+            process::id()
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    fn run(vault: &Path, notes: &[(&str, &str)]) -> SyncReport {
+        let mut sync = NoteSync::new(&vault.join("Resources"), &vault.join(SYNC_STATE_PATH));
+        notes
+            .iter()
+            .for_each(|(name, content)| sync.note("Papers", name, content).unwrap());
+        sync.finish().unwrap()
+    }
+
+    fn read(vault: &Path, name: &str) -> String {
+        fs::read_to_string(vault.join("Resources/Papers").join(format!("{}.md", name))).unwrap()
+    }
+
+    #[test]
+    fn writes_new_notes_and_skips_unchanged_ones() {
+        let vault = vault("new");
+        let report = run(&vault, &[("smith2020", "# Smith\n")]);
+        assert_eq!(report.created, ["Papers/smith2020.md"]);
+        assert_eq!(read(&vault, "smith2020"), "# Smith\n");
+        assert!(vault.join(SYNC_STATE_PATH).exists());
+
+        let report = run(&vault, &[("smith2020", "# Smith\n")]);
+        assert!(report.created.is_empty() && report.updated.is_empty());
+        assert_eq!(report.unchanged, 1);
+
+        let report = run(&vault, &[("smith2020", "# Smith (2020)\n")]);
+        assert_eq!(report.updated, ["Papers/smith2020.md"]);
+        assert_eq!(read(&vault, "smith2020"), "# Smith (2020)\n");
+    }
+
+    #[test]
+    fn keeps_notes_edited_since_the_last_sync() {
+        let vault = vault("edited");
+        run(&vault, &[("smith2020", "# Smith\n")]);
+        let path = vault.join("Resources/Papers/smith2020.md");
+        fs::write(&path, "# Smith\n\nMy own thoughts\n").unwrap();
+
+        for _ in 0..2 {
+            let report = run(&vault, &[("smith2020", "# Smith (2020)\n")]);
+            assert_eq!(report.kept_edited, ["Papers/smith2020.md"]);
+            assert!(report.updated.is_empty());
+            assert_eq!(read(&vault, "smith2020"), "# Smith\n\nMy own thoughts\n");
+        }
+    }
+
+    #[test]
+    fn overwrites_edited_notes_when_forced() {
+        // a note from before there was any sync state
+        let vault = vault("forced");
+        let path = vault.join("Resources/Papers/smith2020.md");
+        write_file(&path, "# Smith, edited long ago\n").unwrap();
+        let report = run(&vault, &[("smith2020", "# Smith\n")]);
+        assert_eq!(report.kept_edited, ["Papers/smith2020.md"]);
+
+        let state_path = vault.join(SYNC_STATE_PATH);
+        let mut sync = NoteSync::forced(&vault.join("Resources"), &state_path);
+        sync.note("Papers", "smith2020", "# Smith\n").unwrap();
+        let report = sync.finish().unwrap();
+        assert_eq!(report.updated, ["Papers/smith2020.md"]);
+        assert_eq!(read(&vault, "smith2020"), "# Smith\n");
+
+        // and from then on it is recognised as ours
+        let report = run(&vault, &[("smith2020", "# Smith (2020)\n")]);
+        assert_eq!(report.updated, ["Papers/smith2020.md"]);
+    }
+
+    #[test]
+    fn retires_notes_whose_item_is_gone() {
+        let vault = vault("retired");
+        run(
+            &vault,
+            &[
+                ("smith2020", "# Smith\n"),
+                ("jones2021", "# Jones\n"),
+                ("lee2019", "# Lee\n"),
+            ],
+        );
+        fs::write(
+            vault.join("Resources/Papers/lee2019.md"),
+            "# Lee\n\nkeep me\n",
+        )
+        .unwrap();
+
+        let report = run(&vault, &[("smith2020", "# Smith\n")]);
+        assert_eq!(report.retired, ["Papers/jones2021.md"]);
+        assert_eq!(report.left_behind, ["Papers/lee2019.md"]);
+        assert!(report.kept_edited.is_empty());
+        assert!(!vault.join("Resources/Papers/jones2021.md").exists());
+        assert_eq!(read(&vault, "lee2019"), "# Lee\n\nkeep me\n");
+    }
+
+    #[test]
+    fn reports_an_edited_note_left_behind_by_a_rename() {
+        let vault = vault("renamed");
+        run(&vault, &[("Attention", "# Attention\n")]);
+        fs::write(
+            vault.join("Resources/Papers/Attention.md"),
+            "# Attention\n\nmy notes\n",
+        )
+        .unwrap();
+
+        // the item's title changed, and with it the note's name
+        let report = run(&vault, &[("Attention Is All You Need", "# Attention\n")]);
+        assert_eq!(report.created, ["Papers/Attention Is All You Need.md"]);
+        assert_eq!(report.left_behind, ["Papers/Attention.md"]);
+        assert_eq!(read(&vault, "Attention"), "# Attention\n\nmy notes\n");
+
+        // it is reported once, after that it is the user's note
+        let report = run(&vault, &[("Attention Is All You Need", "# Attention\n")]);
+        assert!(report.left_behind.is_empty());
+    }
+
+    #[test]
+    fn writes_nothing_on_a_dry_run() {
+        let vault = vault("dry-run");
+        run(
+            &vault,
+            &[("smith2020", "# Smith\n"), ("jones2021", "# Jones\n")],
+        );
+        let state_before = fs::read_to_string(vault.join(SYNC_STATE_PATH)).unwrap();
+
+        let mut sync = NoteSync::dry_run(&vault.join("Resources"), &vault.join(SYNC_STATE_PATH));
+        sync.note("Papers", "smith2020", "# Smith (2020)\n")
+            .unwrap();
+        sync.note("Papers", "lee2019", "# Lee\n").unwrap();
+        let report = sync.finish().unwrap();
+
+        assert_eq!(report.created, ["Papers/lee2019.md"]);
+        assert_eq!(report.updated, ["Papers/smith2020.md"]);
+        assert_eq!(report.retired, ["Papers/jones2021.md"]);
+        assert!(!vault.join("Resources/Papers/lee2019.md").exists());
+        assert!(vault.join("Resources/Papers/jones2021.md").exists());
+        assert_eq!(read(&vault, "smith2020"), "# Smith\n");
+        assert_eq!(
+            fs::read_to_string(vault.join(SYNC_STATE_PATH)).unwrap(),
+            state_before
+        );
+    }
+
+    #[test]
+    fn carries_over_notes_that_were_not_rendered() {
+        let vault = vault("keep");
+        run(
+            &vault,
+            &[("smith2020", "# Smith\n"), ("jones2021", "# Jones\n")],
+        );
+
+        let mut sync = NoteSync::new(&vault.join("Resources"), &vault.join(SYNC_STATE_PATH));
+        sync.note("Papers", "smith2020", "# Smith\n").unwrap();
+        sync.keep("Papers", "jones2021");
+        let report = sync.finish().unwrap();
+        assert!(report.retired.is_empty());
+        assert_eq!(report.unchanged, 2);
+    }
+}
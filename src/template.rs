@@ -0,0 +1,1076 @@
+use chrono::format::{Item as DateItem, StrftimeItems};
+use chrono::NaiveDate;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use super::error::{Error, Result};
+
+// The language the note templates are written in, a small part of Handlebars with filters:
+//
+//   {{full_title}}                         a field
+//   {{publish_date | date:"%Y" | lower}}   a field through filters, left to right
+//   {{#if doi}} ... {{else}} ... {{/if}}   `{{#unless doi}}` for the opposite
+//   {{#each authors}} ... {{/each}}        `{{this}}`, `{{@index}}`, `{{@first}}`, `{{@last}}`
+//   {{> zotero-links}}                     the partial `partials/zotero-links.md`
+//   {{! a comment }}
+//   \{{                                    a literal `{{`
+//
+// Values go into the note as they are, nothing in them is read as template syntax. A
+// list printed as a whole (`{{authors}}`, `{{tags}}`) comes out the way the notes have
+// always had it. A block tag that is alone on its line takes the line with it, so blocks
+// can be written one tag per line without leaving blank lines behind.
+
+const PARTIALS: &str = "partials";
+// partials using partials, deeper than this is taken to be a loop
+const MAX_PARTIAL_DEPTH: usize = 8;
+const FILTERS: &[&str] = &[
+    "lower", "upper", "slug", "date", "default", "join", "yaml", "markdown", "link",
+];
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Text(String),
+    // the items, and how the list prints as a whole
+    List(Vec<Value>, String),
+    // the fields, and how the whole thing prints
+    Object(BTreeMap<String, Value>, String),
+}
+
+impl Value {
+    pub fn object(fields: Vec<(&str, String)>, shown: String) -> Value {
+        let fields = fields
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), Value::Text(value)))
+            .collect();
+        Value::Object(fields, shown)
+    }
+
+    fn text(&self) -> &str {
+        match self {
+            Value::Text(text) | Value::List(_, text) | Value::Object(_, text) => text,
+        }
+    }
+
+    fn is_truthy(&self) -> bool {
+        match self {
+            Value::Text(text) => !text.is_empty(),
+            Value::List(items, _) => !items.is_empty(),
+            Value::Object(..) => true,
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(text: &str) -> Value {
+        Value::Text(text.to_string())
+    }
+}
+
+impl From<&String> for Value {
+    fn from(text: &String) -> Value {
+        Value::Text(text.clone())
+    }
+}
+
+impl From<String> for Value {
+    fn from(text: String) -> Value {
+        Value::Text(text)
+    }
+}
+
+// the fields a template is filled in with
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    fields: BTreeMap<String, Value>,
+}
+
+impl Context {
+    pub fn set(&mut self, name: &str, value: impl Into<Value>) -> &mut Context {
+        self.fields.insert(name.to_string(), value.into());
+        self
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.fields.keys()
+    }
+}
+
+#[derive(Debug)]
+struct Expression {
+    path: String,
+    line: usize,
+    // filter name and its argument
+    filters: Vec<(String, Option<String>)>,
+}
+
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    Field(Expression),
+    If {
+        condition: Expression,
+        negated: bool,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    Each {
+        list: Expression,
+        body: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    FrontMatter(Vec<FrontMatterKey>),
+}
+
+// `key: field` or `key: [field, field]` in a `{{#frontmatter}}` block, or a field on its
+// own whose items have a `name` and a `value`, each becoming a key of its own
+#[derive(Debug)]
+struct FrontMatterKey {
+    key: Option<String>,
+    values: Vec<Expression>,
+    list: bool,
+}
+
+#[derive(Debug)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+enum Token {
+    Text(String),
+    // what is between the braces, and the line it starts on
+    Tag(String, usize),
+}
+
+impl Template {
+    // reads a template, and the partials it uses, from the template folder
+    pub fn load(dir: &Path, name: &str) -> Result<Template> {
+        let path = dir.join(name);
+        let source = fs::read_to_string(&path).map_err(|error| Error::io(&path, error))?;
+        Template::parse(&source, &path, dir)
+    }
+
+    pub fn parse(source: &str, path: &Path, dir: &Path) -> Result<Template> {
+        let mut parser = Parser {
+            tokens: tokenize(source, path)?,
+            position: 0,
+            path: path.to_path_buf(),
+            dir: dir.to_path_buf(),
+            depth: 0,
+        };
+        Ok(Template {
+            nodes: parser.nodes(None)?.0,
+        })
+    }
+
+    pub fn render(&self, context: &Context) -> String {
+        let root = Scope {
+            value: Value::Object(context.fields.clone(), String::new()),
+            index: None,
+        };
+        let mut output = String::new();
+        render_nodes(&self.nodes, &mut vec![root], &mut output);
+        output
+    }
+}
+
+// what a template asks for that the context does not have, with the line it is on, and
+// the context's fields the template does use
+#[derive(Debug, Default)]
+pub struct Check {
+    pub unknown: Vec<(usize, String)>,
+    pub used: BTreeSet<String>,
+}
+
+impl Template {
+    // Goes through every branch, with each `#each` looking at the first item of its
+    // list, so the context should have something in every field and every list.
+    pub fn check(&self, context: &Context) -> Check {
+        let root = Scope {
+            value: Value::Object(context.fields.clone(), String::new()),
+            index: None,
+        };
+        let mut check = Check::default();
+        check_nodes(&self.nodes, &mut vec![root], &mut check);
+        check
+    }
+}
+
+impl Check {
+    fn look_up(&mut self, expression: &Expression, scopes: &[Scope]) -> Option<Value> {
+        let value = lookup(&expression.path, scopes);
+        if value.is_none() {
+            self.unknown
+                .push((expression.line, expression.path.clone()));
+        }
+        let name = expression.path.split('.').next().unwrap_or("");
+        if let Some(Value::Object(fields, _)) = scopes.first().map(|root| &root.value) {
+            if fields.contains_key(name) {
+                self.used.insert(name.to_string());
+            }
+        }
+        value
+    }
+}
+
+fn check_nodes(nodes: &[Node], scopes: &mut Vec<Scope>, check: &mut Check) {
+    for node in nodes {
+        match node {
+            Node::Text(_) => {}
+            Node::Field(expression) => {
+                check.look_up(expression, scopes);
+            }
+            Node::If {
+                condition,
+                then,
+                otherwise,
+                ..
+            } => {
+                check.look_up(condition, scopes);
+                check_nodes(then, scopes, check);
+                check_nodes(otherwise, scopes, check);
+            }
+            Node::Each {
+                list,
+                body,
+                otherwise,
+            } => {
+                let first = match check.look_up(list, scopes) {
+                    Some(Value::List(items, _)) => items.into_iter().next(),
+                    _ => None,
+                };
+                scopes.push(Scope {
+                    value: first.unwrap_or_else(|| Value::Text(String::new())),
+                    index: Some((0, 1)),
+                });
+                check_nodes(body, scopes, check);
+                scopes.pop();
+                check_nodes(otherwise, scopes, check);
+            }
+            Node::FrontMatter(keys) => {
+                keys.iter()
+                    .flat_map(|key| &key.values)
+                    .for_each(|expression| {
+                        check.look_up(expression, scopes);
+                    })
+            }
+        }
+    }
+}
+
+fn syntax(path: &Path, line: usize, message: String) -> Error {
+    Error::Syntax {
+        path: path.to_path_buf(),
+        line,
+        message,
+    }
+}
+
+fn tokenize(source: &str, path: &Path) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut rest = source;
+    let mut line = 1;
+    while let Some(start) = rest.find("{{") {
+        let before = &rest[..start];
+        line += before.matches('\n').count();
+        if let Some(before) = before.strip_suffix('\\') {
+            text.push_str(before);
+            text.push_str("{{");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        text.push_str(before);
+        let end = rest[start..]
+            .find("}}")
+            .ok_or_else(|| syntax(path, line, "`{{` is never closed".to_string()))?;
+        let body = &rest[start + 2..start + end];
+        tokens.push(Token::Text(std::mem::take(&mut text)));
+        tokens.push(Token::Tag(body.trim().to_string(), line));
+        line += body.matches('\n').count();
+        rest = &rest[start + end + 2..];
+    }
+    text.push_str(rest);
+    tokens.push(Token::Text(text));
+    drop_standalone_lines(&mut tokens);
+    Ok(tokens)
+}
+
+fn is_block_tag(body: &str) -> bool {
+    body == "else" || body.starts_with(['#', '/', '!', '>'])
+}
+
+// Tokens alternate text, tag, text, ... so a tag's neighbours are always text. A block
+// tag with nothing but whitespace around it on its line loses that whitespace and the
+// line break after it.
+fn drop_standalone_lines(tokens: &mut [Token]) {
+    let text = |token: &Token| match token {
+        Token::Text(text) => text.clone(),
+        Token::Tag(..) => String::new(),
+    };
+    let last = tokens.len() - 1;
+    let standalone: Vec<usize> = (1..last)
+        .step_by(2)
+        .filter(|&index| match &tokens[index] {
+            Token::Tag(body, _) if is_block_tag(body) => {
+                let before = text(&tokens[index - 1]);
+                let after = text(&tokens[index + 1]);
+                let line_start = match before.rfind('\n') {
+                    Some(at) => before[at + 1..].trim().is_empty(),
+                    None => index == 1 && before.trim().is_empty(),
+                };
+                let line_end = match after.find('\n') {
+                    Some(at) => after[..at].trim().is_empty(),
+                    None => index + 1 == last && after.trim().is_empty(),
+                };
+                line_start && line_end
+            }
+            _ => false,
+        })
+        .collect();
+    for index in (0..=last).step_by(2) {
+        let original = text(&tokens[index]);
+        let start = match standalone.contains(&(index.wrapping_sub(1))) {
+            true => original.find('\n').map_or(original.len(), |at| at + 1),
+            false => 0,
+        };
+        let end = match standalone.contains(&(index + 1)) {
+            true => original.rfind('\n').map_or(0, |at| at + 1),
+            false => original.len(),
+        };
+        tokens[index] = Token::Text(
+            original
+                .get(start..end.max(start))
+                .unwrap_or("")
+                .to_string(),
+        );
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    path: PathBuf,
+    dir: PathBuf,
+    depth: usize,
+}
+
+impl Parser {
+    // nodes up to the end of the block that was opened, with the nodes after its `{{else}}`
+    fn nodes(&mut self, block: Option<(&str, usize)>) -> Result<(Vec<Node>, Vec<Node>)> {
+        let mut nodes = Vec::new();
+        let mut otherwise = None;
+        while self.position < self.tokens.len() {
+            let token =
+                std::mem::replace(&mut self.tokens[self.position], Token::Text(String::new()));
+            self.position += 1;
+            let (body, line) = match token {
+                Token::Text(text) => {
+                    if !text.is_empty() {
+                        nodes.push(Node::Text(text));
+                    }
+                    continue;
+                }
+                Token::Tag(body, line) => (body, line),
+            };
+            if body.starts_with('!') {
+                continue;
+            } else if body == "else" {
+                match (block, &otherwise) {
+                    (Some(_), None) => otherwise = Some(std::mem::take(&mut nodes)),
+                    _ => return Err(self.error(line, "`{{else}}` outside of a block")),
+                }
+            } else if let Some(name) = body.strip_prefix('/') {
+                return match block {
+                    Some((open, _)) if open == name.trim() => Ok(match otherwise {
+                        Some(then) => (then, nodes),
+                        None => (nodes, Vec::new()),
+                    }),
+                    Some((open, opened_on)) => Err(self.error(
+                        line,
+                        &format!(
+                            "`{{{{/{}}}}}` closes the `{{{{#{}}}}}` from line {}",
+                            name.trim(),
+                            open,
+                            opened_on
+                        ),
+                    )),
+                    None => {
+                        Err(self.error(line, &format!("`{{{{/{}}}}}` closes nothing", name.trim())))
+                    }
+                };
+            } else if let Some(opening) = body.strip_prefix('#') {
+                let (name, argument) = opening
+                    .trim()
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((opening.trim(), ""));
+                if name == "frontmatter" {
+                    nodes.push(self.front_matter(line)?);
+                    continue;
+                }
+                let expression = self.expression(argument, line)?;
+                let (then, otherwise) = match name {
+                    "if" | "unless" | "each" => self.nodes(Some((name, line)))?,
+                    _ => return Err(self.error(line, &format!("unknown block `#{}`", name))),
+                };
+                nodes.push(match name {
+                    "each" => Node::Each {
+                        list: expression,
+                        body: then,
+                        otherwise,
+                    },
+                    _ => Node::If {
+                        condition: expression,
+                        negated: name == "unless",
+                        then,
+                        otherwise,
+                    },
+                });
+            } else if let Some(name) = body.strip_prefix('>') {
+                nodes.extend(self.partial(name.trim(), line)?);
+            } else {
+                nodes.push(Node::Field(self.expression(&body, line)?));
+            }
+        }
+        match block {
+            Some((name, line)) => {
+                Err(self.error(line, &format!("`{{{{#{}}}}}` is never closed", name)))
+            }
+            None => Ok((nodes, Vec::new())),
+        }
+    }
+
+    // The block holds one `key: field` per line, and the YAML for it is written when the
+    // note is rendered, so a template only picks the keys and never has to quote anything.
+    fn front_matter(&mut self, opened_on: usize) -> Result<Node> {
+        let mut source = String::new();
+        loop {
+            let token = self
+                .tokens
+                .get(self.position)
+                .ok_or_else(|| self.error(opened_on, "`{{#frontmatter}}` is never closed"))?;
+            self.position += 1;
+            match token {
+                Token::Text(text) => source.push_str(text),
+                Token::Tag(body, _) if body == "/frontmatter" => break,
+                Token::Tag(body, line) => {
+                    return Err(self.error(
+                        *line,
+                        &format!(
+                        "`{{{{{}}}}}` inside `{{{{#frontmatter}}}}`, write `key: field` instead",
+                        body
+                    ),
+                    ))
+                }
+            }
+        }
+        let mut keys = Vec::new();
+        for (offset, text) in source.lines().enumerate() {
+            let line = opened_on + 1 + offset;
+            let text = text.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+            let (key, fields) = match text.split_once(':') {
+                Some((key, fields)) => (Some(key.trim().to_string()), fields.trim()),
+                None => (None, text),
+            };
+            let list = fields.starts_with('[') && fields.ends_with(']');
+            let fields = match list {
+                true => split_unquoted(&fields[1..fields.len() - 1], ','),
+                false => vec![fields.to_string()],
+            };
+            keys.push(FrontMatterKey {
+                key,
+                values: fields
+                    .iter()
+                    .map(|field| self.expression(field, line))
+                    .collect::<Result<Vec<_>>>()?,
+                list,
+            });
+        }
+        Ok(Node::FrontMatter(keys))
+    }
+
+    fn partial(&self, name: &str, line: usize) -> Result<Vec<Node>> {
+        if self.depth >= MAX_PARTIAL_DEPTH {
+            return Err(self.error(line, &format!("partial `{}` includes itself", name)));
+        }
+        let path = self.dir.join(PARTIALS).join(format!("{}.md", name));
+        let source = fs::read_to_string(&path).map_err(|_| {
+            self.error(
+                line,
+                &format!("no partial `{}` at {}", name, path.display()),
+            )
+        })?;
+        let mut parser = Parser {
+            tokens: tokenize(&source, &path)?,
+            position: 0,
+            path,
+            dir: self.dir.clone(),
+            depth: self.depth + 1,
+        };
+        Ok(parser.nodes(None)?.0)
+    }
+
+    // `field | filter | filter:"argument"`
+    fn expression(&self, source: &str, line: usize) -> Result<Expression> {
+        let mut parts = split_unquoted(source, '|').into_iter();
+        let path = parts.next().unwrap_or_default();
+        if !path_pattern().is_match(&path) {
+            return Err(self.error(line, &format!("`{}` is not a field name", path)));
+        }
+        let filters = parts
+            .map(|filter| {
+                let captures = filter_pattern()
+                    .captures(&filter)
+                    .ok_or_else(|| self.error(line, &format!("bad filter `{}`", filter)))?;
+                let name = captures[1].to_string();
+                let argument = captures
+                    .get(2)
+                    .map(|argument| argument.as_str().replace("\\\"", "\""));
+                if !FILTERS.contains(&name.as_str()) {
+                    return Err(self.error(line, &format!("unknown filter `{}`", name)));
+                }
+                if let (Some(format), "date") = (&argument, name.as_str()) {
+                    if StrftimeItems::new(format).any(|item| matches!(item, DateItem::Error)) {
+                        return Err(self.error(line, &format!("bad date format `{}`", format)));
+                    }
+                }
+                Ok((name, argument))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Expression {
+            path,
+            line,
+            filters,
+        })
+    }
+
+    fn error(&self, line: usize, message: &str) -> Error {
+        syntax(&self.path, line, message.to_string())
+    }
+}
+
+// splits on the separators that are not inside a quoted argument
+fn split_unquoted(source: &str, separator: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in source.chars() {
+        match c {
+            _ if c == separator && !quoted => parts.push(String::new()),
+            _ => {
+                if c == '"' && !escaped {
+                    quoted = !quoted;
+                }
+                escaped = c == '\\' && !escaped;
+                parts.last_mut().expect("there is always a part").push(c);
+            }
+        }
+    }
+    parts
+        .into_iter()
+        .map(|part| part.trim().to_string())
+        .collect()
+}
+
+struct Scope {
+    value: Value,
+    // position in and length of the list being looped over
+    index: Option<(usize, usize)>,
+}
+
+fn render_nodes(nodes: &[Node], scopes: &mut Vec<Scope>, output: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Field(expression) => {
+                if let Some(value) = evaluate(expression, scopes) {
+                    output.push_str(value.text());
+                }
+            }
+            Node::If {
+                condition,
+                negated,
+                then,
+                otherwise,
+            } => {
+                let truthy = evaluate(condition, scopes).is_some_and(|value| value.is_truthy());
+                match truthy != *negated {
+                    true => render_nodes(then, scopes, output),
+                    false => render_nodes(otherwise, scopes, output),
+                }
+            }
+            Node::Each {
+                list,
+                body,
+                otherwise,
+            } => match evaluate(list, scopes) {
+                Some(Value::List(items, _)) if !items.is_empty() => {
+                    let length = items.len();
+                    for (index, item) in items.into_iter().enumerate() {
+                        scopes.push(Scope {
+                            value: item,
+                            index: Some((index, length)),
+                        });
+                        render_nodes(body, scopes, output);
+                        scopes.pop();
+                    }
+                }
+                _ => render_nodes(otherwise, scopes, output),
+            },
+            Node::FrontMatter(keys) => render_front_matter(keys, scopes, output),
+        }
+    }
+}
+
+// empty values are left out, lists get one item per line
+fn render_front_matter(keys: &[FrontMatterKey], scopes: &[Scope], output: &mut String) {
+    for key in keys {
+        let name = match &key.key {
+            Some(name) => name,
+            None => {
+                key.values
+                    .iter()
+                    .filter_map(|expression| evaluate(expression, scopes))
+                    .for_each(|value| render_named_values(&value, output));
+                continue;
+            }
+        };
+        let mut list = key.list;
+        let mut values = Vec::new();
+        key.values
+            .iter()
+            .filter_map(|expression| evaluate(expression, scopes))
+            .for_each(|value| match value {
+                Value::List(items, _) => {
+                    list = true;
+                    values.extend(items.iter().map(|item| item.text().to_string()));
+                }
+                value => values.push(value.text().to_string()),
+            });
+        values.retain(|value| !value.is_empty());
+        if values.is_empty() {
+            continue;
+        }
+        output.push_str(&yaml_scalar(name));
+        match list {
+            true => {
+                output.push_str(":\n");
+                values
+                    .iter()
+                    .for_each(|value| output.push_str(&format!("  - {}\n", yaml_scalar(value))));
+            }
+            false => output.push_str(&format!(": {}\n", yaml_scalar(&values.join(" ")))),
+        }
+    }
+}
+
+// a list of `name`/`value` pairs, such as a generic item's fields, one key per pair
+fn render_named_values(list: &Value, output: &mut String) {
+    let items = match list {
+        Value::List(items, _) => items,
+        _ => return,
+    };
+    for item in items {
+        if let Value::Object(fields, _) = item {
+            let text = |name| fields.get(name).map(Value::text).unwrap_or("");
+            if !text("name").is_empty() && !text("value").is_empty() {
+                output.push_str(&format!(
+                    "{}: {}\n",
+                    yaml_scalar(text("name")),
+                    yaml_scalar(text("value"))
+                ));
+            }
+        }
+    }
+}
+
+fn evaluate(expression: &Expression, scopes: &[Scope]) -> Option<Value> {
+    let value = lookup(&expression.path, scopes);
+    expression
+        .filters
+        .iter()
+        .fold(value, |value, (name, argument)| {
+            filter(name, argument.as_deref(), value)
+        })
+}
+
+// `this` and the loop variables are the innermost scope's, other fields are looked up
+// from the innermost scope outwards
+fn lookup(path: &str, scopes: &[Scope]) -> Option<Value> {
+    let innermost = scopes.last()?;
+    let flag = |set: bool| Value::Text(if set { "true" } else { "" }.to_string());
+    let (index, length) = innermost.index.unwrap_or((0, 1));
+    match path {
+        "@index" => return innermost.index.map(|_| Value::Text(index.to_string())),
+        "@first" => return Some(flag(index == 0)),
+        "@last" => return Some(flag(index + 1 == length)),
+        _ => {}
+    }
+    let mut segments = path.split('.');
+    let first = segments.next()?;
+    let mut value = match first {
+        "this" => innermost.value.clone(),
+        _ => scopes.iter().rev().find_map(|scope| match &scope.value {
+            Value::Object(fields, _) => fields.get(first).cloned(),
+            _ => None,
+        })?,
+    };
+    for segment in segments {
+        value = match value {
+            Value::Object(mut fields, _) => fields.remove(segment)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+fn filter(name: &str, argument: Option<&str>, value: Option<Value>) -> Option<Value> {
+    let text = value.as_ref().map(|value| value.text().to_string());
+    let changed = |text: Option<String>| text.map(Value::Text);
+    match name {
+        "lower" => changed(text.map(|text| text.to_lowercase())),
+        "upper" => changed(text.map(|text| text.to_uppercase())),
+        "slug" => changed(text.map(|text| slug(&text))),
+        "date" => changed(text.map(|text| format_date(&text, argument.unwrap_or("%Y-%m-%d")))),
+        "default" => match value {
+            Some(value) if value.is_truthy() => Some(value),
+            _ => Some(Value::Text(argument.unwrap_or("").to_string())),
+        },
+        "join" => match value {
+            Some(Value::List(items, _)) => Some(Value::Text(
+                items
+                    .iter()
+                    .map(|item| item.text())
+                    .collect::<Vec<&str>>()
+                    .join(argument.unwrap_or(", ")),
+            )),
+            other => other,
+        },
+        "yaml" => changed(text.map(|text| yaml_scalar(&text))),
+        // `[[...]]` around the value, or around each item of a list
+        "link" => match value {
+            Some(Value::List(items, _)) => {
+                let items: Vec<Value> = items
+                    .iter()
+                    .filter(|item| !item.text().is_empty())
+                    .map(|item| Value::Text(format!("[[{}]]", item.text())))
+                    .collect();
+                let shown = items
+                    .iter()
+                    .map(|item| item.text())
+                    .collect::<Vec<&str>>()
+                    .join(", ");
+                Some(Value::List(items, shown))
+            }
+            _ => changed(
+                text.filter(|text| !text.is_empty())
+                    .map(|text| format!("[[{}]]", text)),
+            ),
+        },
+        "markdown" => changed(text.map(|text| escape_markdown(&text))),
+        _ => value,
+    }
+}
+
+fn path_pattern() -> &'static Regex {
+    static PATH: OnceLock<Regex> = OnceLock::new();
+    PATH.get_or_init(|| Regex::new(r"^(@?[A-Za-z_][\w.]*)$").expect("invalid path pattern"))
+}
+
+fn filter_pattern() -> &'static Regex {
+    static FILTER: OnceLock<Regex> = OnceLock::new();
+    FILTER.get_or_init(|| {
+        Regex::new(r#"^(\w+)\s*(?::\s*"((?:[^"\\]|\\.)*)")?$"#).expect("invalid filter pattern")
+    })
+}
+
+// Left plain when YAML reads it back as the same string, double quoted otherwise. A JSON
+// string is also a valid YAML string, whatever is in it. Full dates stay plain so that
+// Obsidian sees them as dates.
+fn yaml_scalar(text: &str) -> String {
+    static PLAIN: OnceLock<Regex> = OnceLock::new();
+    static NUMBER: OnceLock<Regex> = OnceLock::new();
+    let plain = PLAIN.get_or_init(|| {
+        Regex::new(r"^[\p{L}\p{N}][\p{L}\p{N} ._/()+,'-]*$").expect("invalid plain pattern")
+    });
+    let number = NUMBER.get_or_init(|| {
+        Regex::new(r"^[-+]?((\d[\d_]*)?\.?\d*([eE][-+]?\d+)?|0[xXoObB][\da-fA-F_]+)$")
+            .expect("invalid number pattern")
+    });
+    let keywords = ["true", "false", "yes", "no", "on", "off", "null", "y", "n"];
+    let is_plain = plain.is_match(text)
+        && !text.ends_with(' ')
+        && !number.is_match(text)
+        && !keywords.contains(&text.to_lowercase().as_str());
+    match is_plain {
+        true => text.to_string(),
+        false => serde_json::to_string(text).unwrap_or_default(),
+    }
+}
+
+// `Attention Is All You Need` -> `attention-is-all-you-need`
+fn slug(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-")
+}
+
+// Zotero dates are `2023-09-16`, `2023-09` or `2023` by the time they get here; the
+// missing parts count as the first. Anything else is left as it is.
+fn format_date(date: &str, format: &str) -> String {
+    static DATE: OnceLock<Regex> = OnceLock::new();
+    let pattern = DATE.get_or_init(|| {
+        Regex::new(r"^(\d{4})(?:-(\d{1,2}))?(?:-(\d{1,2}))?").expect("invalid date pattern")
+    });
+    let parsed = pattern.captures(date.trim()).and_then(|captures| {
+        let part = |index: usize| {
+            captures
+                .get(index)
+                .map_or(Some(1), |part| part.as_str().parse::<u32>().ok())
+        };
+        NaiveDate::from_ymd_opt(captures[1].parse().ok()?, part(2)?, part(3)?)
+    });
+    match parsed {
+        Some(parsed) => parsed.format(format).to_string(),
+        None => date.to_string(),
+    }
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if "\\`*_[]<>#|".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    fn render(source: &str, context: &Context) -> String {
+        let dir = Path::new("Meta/Templates/Resource");
+        Template::parse(source, &dir.join("Test.md"), dir)
+            .expect("template should parse")
+            .render(context)
+    }
+
+    fn error(source: &str) -> String {
+        let dir = Path::new("Meta/Templates/Resource");
+        Template::parse(source, &dir.join("Test.md"), dir)
+            .expect_err("template should not parse")
+            .to_string()
+    }
+
+    fn paper() -> Context {
+        let authors = vec![
+            Value::object(
+                vec![("name", "Ann Smith".to_string())],
+                "Ann Smith".to_string(),
+            ),
+            Value::object(vec![("name", "CERN".to_string())], "CERN".to_string()),
+        ];
+        let mut context = Context::default();
+        context
+            .set("full_title", "Attention Is All You Need")
+            .set("doi", "")
+            .set("publish_date", "2017-06-12")
+            .set(
+                "authors",
+                Value::List(authors, "\n- [[Ann Smith]],\n- [[CERN]]".to_string()),
+            );
+        context
+    }
+
+    #[test]
+    fn fills_in_fields_through_filters() {
+        let context = paper();
+        assert_eq!(
+            render(
+                "{{full_title | slug}} {{ publish_date | date:\"%B %Y\" | lower }}",
+                &context
+            ),
+            "attention-is-all-you-need june 2017"
+        );
+        assert_eq!(render("{{doi | default:\"none\"}}", &context), "none");
+        assert_eq!(
+            render("{{authors | join:\"; \"}}", &context),
+            "Ann Smith; CERN"
+        );
+        assert_eq!(
+            render("{{full_title | yaml}}", &context),
+            "Attention Is All You Need"
+        );
+        assert_eq!(
+            render("{{authors | link | join:\", \"}}", &context),
+            "[[Ann Smith]], [[CERN]]"
+        );
+        assert_eq!(render("{{missing}}", &context), "");
+    }
+
+    #[test]
+    fn does_not_read_values_as_template_syntax() {
+        let mut context = Context::default();
+        context.set("title", "{{doi}} [draft]").set("doi", "10.1/x");
+        assert_eq!(render("{{title}}", &context), "{{doi}} [draft]");
+        assert_eq!(
+            render("{{title | markdown}}", &context),
+            "{{doi}} \\[draft\\]"
+        );
+        assert_eq!(render("\\{{doi}}", &context), "{{doi}}");
+    }
+
+    #[test]
+    fn takes_lines_with_block_tags_out() {
+        let template = "## Links\n{{#if doi}}\n- [DOI]({{doi}})\n{{else}}\n- no DOI\n{{/if}}\n{{#each authors}}\n- {{@index}} [[{{name}}]] of {{full_title}}\n{{/each}}\nend\n";
+        assert_eq!(
+            render(template, &paper()),
+            "## Links\n- no DOI\n- 0 [[Ann Smith]] of Attention Is All You Need\n- 1 [[CERN]] of Attention Is All You Need\nend\n"
+        );
+        assert_eq!(
+            render("{{#unless doi}}no DOI{{/unless}}, {{#each authors}}{{this}}{{#unless @last}} & {{/unless}}{{/each}}", &paper()),
+            "no DOI, Ann Smith & CERN"
+        );
+        assert_eq!(
+            render("authors: {{authors}}", &paper()),
+            "authors: \n- [[Ann Smith]],\n- [[CERN]]"
+        );
+    }
+
+    #[test]
+    fn writes_front_matter_as_yaml() {
+        let mut context = paper();
+        context
+            .set("full_title", "[Re]: \"Attention\" #1")
+            .set("version", "1.10")
+            .set("id", "vaswani2017");
+        let template = "---\n{{#frontmatter}}\ntitle: full_title\naliases: [id, full_title]\nDOI: doi\nversion: version\nauthors: authors | link\ndate: publish_date\n{{/frontmatter}}\n---\n";
+        assert_eq!(
+            render(template, &context),
+            "---\ntitle: \"[Re]: \\\"Attention\\\" #1\"\naliases:\n  - vaswani2017\n  - \"[Re]: \\\"Attention\\\" #1\"\nversion: \"1.10\"\nauthors:\n  - \"[[Ann Smith]]\"\n  - \"[[CERN]]\"\ndate: 2017-06-12\n---\n"
+        );
+        assert_eq!(
+            error("{{#frontmatter}}\ntitle: {{full_title}}\n{{/frontmatter}}"),
+            "Meta/Templates/Resource/Test.md:2: `{{full_title}}` inside `{{#frontmatter}}`, write `key: field` instead"
+        );
+    }
+
+    #[test]
+    fn writes_a_list_of_named_values_as_keys() {
+        let field = |name: &str, value: &str| {
+            Value::object(
+                vec![("name", name.to_string()), ("value", value.to_string())],
+                value.to_string(),
+            )
+        };
+        let mut context = Context::default();
+        context.set("title", "Minutes").set(
+            "fields",
+            Value::List(
+                vec![
+                    field("place", "Geneva: CERN"),
+                    field("edition", ""),
+                    field("pages", "12"),
+                ],
+                String::new(),
+            ),
+        );
+        assert_eq!(
+            render(
+                "---\n{{#frontmatter}}\ntitle: title\nfields\n{{/frontmatter}}\n---\n",
+                &context
+            ),
+            "---\ntitle: Minutes\nplace: \"Geneva: CERN\"\npages: \"12\"\n---\n"
+        );
+        assert_eq!(
+            error("{{#frontmatter}}\ntitle full_title\n{{/frontmatter}}"),
+            "Meta/Templates/Resource/Test.md:2: `title full_title` is not a field name"
+        );
+    }
+
+    #[test]
+    fn reads_partials_from_the_template_folder() {
+        let dir = env::temp_dir().join(format!("zotero-to-obsidian-partials-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join(PARTIALS)).unwrap();
+        fs::write(
+            dir.join(PARTIALS).join("links.md"),
+            "{{#if url}}\n- [Open]({{url}})\n{{/if}}\n{{> cloud}}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join(PARTIALS).join("cloud.md"),
+            "- [Open in Zotero (cloud)]({{zotero_cloud_link}})\n",
+        )
+        .unwrap();
+        let mut context = Context::default();
+        context.set("zotero_cloud_link", "http://zotero.org/users/1/items/AAA");
+        let template = Template::parse(
+            "%% zotero:begin links %%\n{{> links}}\n%% zotero:end links %%\n",
+            &dir.join("Test.md"),
+            &dir,
+        )
+        .expect("template should parse");
+        assert_eq!(
+            template.render(&context),
+            "%% zotero:begin links %%\n- [Open in Zotero (cloud)](http://zotero.org/users/1/items/AAA)\n%% zotero:end links %%\n"
+        );
+    }
+
+    #[test]
+    fn finds_unknown_and_used_fields() {
+        let dir = Path::new("Meta/Templates/Resource");
+        let template = Template::parse(
+            "journal: {{journal_name}}\n{{#each authors}}\n- {{name}} {{nick}}\n{{/each}}\n{{#if doi}}{{@index}}{{/if}}\n",
+            &dir.join("Test.md"),
+            dir,
+        )
+        .expect("template should parse");
+        let check = template.check(&paper());
+        assert_eq!(
+            check.unknown,
+            vec![
+                (1, "journal_name".to_string()),
+                (3, "nick".to_string()),
+                (5, "@index".to_string())
+            ]
+        );
+        assert_eq!(
+            check.used.into_iter().collect::<Vec<String>>(),
+            vec!["authors", "doi"]
+        );
+    }
+
+    #[test]
+    fn reports_mistakes_with_their_line() {
+        assert_eq!(
+            error("title\n{{#if doi}}\n{{doi}}\n"),
+            "Meta/Templates/Resource/Test.md:2: `{{#if}}` is never closed"
+        );
+        assert_eq!(
+            error("{{#each authors}}\n{{/if}}"),
+            "Meta/Templates/Resource/Test.md:2: `{{/if}}` closes the `{{#each}}` from line 1"
+        );
+        assert_eq!(
+            error("{{title | shout}}"),
+            "Meta/Templates/Resource/Test.md:1: unknown filter `shout`"
+        );
+        assert_eq!(
+            error("{{date | date:\"%Q\"}}"),
+            "Meta/Templates/Resource/Test.md:1: bad date format `%Q`"
+        );
+        assert!(error("{{> nowhere}}").contains("no partial `nowhere`"));
+    }
+}
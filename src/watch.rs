@@ -0,0 +1,418 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use super::cli::Paths;
+use super::config::VaultConfig;
+use super::json_parser::{Item, ZoteroData};
+use super::resource_types::{ReferenceList, References, Templates};
+use super::sync::{NoteSync, SyncReport};
+use super::{read_library, report_failures, sync_notes};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+// Better BibTeX rewrites the export several times in a row, so wait for the file to
+// stay the same for a while before reading it
+const DEBOUNCE: Duration = Duration::from_secs(2);
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+struct Stamp {
+    modified: SystemTime,
+    len: u64,
+}
+
+fn stamp(path: &Path) -> Option<Stamp> {
+    let metadata = fs::metadata(path).ok()?;
+    Some(Stamp {
+        modified: metadata.modified().ok()?,
+        len: metadata.len(),
+    })
+}
+
+// every file in the template folder, partials included, so editing any of them is seen
+fn template_stamps(dir: &Path) -> Vec<(PathBuf, Stamp)> {
+    let mut stamps = Vec::new();
+    let mut folders = vec![dir.to_path_buf()];
+    while let Some(folder) = folders.pop() {
+        let entries = match fs::read_dir(&folder) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for path in entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+        {
+            if path.is_dir() {
+                folders.push(path);
+            } else if let Some(stamp) = stamp(&path) {
+                stamps.push((path, stamp));
+            }
+        }
+    }
+    stamps.sort_by(|a, b| a.0.cmp(&b.0));
+    stamps
+}
+
+// one snapshot of each item, keyed by citekey, to tell which items an export touched
+fn snapshot(zotero_data: &ZoteroData) -> HashMap<String, String> {
+    zotero_data
+        .items
+        .iter()
+        .map(|item: &Item| {
+            (
+                item.id.clone(),
+                serde_json::to_string(item).unwrap_or_default(),
+            )
+        })
+        .collect()
+}
+
+pub fn watch(paths: &Paths, config: &VaultConfig, templates: Templates) {
+    println!("Watching {} for changes", paths.library.display());
+    let mut watcher = Watcher::new(paths, config, templates, DEBOUNCE);
+    loop {
+        watcher.poll();
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+// what `watch` remembers between two looks at the library and the templates
+struct Watcher<'a> {
+    paths: &'a Paths,
+    config: &'a VaultConfig,
+    templates: Templates,
+    debounce: Duration,
+    library_seen: Option<Stamp>,
+    templates_seen: Vec<(PathBuf, Stamp)>,
+    items: HashMap<String, String>,
+    first_run: bool,
+}
+
+impl<'a> Watcher<'a> {
+    fn new(
+        paths: &'a Paths,
+        config: &'a VaultConfig,
+        templates: Templates,
+        debounce: Duration,
+    ) -> Watcher<'a> {
+        Watcher {
+            paths,
+            config,
+            templates,
+            debounce,
+            library_seen: stamp(&paths.library),
+            templates_seen: template_stamps(&paths.templates),
+            items: HashMap::new(),
+            first_run: true,
+        }
+    }
+
+    // Syncs when the library or a template changed since the last look, and returns the
+    // report when notes were synced. Edited templates are read again and every note is
+    // rewritten with them; templates that no longer load are reported and the previous
+    // ones kept.
+    fn poll(&mut self) -> Option<SyncReport> {
+        let mut everything = self.first_run;
+        if template_stamps(&self.paths.templates) != self.templates_seen {
+            let dir = &self.paths.templates;
+            self.templates_seen = settle(|| template_stamps(dir), self.debounce);
+            match Templates::load(dir, self.config) {
+                Ok(templates) => {
+                    println!("Templates changed, rewriting every note");
+                    self.templates = templates;
+                    everything = true;
+                }
+                Err(error) => {
+                    eprintln!(
+                        "Could not read the templates, keeping the previous ones: {}",
+                        error
+                    );
+                }
+            }
+        }
+        if !everything && stamp(&self.paths.library) == self.library_seen {
+            return None;
+        }
+
+        let library_path = self.paths.library.as_path();
+        self.library_seen = settle(|| stamp(library_path), self.debounce);
+        self.first_run = false;
+        match read_library(library_path) {
+            Ok(mut zotero_data) => {
+                zotero_data
+                    .items
+                    .retain(|item| !self.config.skips(&item.item_type));
+                let current = snapshot(&zotero_data);
+                let affected = match everything {
+                    true => None,
+                    false => Some(log_changes(&self.items, &current)),
+                };
+                self.items = current;
+                resync(
+                    self.paths,
+                    self.config,
+                    &self.templates,
+                    &zotero_data,
+                    affected.as_ref(),
+                )
+            }
+            Err(error) => {
+                eprintln!(
+                    "Could not read {}, keeping the previous notes: {}",
+                    library_path.display(),
+                    error
+                );
+                None
+            }
+        }
+    }
+}
+
+// looks again after every pause until two looks in a row agree
+fn settle<T: PartialEq>(mut look: impl FnMut() -> T, pause: Duration) -> T {
+    let mut current = look();
+    loop {
+        thread::sleep(pause);
+        let latest = look();
+        if latest == current {
+            return latest;
+        }
+        current = latest;
+    }
+}
+
+#[derive(PartialEq, Debug)]
+enum Change {
+    Added,
+    Changed,
+    Removed,
+}
+
+// the items an export added, changed or removed, in citekey order
+fn changes(
+    previous: &HashMap<String, String>,
+    current: &HashMap<String, String>,
+) -> Vec<(Change, String)> {
+    let mut changes: Vec<(Change, String)> = current
+        .iter()
+        .filter_map(|(citekey, item)| match previous.get(citekey) {
+            None => Some((Change::Added, citekey.clone())),
+            Some(previous_item) if previous_item != item => {
+                Some((Change::Changed, citekey.clone()))
+            }
+            Some(_) => None,
+        })
+        .chain(
+            previous
+                .keys()
+                .filter(|citekey| !current.contains_key(*citekey))
+                .map(|citekey| (Change::Removed, citekey.clone())),
+        )
+        .collect();
+    changes.sort_by(|a, b| a.1.cmp(&b.1));
+    changes
+}
+
+// prints the changes and returns the citekeys whose notes have to be written again
+fn log_changes(
+    previous: &HashMap<String, String>,
+    current: &HashMap<String, String>,
+) -> HashSet<String> {
+    let mut affected = HashSet::new();
+    changes(previous, current)
+        .into_iter()
+        .for_each(|(change, citekey)| match change {
+            Change::Added => {
+                println!("added    {}", citekey);
+                affected.insert(citekey);
+            }
+            Change::Changed => {
+                println!("changed  {}", citekey);
+                affected.insert(citekey);
+            }
+            Change::Removed => println!("removed  {}", citekey),
+        });
+    affected
+}
+
+fn resync(
+    paths: &Paths,
+    config: &VaultConfig,
+    templates: &Templates,
+    zotero_data: &ZoteroData,
+    affected: Option<&HashSet<String>>,
+) -> Option<SyncReport> {
+    let mut references = References::new();
+    references.populate(&zotero_data.items, config);
+
+    let mut sync = NoteSync::new(&paths.output, &paths.sync_state);
+    let result =
+        sync_notes(&references, config, templates, &mut sync, affected).and_then(|_| sync.finish());
+    let report = match result {
+        Ok(report) => {
+            report.print();
+            Some(report)
+        }
+        Err(error) => {
+            eprintln!("Failed to sync notes: {}", error);
+            None
+        }
+    };
+    report_failures(&references);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    const SHORT: Duration = Duration::from_millis(20);
+
+    fn items(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(citekey, item)| (citekey.to_string(), item.to_string()))
+            .collect()
+    }
+
+    fn library(titles: &[(&str, &str)]) -> String {
+        let items: Vec<serde_json::Value> = titles
+            .iter()
+            .map(|(citekey, title)| {
+                serde_json::json!({
+                    "citationKey": citekey,
+                    "itemType": "letter",
+                    "title": title,
+                    "creators": [],
+                    "tags": [],
+                    "notes": []
+                })
+            })
+            .collect();
+        serde_json::json!({ "config": {}, "items": items }).to_string()
+    }
+
+    // a vault of its own with a library and a template for every resource type
+    fn vault(name: &str, config: &VaultConfig) -> Paths {
+        let root = env::temp_dir().join(format!(
+            "zotero-to-obsidian-watch-{}-{}",
+            name,
+            process::id()
+        ));
+        let _ = fs::remove_dir_all(&root);
+        let templates = root.join("Templates");
+        fs::create_dir_all(templates.join("partials")).unwrap();
+        for (_, template) in config.template_uses() {
+            fs::write(templates.join(template), "{{> title}}\n").unwrap();
+        }
+        fs::write(templates.join("partials/title.md"), "# {{full_title}}").unwrap();
+        fs::write(
+            root.join("library.json"),
+            library(&[("lee2019", "Dear Ann")]),
+        )
+        .unwrap();
+        Paths {
+            library: root.join("library.json"),
+            templates,
+            output: root.join("Resources"),
+            sync_state: root.join(".library/sync-state.json"),
+            web_api: None,
+            api_cache: root.join(".library/zotero-api.json"),
+        }
+    }
+
+    fn watcher<'a>(paths: &'a Paths, config: &'a VaultConfig) -> Watcher<'a> {
+        let templates = Templates::load(&paths.templates, config).unwrap();
+        Watcher::new(paths, config, templates, SHORT)
+    }
+
+    fn note(paths: &Paths, citekey: &str) -> String {
+        fs::read_to_string(paths.output.join(format!("Misc/{}.md", citekey))).unwrap()
+    }
+
+    #[test]
+    fn tells_added_changed_and_removed_items_apart() {
+        let previous = items(&[("a2020", "A"), ("b2020", "B"), ("c2020", "C")]);
+        let current = items(&[("a2020", "A"), ("b2020", "B, edited"), ("d2020", "D")]);
+        assert_eq!(
+            changes(&previous, &current),
+            [
+                (Change::Changed, "b2020".to_string()),
+                (Change::Removed, "c2020".to_string()),
+                (Change::Added, "d2020".to_string()),
+            ]
+        );
+        let mut affected: Vec<String> = log_changes(&previous, &current).into_iter().collect();
+        affected.sort();
+        assert_eq!(affected, ["b2020", "d2020"]);
+        assert!(log_changes(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn waits_for_the_file_to_settle() {
+        let mut looks = vec![3, 3, 2, 1].into_iter();
+        let mut count = 0;
+        let settled = settle(
+            || {
+                count += 1;
+                looks.next_back().unwrap()
+            },
+            Duration::from_millis(1),
+        );
+        assert_eq!(settled, 3);
+        assert_eq!(count, 4);
+    }
+
+    #[test]
+    fn syncs_only_the_items_an_export_touched() {
+        let config = VaultConfig::default();
+        let paths = vault("library", &config);
+        let mut watcher = watcher(&paths, &config);
+
+        let report = watcher.poll().expect("the first look syncs everything");
+        assert_eq!(report.created, ["Misc/lee2019.md"]);
+        assert!(watcher.poll().is_none());
+
+        fs::write(
+            &paths.library,
+            library(&[("lee2019", "Dear Ann, again"), ("kim2021", "Hello")]),
+        )
+        .unwrap();
+        let report = watcher.poll().expect("a new export is synced");
+        assert_eq!(report.created, ["Misc/kim2021.md"]);
+        assert_eq!(report.updated, ["Misc/lee2019.md"]);
+        assert_eq!(note(&paths, "lee2019"), "# Dear Ann, again");
+
+        // an export that can't be read keeps the notes
+        fs::write(&paths.library, "{ not json").unwrap();
+        assert!(watcher.poll().is_none());
+        assert_eq!(note(&paths, "kim2021"), "# Hello");
+    }
+
+    #[test]
+    fn rewrites_every_note_when_a_template_changes() {
+        let config = VaultConfig::default();
+        let paths = vault("templates", &config);
+        let mut watcher = watcher(&paths, &config);
+        watcher.poll();
+
+        fs::write(
+            paths.templates.join("partials/title.md"),
+            "## {{full_title}}",
+        )
+        .unwrap();
+        let report = watcher.poll().expect("edited templates are synced");
+        assert_eq!(report.updated, ["Misc/lee2019.md"]);
+        assert_eq!(note(&paths, "lee2019"), "## Dear Ann");
+
+        // a broken template is reported and the previous ones are used
+        fs::write(paths.templates.join("Generic.md"), "{{#if}}").unwrap();
+        assert!(watcher.poll().is_none());
+        fs::write(&paths.library, library(&[("lee2019", "Dear Bo")])).unwrap();
+        watcher.poll().expect("the library is still watched");
+        assert_eq!(note(&paths, "lee2019"), "## Dear Bo");
+    }
+}
@@ -0,0 +1,785 @@
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use super::citekey;
+use super::config::WebApiConfig;
+use super::error::{Error, Result};
+use super::json_parser::{Attachment, Creator, Item, Note, Tag, ZoteroData};
+
+pub const DEFAULT_API_URL: &str = "https://api.zotero.org";
+// what we fetched last time, so later runs only ask for what changed since
+pub const API_CACHE_PATH: &str = ".library/zotero-api.json";
+
+// the most the API hands out in one page
+const PAGE_SIZE: usize = 100;
+const API_VERSION: &str = "3";
+
+pub struct Response {
+    pub status: u16,
+    // names are lower case
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl Response {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_lowercase()).map(String::as_str)
+    }
+}
+
+// all we need from an HTTP client, so that tests can point us at a stand-in server
+pub trait HttpClient {
+    fn get(&self, url: &str, headers: &[(&str, String)]) -> Result<Response>;
+}
+
+pub struct UreqClient {
+    agent: ureq::Agent,
+}
+
+impl UreqClient {
+    pub fn new() -> UreqClient {
+        UreqClient {
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+}
+
+impl HttpClient for UreqClient {
+    fn get(&self, url: &str, headers: &[(&str, String)]) -> Result<Response> {
+        let http_error = |message: String| Error::Http {
+            url: url.to_string(),
+            message,
+        };
+        let request = headers
+            .iter()
+            .fold(self.agent.get(url), |request, (name, value)| {
+                request.set(name, value)
+            });
+        // error statuses are handed back like any other response and checked by the caller
+        let response = match request.call() {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(error) => return Err(http_error(error.to_string())),
+        };
+        let headers = response
+            .headers_names()
+            .iter()
+            .filter_map(|name| {
+                response
+                    .header(name)
+                    .map(|value| (name.to_lowercase(), value.to_string()))
+            })
+            .collect();
+        let status = response.status();
+        let body = response
+            .into_string()
+            .map_err(|error| http_error(error.to_string()))?;
+        Ok(Response {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Cache {
+    libraries: HashMap<String, CachedLibrary>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct CachedLibrary {
+    version: u64,
+    // the API's JSON for every item, keyed by item key
+    items: HashMap<String, Value>,
+}
+
+impl Cache {
+    fn load(path: &Path) -> Cache {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_default(),
+            Err(_) => Cache::default(),
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|error| Error::io(parent, error))?;
+        }
+        let contents = serde_json::to_string(self).expect("the cache is always valid JSON");
+        fs::write(path, contents).map_err(|error| Error::io(path, error))
+    }
+}
+
+// Fetches every configured library from the Zotero web API, only asking for what changed
+// since the last run
+pub fn read(config: &WebApiConfig, cache_path: &Path) -> Result<ZoteroData> {
+    read_with(&UreqClient::new(), config, cache_path)
+}
+
+fn read_with(
+    client: &impl HttpClient,
+    config: &WebApiConfig,
+    cache_path: &Path,
+) -> Result<ZoteroData> {
+    let api = ZoteroApi {
+        client,
+        url: config.url.trim_end_matches('/'),
+        api_key: config
+            .api_key
+            .clone()
+            .or_else(|| env::var("ZOTERO_API_KEY").ok()),
+    };
+
+    let mut cache = Cache::load(cache_path);
+    for library in &config.libraries {
+        let cached = cache.libraries.entry(library.clone()).or_default();
+        api.update(library, cached)?;
+    }
+    cache.save(cache_path)?;
+
+    let mut items = Vec::new();
+    for library in &config.libraries {
+        if let Some(cached) = cache.libraries.get(library) {
+            items.extend(library_items(library, cached));
+        }
+    }
+    Ok(ZoteroData {
+        collections: HashMap::new(),
+        config: None,
+        items,
+    })
+}
+
+struct ZoteroApi<'a, C: HttpClient> {
+    client: &'a C,
+    url: &'a str,
+    api_key: Option<String>,
+}
+
+impl<C: HttpClient> ZoteroApi<'_, C> {
+    fn get(&self, path: &str, since: Option<u64>) -> Result<Response> {
+        let url = format!("{}{}", self.url, path);
+        let mut headers = vec![("Zotero-API-Version", API_VERSION.to_string())];
+        if let Some(api_key) = &self.api_key {
+            headers.push(("Zotero-API-Key", api_key.clone()));
+        }
+        if let Some(version) = since {
+            headers.push(("If-Modified-Since-Version", version.to_string()));
+        }
+
+        let response = self.client.get(&url, &headers)?;
+        match response.status {
+            200..=299 | 304 => Ok(response),
+            status => Err(Error::Http {
+                url,
+                message: format!("HTTP {}: {}", status, response.body.trim()),
+            }),
+        }
+    }
+
+    // Brings a cached library up to date. If nothing changed since the cached version the
+    // API answers 304 and we are done; otherwise we page through the items changed since
+    // then and drop the ones deleted since then.
+    fn update(&self, library: &str, cached: &mut CachedLibrary) -> Result<()> {
+        let since = Some(cached.version).filter(|version| *version > 0);
+        let mut start = 0;
+        let mut version = cached.version;
+        loop {
+            let mut path = format!(
+                "/{}/items?format=json&limit={}&start={}",
+                library, PAGE_SIZE, start
+            );
+            if let Some(since) = since {
+                path.push_str(&format!("&since={}", since));
+            }
+            // only the first page is conditional, later pages belong to the same fetch
+            let response = self.get(&path, since.filter(|_| start == 0))?;
+            if response.status == 304 {
+                return Ok(());
+            }
+            if let Some(modified) = response
+                .header("Last-Modified-Version")
+                .and_then(|version| version.parse().ok())
+            {
+                version = modified;
+            }
+
+            let page: Vec<Value> = self.json(&path, &response)?;
+            let total: usize = response
+                .header("Total-Results")
+                .and_then(|total| total.parse().ok())
+                .unwrap_or(0);
+            start += page.len();
+            let is_last_page = page.is_empty() || start >= total;
+            page.into_iter().for_each(|item| {
+                if let Some(key) = item.get("key").and_then(Value::as_str) {
+                    cached.items.insert(key.to_string(), item.clone());
+                }
+            });
+            if is_last_page {
+                break;
+            }
+        }
+
+        if let Some(since) = since {
+            let path = format!("/{}/deleted?since={}", library, since);
+            let deleted: Value = self.json(&path, &self.get(&path, None)?)?;
+            deleted
+                .get("items")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .for_each(|key| {
+                    cached.items.remove(key);
+                });
+        }
+        cached.version = version;
+        Ok(())
+    }
+
+    fn json<T: serde::de::DeserializeOwned>(&self, path: &str, response: &Response) -> Result<T> {
+        serde_json::from_str(&response.body).map_err(|error| Error::Http {
+            url: format!("{}{}", self.url, path),
+            message: format!("unexpected response: {}", error),
+        })
+    }
+}
+
+// turns the API's items into ours, with child notes and attachments moved under their
+// parents and trashed items left out
+fn library_items(library: &str, cached: &CachedLibrary) -> Vec<Item> {
+    let data = |item: &Value| item.get("data").and_then(Value::as_object).cloned();
+    let mut entries: Vec<(String, Map<String, Value>)> = cached
+        .items
+        .iter()
+        .filter_map(|(key, item)| Some((key.clone(), data(item)?)))
+        .filter(|(_, data)| !is_trashed(data))
+        .collect();
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut notes: HashMap<String, Vec<Note>> = HashMap::new();
+    let mut attachments: HashMap<String, Vec<Attachment>> = HashMap::new();
+    for (key, data) in &entries {
+        let parent = match text(data, "parentItem") {
+            Some(parent) => parent,
+            None => continue,
+        };
+        match text(data, "itemType").as_deref() {
+            Some("note") => notes.entry(parent).or_default().push(Note {
+                date_added: text(data, "dateAdded").unwrap_or_default(),
+                date_modified: text(data, "dateModified").unwrap_or_default(),
+                content: text(data, "note").unwrap_or_default(),
+                uri: format!("http://zotero.org/{}/items/{}", library, key),
+            }),
+            Some("attachment") => {
+                if let Some(attachment) = attachment(library, key, data) {
+                    attachments.entry(parent).or_default().push(attachment);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    entries
+        .iter()
+        .filter(|(_, data)| {
+            text(data, "parentItem").is_none()
+                && !matches!(
+                    text(data, "itemType").as_deref(),
+                    Some("note") | Some("attachment") | Some("annotation")
+                )
+        })
+        .map(|(key, data)| Item {
+            id: text(data, "citationKey")
+                .or_else(|| text(data, "extra").and_then(|extra| citekey::pinned(&extra)))
+                .unwrap_or_default(),
+            item_type: text(data, "itemType").unwrap_or_default(),
+            doi: text(data, "DOI"),
+            title: text(data, "title")
+                .or_else(|| text(data, "caseName"))
+                .or_else(|| text(data, "nameOfAct")),
+            library_catalog: text(data, "libraryCatalog"),
+            url: text(data, "url"),
+            creators: data
+                .get("creators")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_object)
+                .map(|creator| Creator {
+                    creator_type: text(creator, "creatorType"),
+                    first_name: text(creator, "firstName"),
+                    last_name: text(creator, "lastName"),
+                    name: text(creator, "name"),
+                })
+                .collect(),
+            isbn: text(data, "ISBN"),
+            short_title: text(data, "shortTitle"),
+            select: Some(format!("{}/items/{}", select_prefix(library), key)),
+            uri: Some(format!("http://zotero.org/{}/items/{}", library, key)),
+            published_date: text(data, "date")
+                .or_else(|| text(data, "dateDecided"))
+                .or_else(|| text(data, "dateEnacted")),
+            journal: text(data, "publicationTitle"),
+            book_title: text(data, "bookTitle"),
+            proceedings_title: text(data, "proceedingsTitle"),
+            conference_name: text(data, "conferenceName"),
+            publisher: text(data, "publisher"),
+            place: text(data, "place"),
+            pages: text(data, "pages"),
+            university: text(data, "university"),
+            thesis_type: text(data, "thesisType"),
+            institution: text(data, "institution"),
+            report_number: text(data, "reportNumber"),
+            report_type: text(data, "reportType"),
+            series_title: text(data, "seriesTitle"),
+            episode_number: text(data, "episodeNumber"),
+            running_time: text(data, "runningTime"),
+            section: text(data, "section"),
+            edition: text(data, "edition"),
+            archive_id: text(data, "archiveID"),
+            repository: text(data, "repository"),
+            extra: text(data, "extra"),
+            court: text(data, "court"),
+            reporter: text(data, "reporter"),
+            reporter_volume: text(data, "reporterVolume"),
+            first_page: text(data, "firstPage"),
+            docket_number: text(data, "docketNumber"),
+            jurisdiction: text(data, "jurisdiction"),
+            code: text(data, "code"),
+            code_number: text(data, "codeNumber"),
+            code_volume: text(data, "codeVolume"),
+            code_pages: text(data, "codePages"),
+            public_law_number: text(data, "publicLawNumber"),
+            bill_number: text(data, "billNumber"),
+            legislative_body: text(data, "legislativeBody"),
+            session: text(data, "session"),
+            version_number: text(data, "versionNumber"),
+            programming_language: text(data, "programmingLanguage"),
+            system: text(data, "system"),
+            company: text(data, "company"),
+            rights: text(data, "rights"),
+            relations: data
+                .get("relations")
+                .and_then(Value::as_object)
+                .into_iter()
+                .flat_map(|relations| relations.values())
+                .flat_map(|value| match value {
+                    Value::Array(values) => values.iter().filter_map(Value::as_str).collect(),
+                    value => value.as_str().into_iter().collect::<Vec<_>>(),
+                })
+                .map(str::to_string)
+                .collect(),
+            tags: data
+                .get("tags")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_object)
+                .filter_map(|tag| text(tag, "tag"))
+                .map(|tag| Tag { tag })
+                .collect(),
+            notes: notes.remove(key).unwrap_or_default(),
+            attachments: attachments.remove(key).unwrap_or_default(),
+        })
+        .collect()
+}
+
+fn text(data: &Map<String, Value>, field: &str) -> Option<String> {
+    match data.get(field)? {
+        Value::String(value) if !value.is_empty() => Some(value.clone()),
+        _ => None,
+    }
+}
+
+fn is_trashed(data: &Map<String, Value>) -> bool {
+    match data.get("deleted") {
+        Some(Value::Bool(deleted)) => *deleted,
+        Some(Value::Number(deleted)) => deleted.as_u64() != Some(0),
+        _ => false,
+    }
+}
+
+// users/123 -> zotero://select/library, groups/456 -> zotero://select/groups/456
+fn select_prefix(library: &str) -> String {
+    match library.strip_prefix("groups/") {
+        Some(group) => format!("zotero://select/groups/{}", group),
+        None => "zotero://select/library".to_string(),
+    }
+}
+
+// Files stored in Zotero can't be reached from here, so they open in the Zotero app;
+// linked files and links keep their path or URL
+fn attachment(library: &str, key: &str, data: &Map<String, Value>) -> Option<Attachment> {
+    let title = text(data, "title");
+    match text(data, "linkMode").as_deref() {
+        Some("linked_url") => Some(Attachment {
+            title,
+            path: None,
+            url: text(data, "url"),
+        }),
+        Some("linked_file") => Some(Attachment {
+            title,
+            path: text(data, "path"),
+            url: None,
+        }),
+        Some("imported_file") | Some("imported_url") => Some(Attachment {
+            title,
+            path: None,
+            url: Some(format!(
+                "{}/items/{}",
+                select_prefix(library).replace("zotero://select", "zotero://open-pdf"),
+                key
+            )),
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::path::PathBuf;
+    use std::process;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    // responses recorded from api.zotero.org, trimmed down to a handful of items
+    const ITEMS_PAGE_1: &str = include_str!("../tests/fixtures/zotero-api/items-page-1.json");
+    const ITEMS_PAGE_2: &str = include_str!("../tests/fixtures/zotero-api/items-page-2.json");
+    const ITEMS_SINCE: &str = include_str!("../tests/fixtures/zotero-api/items-since-20.json");
+    const DELETED_SINCE: &str = include_str!("../tests/fixtures/zotero-api/deleted-since-20.json");
+    const GROUP_ITEMS: &str = include_str!("../tests/fixtures/zotero-api/group-items.json");
+
+    struct Route {
+        target: &'static str,
+        status: u16,
+        headers: Vec<(&'static str, &'static str)>,
+        body: &'static str,
+    }
+
+    struct Recorded {
+        target: String,
+        headers: HashMap<String, String>,
+    }
+
+    // A stand-in for api.zotero.org on a local port. It answers the routes it is given,
+    // 404 for anything else, and records every request.
+    struct StandIn {
+        url: String,
+        requests: Arc<Mutex<Vec<Recorded>>>,
+    }
+
+    impl StandIn {
+        fn start(routes: Vec<Route>) -> StandIn {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let recorded = Arc::clone(&requests);
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let mut stream = stream.unwrap();
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut request_line = String::new();
+                    reader.read_line(&mut request_line).unwrap();
+                    let target = request_line
+                        .split_whitespace()
+                        .nth(1)
+                        .unwrap_or_default()
+                        .to_string();
+                    let mut headers = HashMap::new();
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        match line.trim_end().split_once(": ") {
+                            Some((name, value)) => {
+                                headers.insert(name.to_lowercase(), value.to_string());
+                            }
+                            None => break,
+                        }
+                    }
+
+                    let (status, extra_headers, body) =
+                        match routes.iter().find(|route| route.target == target) {
+                            Some(route) => (route.status, route.headers.clone(), route.body),
+                            None => (404, Vec::new(), "Not found"),
+                        };
+                    let mut response = format!(
+                        "HTTP/1.1 {} Stand-in\r\nContent-Length: {}\r\nConnection: close\r\n",
+                        status,
+                        body.len()
+                    );
+                    extra_headers.iter().for_each(|(name, value)| {
+                        response.push_str(&format!("{}: {}\r\n", name, value))
+                    });
+                    response.push_str("\r\n");
+                    response.push_str(body);
+                    stream.write_all(response.as_bytes()).unwrap();
+                    recorded.lock().unwrap().push(Recorded { target, headers });
+                }
+            });
+            StandIn { url, requests }
+        }
+
+        fn targets(&self) -> Vec<String> {
+            self.requests
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|request| request.target.clone())
+                .collect()
+        }
+    }
+
+    fn full_fetch_routes() -> Vec<Route> {
+        vec![
+            Route {
+                target: "/users/475425/items?format=json&limit=100&start=0",
+                status: 200,
+                headers: vec![("Total-Results", "5"), ("Last-Modified-Version", "20")],
+                body: ITEMS_PAGE_1,
+            },
+            Route {
+                target: "/users/475425/items?format=json&limit=100&start=3",
+                status: 200,
+                headers: vec![("Total-Results", "5"), ("Last-Modified-Version", "20")],
+                body: ITEMS_PAGE_2,
+            },
+        ]
+    }
+
+    fn config(stand_in: &StandIn, libraries: &[&str]) -> WebApiConfig {
+        WebApiConfig {
+            libraries: libraries
+                .iter()
+                .map(|library| library.to_string())
+                .collect(),
+            api_key: Some("test-key".to_string()),
+            url: stand_in.url.clone(),
+        }
+    }
+
+    fn cache_path(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!(
+            "zotero-to-obsidian-api-{}-{}.json",
+            name,
+            process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn item<'a>(zotero_data: &'a ZoteroData, title: &str) -> &'a Item {
+        zotero_data
+            .items
+            .iter()
+            .find(|item| item.title.as_deref() == Some(title))
+            .unwrap_or_else(|| panic!("no item titled {}", title))
+    }
+
+    fn titles(zotero_data: &ZoteroData) -> Vec<String> {
+        let mut titles: Vec<String> = zotero_data
+            .items
+            .iter()
+            .filter_map(|item| item.title.clone())
+            .collect();
+        titles.sort();
+        titles
+    }
+
+    #[test]
+    fn pages_through_the_whole_library() {
+        let stand_in = StandIn::start(full_fetch_routes());
+        let zotero_data = read_with(
+            &UreqClient::new(),
+            &config(&stand_in, &["users/475425"]),
+            &cache_path("pages"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            stand_in.targets(),
+            [
+                "/users/475425/items?format=json&limit=100&start=0",
+                "/users/475425/items?format=json&limit=100&start=3"
+            ]
+        );
+        // the trashed web page and the child items are not items of their own
+        assert_eq!(
+            titles(&zotero_data),
+            ["How to Take Smart Notes", "Salvaging Pascal's Wager"]
+        );
+
+        let request = &stand_in.requests.lock().unwrap()[0];
+        assert_eq!(request.headers["zotero-api-version"], "3");
+        assert_eq!(request.headers["zotero-api-key"], "test-key");
+        assert!(!request.headers.contains_key("if-modified-since-version"));
+    }
+
+    #[test]
+    fn maps_items_onto_the_library_model() {
+        let stand_in = StandIn::start(full_fetch_routes());
+        let zotero_data = read_with(
+            &UreqClient::new(),
+            &config(&stand_in, &["users/475425"]),
+            &cache_path("maps"),
+        )
+        .unwrap();
+
+        let paper = item(&zotero_data, "Salvaging Pascal's Wager");
+        assert_eq!(paper.id, "jackson2019");
+        assert_eq!(paper.item_type, "journalArticle");
+        assert_eq!(paper.doi.as_deref(), Some("10.5840/pc201921110"));
+        assert_eq!(paper.journal.as_deref(), Some("Philosophia Christi"));
+        assert_eq!(paper.published_date.as_deref(), Some("2019-03-05"));
+        assert_eq!(paper.creators.len(), 2);
+        assert_eq!(paper.creators[0].last_name.as_deref(), Some("Jackson"));
+        assert_eq!(
+            paper.uri.as_deref(),
+            Some("http://zotero.org/users/475425/items/PAPER001")
+        );
+        assert_eq!(
+            paper.select.as_deref(),
+            Some("zotero://select/library/items/PAPER001")
+        );
+        let tags: Vec<&str> = paper.tags.iter().map(|tag| tag.tag.as_str()).collect();
+        assert_eq!(tags, ["philosophy", "religion"]);
+        assert_eq!(
+            paper.attachments[0].url.as_deref(),
+            Some("zotero://open-pdf/library/items/PDF00001")
+        );
+
+        let book = item(&zotero_data, "How to Take Smart Notes");
+        // no citekey anywhere, generated once preferences are applied
+        assert_eq!(book.id, "");
+        assert_eq!(book.notes.len(), 1);
+        assert_eq!(book.notes[0].content, "<p>Write <b>permanent</b> notes</p>");
+    }
+
+    #[test]
+    fn skips_the_fetch_when_nothing_changed() {
+        let cache = cache_path("unchanged");
+        let stand_in = StandIn::start(full_fetch_routes());
+        read_with(
+            &UreqClient::new(),
+            &config(&stand_in, &["users/475425"]),
+            &cache,
+        )
+        .unwrap();
+
+        let unchanged = StandIn::start(vec![Route {
+            target: "/users/475425/items?format=json&limit=100&start=0&since=20",
+            status: 304,
+            headers: vec![("Last-Modified-Version", "20")],
+            body: "",
+        }]);
+        let zotero_data = read_with(
+            &UreqClient::new(),
+            &config(&unchanged, &["users/475425"]),
+            &cache,
+        )
+        .unwrap();
+
+        assert_eq!(
+            unchanged.targets(),
+            ["/users/475425/items?format=json&limit=100&start=0&since=20"]
+        );
+        assert_eq!(
+            unchanged.requests.lock().unwrap()[0].headers["if-modified-since-version"],
+            "20"
+        );
+        assert_eq!(
+            titles(&zotero_data),
+            ["How to Take Smart Notes", "Salvaging Pascal's Wager"]
+        );
+    }
+
+    #[test]
+    fn merges_changes_and_deletions_since_the_last_fetch() {
+        let cache = cache_path("incremental");
+        let stand_in = StandIn::start(full_fetch_routes());
+        read_with(
+            &UreqClient::new(),
+            &config(&stand_in, &["users/475425"]),
+            &cache,
+        )
+        .unwrap();
+
+        let changed = StandIn::start(vec![
+            Route {
+                target: "/users/475425/items?format=json&limit=100&start=0&since=20",
+                status: 200,
+                headers: vec![("Total-Results", "1"), ("Last-Modified-Version", "25")],
+                body: ITEMS_SINCE,
+            },
+            Route {
+                target: "/users/475425/deleted?since=20",
+                status: 200,
+                headers: vec![("Last-Modified-Version", "25")],
+                body: DELETED_SINCE,
+            },
+        ]);
+        let zotero_data = read_with(
+            &UreqClient::new(),
+            &config(&changed, &["users/475425"]),
+            &cache,
+        )
+        .unwrap();
+
+        assert_eq!(titles(&zotero_data), ["Salvaging Pascal's Wager: A Reply"]);
+        assert_eq!(Cache::load(&cache).libraries["users/475425"].version, 25);
+    }
+
+    #[test]
+    fn reads_group_libraries() {
+        let stand_in = StandIn::start(vec![Route {
+            target: "/groups/12345/items?format=json&limit=100&start=0",
+            status: 200,
+            headers: vec![("Total-Results", "1"), ("Last-Modified-Version", "7")],
+            body: GROUP_ITEMS,
+        }]);
+        let zotero_data = read_with(
+            &UreqClient::new(),
+            &config(&stand_in, &["groups/12345"]),
+            &cache_path("group"),
+        )
+        .unwrap();
+
+        let page = item(&zotero_data, "Shared reading list");
+        assert_eq!(page.id, "readingList");
+        assert_eq!(
+            page.uri.as_deref(),
+            Some("http://zotero.org/groups/12345/items/GROUP001")
+        );
+        assert_eq!(
+            page.select.as_deref(),
+            Some("zotero://select/groups/12345/items/GROUP001")
+        );
+    }
+
+    #[test]
+    fn reports_api_errors() {
+        let stand_in = StandIn::start(vec![Route {
+            target: "/users/475425/items?format=json&limit=100&start=0",
+            status: 403,
+            headers: Vec::new(),
+            body: "Forbidden",
+        }]);
+        let error = read_with(
+            &UreqClient::new(),
+            &config(&stand_in, &["users/475425"]),
+            &cache_path("error"),
+        )
+        .unwrap_err();
+        assert!(error.to_string().ends_with("HTTP 403: Forbidden"));
+    }
+}
@@ -0,0 +1,824 @@
+use rusqlite::{Connection, OpenFlags};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::citekey;
+use super::error::{Error, Result};
+use super::json_parser::{Attachment, Collection, Creator, Item, Note, Tag, ZoteroData};
+
+// item types that hang off other items rather than being references of their own
+const CHILD_ITEM_TYPES: &[&str] = &["attachment", "note", "annotation"];
+
+// Zotero's link modes for attachments
+const LINK_MODE_LINKED_URL: i64 = 3;
+
+// numbers the copies so that reads running side by side don't share one
+static COPIES: AtomicUsize = AtomicUsize::new(0);
+
+pub fn is_sqlite(contents: &[u8]) -> bool {
+    contents.starts_with(b"SQLite format 3\0")
+}
+
+// Rebuilds the library from Zotero's own database, for people without a Better BibTeX
+// export. Zotero keeps the database locked while it runs, so we read a copy of it.
+pub fn read(path: &Path) -> Result<ZoteroData> {
+    let copy = env::temp_dir().join(format!(
+        "zotero-to-obsidian-{}-{}.sqlite",
+        process::id(),
+        COPIES.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::copy(path, &copy).map_err(|error| Error::io(path, error))?;
+
+    let data_directory = path.parent().unwrap_or_else(|| Path::new("."));
+    let result = Connection::open_with_flags(&copy, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .and_then(|connection| ZoteroDatabase::new(&connection, data_directory).read());
+    let _ = fs::remove_file(&copy);
+
+    let mut zotero_data = result.map_err(|source| Error::Database {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    apply_better_bibtex_keys(&mut zotero_data, data_directory);
+    Ok(zotero_data)
+}
+
+struct ZoteroDatabase<'a> {
+    connection: &'a Connection,
+    data_directory: &'a Path,
+}
+
+// what Zotero knows about an item row besides its fields
+struct ItemRow {
+    id: i64,
+    key: String,
+    library: i64,
+    item_type: String,
+}
+
+impl<'a> ZoteroDatabase<'a> {
+    fn new(connection: &'a Connection, data_directory: &'a Path) -> ZoteroDatabase<'a> {
+        ZoteroDatabase {
+            connection,
+            data_directory,
+        }
+    }
+
+    fn read(&self) -> rusqlite::Result<ZoteroData> {
+        let libraries = self.libraries()?;
+        let mut fields = self.fields()?;
+        let mut creators = self.creators()?;
+        let mut tags = self.tags()?;
+        let mut notes = self.notes(&libraries)?;
+        let mut attachments = self.attachments(&fields)?;
+        let mut relations = self.relations()?;
+
+        let items = self
+            .items()?
+            .into_iter()
+            .filter(|row| !CHILD_ITEM_TYPES.contains(&row.item_type.as_str()))
+            .map(|row| {
+                let fields = fields.remove(&row.id).unwrap_or_default();
+                let field =
+                    |name: &str| fields.get(name).cloned().filter(|value| !value.is_empty());
+                let (uri, select) = libraries
+                    .get(&row.library)
+                    .map(|(uri, select)| {
+                        (
+                            format!("{}/items/{}", uri, row.key),
+                            format!("{}/items/{}", select, row.key),
+                        )
+                    })
+                    .unzip();
+
+                Item {
+                    id: field("extra")
+                        .and_then(|extra| citekey::pinned(&extra))
+                        .unwrap_or_default(),
+                    item_type: row.item_type.clone(),
+                    doi: field("DOI"),
+                    title: field("title")
+                        .or_else(|| field("caseName"))
+                        .or_else(|| field("nameOfAct")),
+                    library_catalog: field("libraryCatalog"),
+                    url: field("url"),
+                    creators: creators.remove(&row.id).unwrap_or_default(),
+                    isbn: field("ISBN"),
+                    short_title: field("shortTitle"),
+                    select,
+                    uri,
+                    published_date: field("date")
+                        .or_else(|| field("dateDecided"))
+                        .or_else(|| field("dateEnacted"))
+                        .map(|date| date_from(&date)),
+                    journal: field("publicationTitle"),
+                    book_title: field("bookTitle"),
+                    proceedings_title: field("proceedingsTitle"),
+                    conference_name: field("conferenceName"),
+                    publisher: field("publisher"),
+                    place: field("place"),
+                    pages: field("pages"),
+                    university: field("university"),
+                    thesis_type: field("thesisType"),
+                    institution: field("institution"),
+                    report_number: field("reportNumber"),
+                    report_type: field("reportType"),
+                    series_title: field("seriesTitle"),
+                    episode_number: field("episodeNumber"),
+                    running_time: field("runningTime"),
+                    section: field("section"),
+                    edition: field("edition"),
+                    archive_id: field("archiveID"),
+                    repository: field("repository"),
+                    extra: field("extra"),
+                    court: field("court"),
+                    reporter: field("reporter"),
+                    reporter_volume: field("reporterVolume"),
+                    first_page: field("firstPage"),
+                    docket_number: field("docketNumber"),
+                    jurisdiction: field("jurisdiction"),
+                    code: field("code"),
+                    code_number: field("codeNumber"),
+                    code_volume: field("codeVolume"),
+                    code_pages: field("codePages"),
+                    public_law_number: field("publicLawNumber"),
+                    bill_number: field("billNumber"),
+                    legislative_body: field("legislativeBody"),
+                    session: field("session"),
+                    version_number: field("versionNumber"),
+                    programming_language: field("programmingLanguage"),
+                    system: field("system"),
+                    company: field("company"),
+                    rights: field("rights"),
+                    relations: relations.remove(&row.id).unwrap_or_default(),
+                    tags: tags.remove(&row.id).unwrap_or_default(),
+                    notes: notes.remove(&row.id).unwrap_or_default(),
+                    attachments: attachments.remove(&row.id).unwrap_or_default(),
+                }
+            })
+            .collect();
+
+        Ok(ZoteroData {
+            collections: self.collections()?,
+            config: None,
+            items,
+        })
+    }
+
+    // the zotero.org URI and zotero:// select link every item key of a library hangs off
+    fn libraries(&self) -> rusqlite::Result<HashMap<i64, (String, String)>> {
+        let user = self
+            .setting("account", "userID")?
+            .or(self
+                .setting("account", "localUserKey")?
+                .map(|key| format!("local/{}", key)))
+            .unwrap_or_else(|| "local".to_string());
+
+        let mut libraries: HashMap<i64, (String, String)> = self
+            .connection
+            .prepare("SELECT libraryID FROM libraries WHERE type = 'user'")?
+            .query_map([], |row| row.get::<_, i64>(0))?
+            .collect::<rusqlite::Result<Vec<i64>>>()?
+            .into_iter()
+            .map(|library| {
+                (
+                    library,
+                    (
+                        format!("http://zotero.org/users/{}", user),
+                        "zotero://select/library".to_string(),
+                    ),
+                )
+            })
+            .collect();
+
+        let mut groups = self
+            .connection
+            .prepare("SELECT libraryID, groupID FROM groups")?;
+        for group in
+            groups.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))?
+        {
+            let (library, group) = group?;
+            libraries.insert(
+                library,
+                (
+                    format!("http://zotero.org/groups/{}", group),
+                    format!("zotero://select/groups/{}", group),
+                ),
+            );
+        }
+        Ok(libraries)
+    }
+
+    fn setting(&self, setting: &str, key: &str) -> rusqlite::Result<Option<String>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT value FROM settings WHERE setting = ?1 AND key = ?2")?;
+        let mut rows = statement.query([setting, key])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get_ref(0)?.as_str().map_or_else(
+                |_| {
+                    row.get::<_, i64>(0)
+                        .map(|value| value.to_string())
+                        .unwrap_or_default()
+                },
+                str::to_string,
+            ))),
+            None => Ok(None),
+        }
+    }
+
+    fn items(&self) -> rusqlite::Result<Vec<ItemRow>> {
+        self.connection
+            .prepare(
+                "SELECT items.itemID, items.key, items.libraryID, itemTypes.typeName
+                 FROM items JOIN itemTypes USING (itemTypeID)
+                 WHERE items.itemID NOT IN (SELECT itemID FROM deletedItems)
+                 ORDER BY items.itemID",
+            )?
+            .query_map([], |row| {
+                Ok(ItemRow {
+                    id: row.get(0)?,
+                    key: row.get(1)?,
+                    library: row.get(2)?,
+                    item_type: row.get(3)?,
+                })
+            })?
+            .collect()
+    }
+
+    fn fields(&self) -> rusqlite::Result<HashMap<i64, HashMap<String, String>>> {
+        let mut fields: HashMap<i64, HashMap<String, String>> = HashMap::new();
+        let mut statement = self.connection.prepare(
+            "SELECT itemData.itemID, fields.fieldName, itemDataValues.value
+             FROM itemData
+             JOIN fields USING (fieldID)
+             JOIN itemDataValues USING (valueID)",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                // most values are text, but Zotero stores numbers as numbers
+                row.get_ref(2)?.as_str().map_or_else(
+                    |_| {
+                        row.get::<_, i64>(2)
+                            .map(|value| value.to_string())
+                            .unwrap_or_default()
+                    },
+                    str::to_string,
+                ),
+            ))
+        })?;
+        for row in rows {
+            let (item, field, value) = row?;
+            fields.entry(item).or_default().insert(field, value);
+        }
+        Ok(fields)
+    }
+
+    fn creators(&self) -> rusqlite::Result<HashMap<i64, Vec<Creator>>> {
+        let mut creators: HashMap<i64, Vec<Creator>> = HashMap::new();
+        let mut statement = self.connection.prepare(
+            "SELECT itemCreators.itemID, creators.firstName, creators.lastName,
+                    creators.fieldMode, creatorTypes.creatorType
+             FROM itemCreators
+             JOIN creators USING (creatorID)
+             JOIN creatorTypes USING (creatorTypeID)
+             ORDER BY itemCreators.itemID, itemCreators.orderIndex",
+        )?;
+        let rows = statement.query_map([], |row| {
+            let first_name: Option<String> = row.get(1)?;
+            let last_name: Option<String> = row.get(2)?;
+            let single_field = row.get::<_, Option<i64>>(3)?.unwrap_or(0) == 1;
+            Ok((
+                row.get::<_, i64>(0)?,
+                // a single field creator, e.g. an organisation, only has a last name
+                if single_field {
+                    Creator {
+                        creator_type: row.get(4)?,
+                        first_name: None,
+                        last_name: None,
+                        name: last_name,
+                    }
+                } else {
+                    Creator {
+                        creator_type: row.get(4)?,
+                        first_name: first_name.filter(|first| !first.is_empty()),
+                        last_name,
+                        name: None,
+                    }
+                },
+            ))
+        })?;
+        for row in rows {
+            let (item, creator) = row?;
+            creators.entry(item).or_default().push(creator);
+        }
+        Ok(creators)
+    }
+
+    fn tags(&self) -> rusqlite::Result<HashMap<i64, Vec<Tag>>> {
+        let mut tags: HashMap<i64, Vec<Tag>> = HashMap::new();
+        let mut statement = self.connection.prepare(
+            "SELECT itemTags.itemID, tags.name
+             FROM itemTags JOIN tags USING (tagID)
+             ORDER BY itemTags.itemID, tags.name",
+        )?;
+        let rows = statement.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get(1)?)))?;
+        for row in rows {
+            let (item, tag) = row?;
+            tags.entry(item).or_default().push(Tag { tag });
+        }
+        Ok(tags)
+    }
+
+    fn relations(&self) -> rusqlite::Result<HashMap<i64, Vec<String>>> {
+        let mut relations: HashMap<i64, Vec<String>> = HashMap::new();
+        let mut statement = self
+            .connection
+            .prepare("SELECT itemID, object FROM itemRelations ORDER BY itemID, object")?;
+        let rows = statement.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get(1)?)))?;
+        for row in rows {
+            let (item, uri) = row?;
+            relations.entry(item).or_default().push(uri);
+        }
+        Ok(relations)
+    }
+
+    fn notes(
+        &self,
+        libraries: &HashMap<i64, (String, String)>,
+    ) -> rusqlite::Result<HashMap<i64, Vec<Note>>> {
+        let mut notes: HashMap<i64, Vec<Note>> = HashMap::new();
+        let mut statement = self.connection.prepare(
+            "SELECT itemNotes.parentItemID, itemNotes.note, items.key, items.libraryID,
+                    items.dateAdded, items.dateModified
+             FROM itemNotes JOIN items USING (itemID)
+             WHERE itemNotes.parentItemID IS NOT NULL
+               AND itemNotes.itemID NOT IN (SELECT itemID FROM deletedItems)
+             ORDER BY itemNotes.itemID",
+        )?;
+        let rows = statement.query_map([], |row| {
+            let key: String = row.get(2)?;
+            let library: i64 = row.get(3)?;
+            Ok((
+                row.get::<_, i64>(0)?,
+                Note {
+                    content: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    uri: libraries
+                        .get(&library)
+                        .map(|(uri, _)| format!("{}/items/{}", uri, key))
+                        .unwrap_or_default(),
+                    date_added: row.get(4)?,
+                    date_modified: row.get(5)?,
+                },
+            ))
+        })?;
+        for row in rows {
+            let (parent, note) = row?;
+            notes.entry(parent).or_default().push(note);
+        }
+        Ok(notes)
+    }
+
+    // Stored files live in `storage/<attachment key>/` next to the database, linked files
+    // keep the path they were linked from
+    fn attachments(
+        &self,
+        fields: &HashMap<i64, HashMap<String, String>>,
+    ) -> rusqlite::Result<HashMap<i64, Vec<Attachment>>> {
+        let mut attachments: HashMap<i64, Vec<Attachment>> = HashMap::new();
+        let mut statement = self.connection.prepare(
+            "SELECT itemAttachments.itemID, itemAttachments.parentItemID,
+                    itemAttachments.linkMode, itemAttachments.path, items.key
+             FROM itemAttachments JOIN items USING (itemID)
+             WHERE itemAttachments.parentItemID IS NOT NULL
+               AND itemAttachments.itemID NOT IN (SELECT itemID FROM deletedItems)
+             ORDER BY itemAttachments.itemID",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, Option<i64>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })?;
+        for row in rows {
+            let (id, parent, link_mode, path, key) = row?;
+            let attachment_fields = fields.get(&id);
+            let field = |name: &str| {
+                attachment_fields
+                    .and_then(|fields| fields.get(name))
+                    .cloned()
+            };
+            let path = match path {
+                Some(path) => match path.strip_prefix("storage:") {
+                    Some(file) => Some(self.storage_path(&key, file)),
+                    None => Some(path),
+                },
+                None => None,
+            };
+            let url = match link_mode {
+                Some(LINK_MODE_LINKED_URL) => field("url"),
+                _ => None,
+            };
+            if path.is_none() && url.is_none() {
+                continue;
+            }
+            attachments.entry(parent).or_default().push(Attachment {
+                title: field("title"),
+                path,
+                url,
+            });
+        }
+        Ok(attachments)
+    }
+
+    fn storage_path(&self, key: &str, file: &str) -> String {
+        let directory: PathBuf = self.data_directory.join("storage").join(key);
+        directory.join(file).display().to_string()
+    }
+
+    // keyed by collection key like Better BibTeX's export, with parents and children
+    // referring to each other by key
+    fn collections(&self) -> rusqlite::Result<HashMap<String, Collection>> {
+        let mut rows: Vec<(i64, String, String, Option<i64>)> = self
+            .connection
+            .prepare(
+                "SELECT collectionID, key, collectionName, parentCollectionID
+                 FROM collections ORDER BY collectionID",
+            )?
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        rows.retain(|(_, key, _, _)| !key.is_empty());
+        let keys: HashMap<i64, String> = rows
+            .iter()
+            .map(|(id, key, _, _)| (*id, key.clone()))
+            .collect();
+
+        let mut items: HashMap<i64, Vec<i32>> = HashMap::new();
+        let mut statement = self.connection.prepare(
+            "SELECT collectionID, itemID FROM collectionItems
+             WHERE itemID NOT IN (SELECT itemID FROM deletedItems)
+             ORDER BY collectionID, orderIndex",
+        )?;
+        for row in
+            statement.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i32>(1)?)))?
+        {
+            let (collection, item) = row?;
+            items.entry(collection).or_default().push(item);
+        }
+
+        Ok(rows
+            .iter()
+            .map(|(id, key, name, parent)| {
+                let children = rows
+                    .iter()
+                    .filter(|(_, _, _, child_parent)| child_parent == &Some(*id))
+                    .map(|(_, child_key, _, _)| child_key.clone())
+                    .collect();
+                (
+                    key.clone(),
+                    Collection {
+                        collections: children,
+                        items: items.remove(id).unwrap_or_default(),
+                        key: key.clone(),
+                        name: name.clone(),
+                        parent: parent.and_then(|parent| keys.get(&parent).cloned()),
+                    },
+                )
+            })
+            .collect())
+    }
+}
+
+// Better BibTeX keeps the keys it generated in its own database next to Zotero's. When
+// it is there and readable its keys win over ours; without it citekeys are generated.
+fn apply_better_bibtex_keys(zotero_data: &mut ZoteroData, data_directory: &Path) {
+    let path = data_directory.join("better-bibtex.sqlite");
+    if !path.exists() {
+        return;
+    }
+    let keys: HashMap<String, String> =
+        match Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY).and_then(
+            |connection| {
+                connection
+                    .prepare("SELECT itemKey, citationKey FROM citationkey")?
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect()
+            },
+        ) {
+            Ok(keys) => keys,
+            Err(_) => return,
+        };
+
+    zotero_data.items.iter_mut().for_each(|item| {
+        let key = item.uri.as_deref().and_then(|uri| uri.rsplit('/').next());
+        if let Some(citekey) = key.and_then(|key| keys.get(key)) {
+            if item.id.is_empty() {
+                item.id = citekey.clone();
+            }
+        }
+    });
+}
+
+// Zotero stores dates as `2019-03-05 March 5, 2019`, the SQL form with zeroes for the
+// parts it couldn't make out followed by the date as typed
+fn date_from(date: &str) -> String {
+    let (sql, typed) = date.split_once(' ').unwrap_or((date, date));
+    let parts: Vec<&str> = sql.split('-').collect();
+    let is_zero = |part: &&str| part.chars().all(|c| c == '0');
+    if parts.len() != 3
+        || parts.iter().any(|part| part.parse::<u32>().is_err())
+        || is_zero(&parts[0])
+    {
+        return typed.to_string();
+    }
+    parts
+        .into_iter()
+        .take_while(|part| !is_zero(part))
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the parts of Zotero's schema we read, with a few items in them
+    const FIXTURE: &str = "
+        CREATE TABLE libraries (libraryID INTEGER PRIMARY KEY, type TEXT NOT NULL);
+        CREATE TABLE groups (groupID INTEGER PRIMARY KEY, libraryID INT NOT NULL, name TEXT);
+        CREATE TABLE settings (setting TEXT, key TEXT, value, PRIMARY KEY (setting, key));
+        CREATE TABLE itemTypes (itemTypeID INTEGER PRIMARY KEY, typeName TEXT);
+        CREATE TABLE items (itemID INTEGER PRIMARY KEY, itemTypeID INT NOT NULL,
+            dateAdded TEXT NOT NULL, dateModified TEXT NOT NULL, libraryID INT NOT NULL,
+            key TEXT NOT NULL);
+        CREATE TABLE deletedItems (itemID INTEGER PRIMARY KEY, dateDeleted TEXT);
+        CREATE TABLE fields (fieldID INTEGER PRIMARY KEY, fieldName TEXT);
+        CREATE TABLE itemDataValues (valueID INTEGER PRIMARY KEY, value UNIQUE);
+        CREATE TABLE itemData (itemID INT, fieldID INT, valueID INT, PRIMARY KEY (itemID, fieldID));
+        CREATE TABLE creators (creatorID INTEGER PRIMARY KEY, firstName TEXT, lastName TEXT,
+            fieldMode INT);
+        CREATE TABLE creatorTypes (creatorTypeID INTEGER PRIMARY KEY, creatorType TEXT);
+        CREATE TABLE itemCreators (itemID INT, creatorID INT, creatorTypeID INT, orderIndex INT);
+        CREATE TABLE tags (tagID INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE);
+        CREATE TABLE itemTags (itemID INT, tagID INT, type INT);
+        CREATE TABLE itemNotes (itemID INTEGER PRIMARY KEY, parentItemID INT, note TEXT,
+            title TEXT);
+        CREATE TABLE itemAttachments (itemID INTEGER PRIMARY KEY, parentItemID INT,
+            linkMode INT, contentType TEXT, path TEXT);
+        CREATE TABLE collections (collectionID INTEGER PRIMARY KEY, collectionName TEXT,
+            parentCollectionID INT, libraryID INT, key TEXT);
+        CREATE TABLE collectionItems (collectionID INT, itemID INT, orderIndex INT);
+        CREATE TABLE itemRelations (itemID INT, predicateID INT, object TEXT,
+            PRIMARY KEY (itemID, predicateID, object));
+
+        INSERT INTO libraries VALUES (1, 'user'), (2, 'group');
+        INSERT INTO groups VALUES (4711, 2, 'Reading group');
+        INSERT INTO settings VALUES ('account', 'userID', 8012208);
+        INSERT INTO itemTypes VALUES (1, 'journalArticle'), (2, 'book'), (3, 'note'),
+            (4, 'attachment'), (5, 'webpage');
+        INSERT INTO fields VALUES (1, 'title'), (2, 'DOI'), (3, 'date'), (4, 'publicationTitle'),
+            (5, 'extra'), (6, 'ISBN'), (7, 'shortTitle'), (8, 'url');
+
+        INSERT INTO items VALUES
+            (1, 1, '2021-01-01 10:00:00', '2021-01-02 10:00:00', 1, 'PAPER001'),
+            (2, 2, '2021-01-01 10:00:00', '2021-01-01 10:00:00', 1, 'BOOK0001'),
+            (3, 3, '2021-01-03 10:00:00', '2021-01-03 10:00:00', 1, 'NOTE0001'),
+            (4, 4, '2021-01-03 10:00:00', '2021-01-03 10:00:00', 1, 'PDF00001'),
+            (5, 4, '2021-01-03 10:00:00', '2021-01-03 10:00:00', 1, 'LINK0001'),
+            (6, 5, '2021-01-04 10:00:00', '2021-01-04 10:00:00', 1, 'GONE0001'),
+            (7, 5, '2021-01-05 10:00:00', '2021-01-05 10:00:00', 2, 'GROUP001');
+        INSERT INTO deletedItems VALUES (6, '2021-02-01 10:00:00');
+
+        INSERT INTO itemDataValues VALUES (1, 'Salvaging Pascal''s Wager'),
+            (2, '10.5840/pc201921110'), (3, '2019-03-05 March 5, 2019'),
+            (4, 'Philosophia Christi'), (5, 'Citation Key: jackson2019'),
+            (6, 'How to Take Smart Notes'), (7, '9781542866507'), (8, 'Smart Notes'),
+            (9, '2017-00-00 2017'), (10, 'Full Text PDF'), (11, 'Publisher page'),
+            (12, 'https://example.com/book'), (13, 'Deleted page'), (14, 'A group page');
+        INSERT INTO itemData VALUES (1, 1, 1), (1, 2, 2), (1, 3, 3), (1, 4, 4), (1, 5, 5),
+            (2, 1, 6), (2, 6, 7), (2, 7, 8), (2, 3, 9),
+            (4, 1, 10), (5, 1, 11), (5, 8, 12), (6, 1, 13), (7, 1, 14);
+
+        INSERT INTO creators VALUES (1, 'Elizabeth', 'Jackson', 0), (2, 'Andrew', 'Rogers', 0),
+            (3, '', 'Evangelical Philosophical Society', 1), (4, 'Sönke', 'Ahrens', 0);
+        INSERT INTO creatorTypes VALUES (1, 'author'), (2, 'editor');
+        INSERT INTO itemCreators VALUES (1, 2, 1, 1), (1, 1, 1, 0), (1, 3, 2, 2), (2, 4, 1, 0);
+
+        INSERT INTO tags VALUES (1, 'philosophy'), (2, 'religion');
+        INSERT INTO itemTags VALUES (1, 1, 0), (1, 2, 0);
+
+        INSERT INTO itemNotes VALUES (3, 2, '<p>Write <b>permanent</b> notes</p>', 'Write');
+        INSERT INTO itemAttachments VALUES (4, 1, 1, 'application/pdf', 'storage:paper.pdf'),
+            (5, 2, 3, 'text/html', NULL);
+
+        INSERT INTO collections VALUES (1, 'Philosophy', NULL, 1, 'COLL0001'),
+            (2, 'Religion', 1, 1, 'COLL0002');
+        INSERT INTO collectionItems VALUES (1, 2, 0), (2, 1, 0), (2, 6, 1);
+        INSERT INTO itemRelations VALUES (1, 1, 'http://zotero.org/users/8012208/items/BOOK0001');
+    ";
+
+    fn fixture(name: &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!(
+            "zotero-to-obsidian-test-{}-{}",
+            name,
+            process::id()
+        ));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("zotero.sqlite");
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch(FIXTURE)
+            .unwrap();
+        path
+    }
+
+    fn item<'a>(zotero_data: &'a ZoteroData, title: &str) -> &'a Item {
+        zotero_data
+            .items
+            .iter()
+            .find(|item| item.title.as_deref() == Some(title))
+            .unwrap_or_else(|| panic!("no item titled {}", title))
+    }
+
+    #[test]
+    fn recognises_database_files() {
+        let path = fixture("recognise");
+        assert!(is_sqlite(&fs::read(&path).unwrap()));
+        assert!(!is_sqlite(b"{\"items\": []}"));
+    }
+
+    #[test]
+    fn reads_top_level_items_only() {
+        let zotero_data = read(&fixture("items")).unwrap();
+        let mut titles: Vec<&str> = zotero_data
+            .items
+            .iter()
+            .filter_map(|item| item.title.as_deref())
+            .collect();
+        titles.sort();
+        assert_eq!(
+            titles,
+            [
+                "A group page",
+                "How to Take Smart Notes",
+                "Salvaging Pascal's Wager"
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_fields_and_creators() {
+        let zotero_data = read(&fixture("fields")).unwrap();
+        let paper = item(&zotero_data, "Salvaging Pascal's Wager");
+        assert_eq!(paper.item_type, "journalArticle");
+        assert_eq!(paper.id, "jackson2019");
+        assert_eq!(paper.doi.as_deref(), Some("10.5840/pc201921110"));
+        assert_eq!(paper.journal.as_deref(), Some("Philosophia Christi"));
+        assert_eq!(paper.published_date.as_deref(), Some("2019-03-05"));
+
+        let names: Vec<(Option<&str>, Option<&str>, Option<&str>)> = paper
+            .creators
+            .iter()
+            .map(|creator| {
+                (
+                    creator.first_name.as_deref(),
+                    creator.last_name.as_deref(),
+                    creator.name.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                (Some("Elizabeth"), Some("Jackson"), None),
+                (Some("Andrew"), Some("Rogers"), None),
+                (None, None, Some("Evangelical Philosophical Society")),
+            ]
+        );
+        assert_eq!(paper.creators[2].creator_type.as_deref(), Some("editor"));
+
+        let book = item(&zotero_data, "How to Take Smart Notes");
+        assert_eq!(book.isbn.as_deref(), Some("9781542866507"));
+        assert_eq!(book.short_title.as_deref(), Some("Smart Notes"));
+        assert_eq!(book.published_date.as_deref(), Some("2017"));
+        // no pinned key and no Better BibTeX database, the key is generated later
+        assert_eq!(book.id, "");
+    }
+
+    #[test]
+    fn reads_tags_notes_and_attachments() {
+        let path = fixture("children");
+        let zotero_data = read(&path).unwrap();
+
+        let paper = item(&zotero_data, "Salvaging Pascal's Wager");
+        let tags: Vec<&str> = paper.tags.iter().map(|tag| tag.tag.as_str()).collect();
+        assert_eq!(tags, ["philosophy", "religion"]);
+        assert_eq!(paper.attachments.len(), 1);
+        assert_eq!(paper.attachments[0].title.as_deref(), Some("Full Text PDF"));
+        assert_eq!(
+            paper.attachments[0].path.as_deref().map(PathBuf::from),
+            Some(path.parent().unwrap().join("storage/PDF00001/paper.pdf"))
+        );
+
+        let book = item(&zotero_data, "How to Take Smart Notes");
+        assert_eq!(book.notes.len(), 1);
+        assert_eq!(book.notes[0].content, "<p>Write <b>permanent</b> notes</p>");
+        assert_eq!(
+            book.notes[0].uri,
+            "http://zotero.org/users/8012208/items/NOTE0001"
+        );
+        assert_eq!(
+            book.attachments[0].url.as_deref(),
+            Some("https://example.com/book")
+        );
+    }
+
+    #[test]
+    fn links_items_back_to_their_library() {
+        let zotero_data = read(&fixture("links")).unwrap();
+
+        let paper = item(&zotero_data, "Salvaging Pascal's Wager");
+        assert_eq!(
+            paper.uri.as_deref(),
+            Some("http://zotero.org/users/8012208/items/PAPER001")
+        );
+        assert_eq!(
+            paper.select.as_deref(),
+            Some("zotero://select/library/items/PAPER001")
+        );
+
+        let group_page = item(&zotero_data, "A group page");
+        assert_eq!(
+            group_page.uri.as_deref(),
+            Some("http://zotero.org/groups/4711/items/GROUP001")
+        );
+        assert_eq!(
+            group_page.select.as_deref(),
+            Some("zotero://select/groups/4711/items/GROUP001")
+        );
+    }
+
+    #[test]
+    fn reads_related_items() {
+        let zotero_data = read(&fixture("relations")).unwrap();
+
+        let paper = item(&zotero_data, "Salvaging Pascal's Wager");
+        assert_eq!(
+            paper.relations,
+            ["http://zotero.org/users/8012208/items/BOOK0001"]
+        );
+        assert!(item(&zotero_data, "How to Take Smart Notes")
+            .relations
+            .is_empty());
+    }
+
+    #[test]
+    fn reads_collections() {
+        let zotero_data = read(&fixture("collections")).unwrap();
+        let philosophy = &zotero_data.collections["COLL0001"];
+        assert_eq!(philosophy.name, "Philosophy");
+        assert_eq!(philosophy.collections, ["COLL0002"]);
+        assert_eq!(philosophy.items, [2]);
+
+        let religion = &zotero_data.collections["COLL0002"];
+        assert_eq!(religion.parent.as_deref(), Some("COLL0001"));
+        // the deleted item is left out
+        assert_eq!(religion.items, [1]);
+    }
+
+    #[test]
+    fn prefers_better_bibtex_keys() {
+        let path = fixture("better-bibtex");
+        Connection::open(path.parent().unwrap().join("better-bibtex.sqlite"))
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE citationkey (itemID INT, itemKey TEXT, libraryID INT,
+                     citationKey TEXT, pinned INT);
+                 INSERT INTO citationkey VALUES (2, 'BOOK0001', 1, 'ahrens2017', 0);",
+            )
+            .unwrap();
+        let zotero_data = read(&path).unwrap();
+        assert_eq!(
+            item(&zotero_data, "How to Take Smart Notes").id,
+            "ahrens2017"
+        );
+    }
+
+    #[test]
+    fn keeps_dates_zotero_could_not_parse() {
+        assert_eq!(date_from("2019-03-05 March 5, 2019"), "2019-03-05");
+        assert_eq!(date_from("2019-03-00 March 2019"), "2019-03");
+        assert_eq!(date_from("0000-00-00 Spring"), "Spring");
+    }
+}